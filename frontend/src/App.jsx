@@ -78,6 +78,7 @@ const App = () => {
               description: svc.description,
               enabled: svc.enabled ? "enabled" : "disabled",
               active_status: svc.active_status,
+              in_maintenance: svc.in_maintenance,
               last_check: svc.last_check,
             },
           ],
@@ -91,6 +92,7 @@ const App = () => {
           description: svc.description,
           enabled: svc.enabled ? "enabled" : "disabled",
           active_status: svc.active_status,
+          in_maintenance: svc.in_maintenance,
           last_check: svc.last_check,
         });
       }
@@ -187,19 +187,25 @@ const Service = ({ hostname, service, doLoadStatuses }) => {
   // START, STOP, RESTART SERVICE
   /****************************************************************************/
   async function doServiceAction(hostname, service, action) {
-    const url = `${URL}/api/systemctl/${action}?host=${hostname}&service=${service}`;
+    const url = `${URL}/api/systemctl/${action}`;
 
     // TODO: handle no output from server. Look in stdout and stderr.
     try {
-      await fetch(url)
-        .then((response) => response.json())
-        .then((result) => console.log(result.data));
+      const response = await axios.post(url, {
+        hostname: hostname,
+        service: service,
+        idempotency_key: uniqid(),
+      });
+      console.log(response.data.data);
     } catch (error) {
       console.log(error.data);
     }
   }
 
   function cardBorderStyle(service) {
+    if (service.in_maintenance) {
+      return "warning";
+    }
     if (service.active_status !== "active") {
       return "danger";
     }
@@ -212,6 +218,7 @@ const Service = ({ hostname, service, doLoadStatuses }) => {
         <Card className="text-center" border={cardBorderStyle(service)}>
           <Card.Header className="text-muted">
             {service.active_status}
+            {service.in_maintenance && " (in maintenance)"}
           </Card.Header>
           <Card.Body>
             <Card.Title>{service.name}</Card.Title>
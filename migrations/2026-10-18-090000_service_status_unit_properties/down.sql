@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE service_status
+  DROP COLUMN sub_state,
+  DROP COLUMN main_pid,
+  DROP COLUMN n_restarts,
+  DROP COLUMN memory_current;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE service_status
+  ADD COLUMN sub_state TEXT AFTER active_status,
+  ADD COLUMN main_pid BIGINT AFTER sub_state,
+  ADD COLUMN n_restarts INT AFTER main_pid,
+  ADD COLUMN memory_current BIGINT AFTER n_restarts;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE
+  service_events (
+    id BIGINT PRIMARY KEY AUTO_INCREMENT UNIQUE NOT NULL,
+    hostname TEXT NOT NULL,
+    name TEXT NOT NULL,
+    old_active_status TEXT,
+    new_active_status TEXT,
+    old_enabled BOOLEAN,
+    new_enabled BOOLEAN,
+    old_sub_state TEXT,
+    new_sub_state TEXT,
+    changed_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX idx_service_events_lookup (hostname(191), name(191), changed_at)
+  );
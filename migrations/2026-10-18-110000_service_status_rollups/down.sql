@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX idx_service_status_last_check ON service_status;
+DROP TABLE service_status_rollups;
@@ -0,0 +1,16 @@
+-- Your SQL goes here
+CREATE TABLE
+  service_status_rollups (
+    id BIGINT PRIMARY KEY AUTO_INCREMENT UNIQUE NOT NULL,
+    hostname VARCHAR(255) NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    period VARCHAR(8) NOT NULL,
+    period_start DATETIME NOT NULL,
+    samples INT NOT NULL,
+    active_samples INT NOT NULL,
+    percent_active DOUBLE NOT NULL,
+    flaps INT NOT NULL,
+    UNIQUE KEY uq_service_status_rollups (hostname, name, period, period_start)
+  );
+
+CREATE INDEX idx_service_status_last_check ON service_status (last_check);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX idx_service_status_latest ON service_status;
+DROP TABLE service_current_status;
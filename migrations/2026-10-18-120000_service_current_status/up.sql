@@ -0,0 +1,30 @@
+-- Your SQL goes here
+CREATE TABLE
+  service_current_status (
+    hostname VARCHAR(255) NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    status_id BIGINT NOT NULL,
+    description TEXT,
+    enabled BOOLEAN,
+    active_status TEXT,
+    sub_state TEXT,
+    main_pid BIGINT,
+    n_restarts INT,
+    memory_current BIGINT,
+    last_check DATETIME,
+    PRIMARY KEY (hostname, name)
+  );
+
+CREATE INDEX idx_service_status_latest ON service_status (hostname(191), name(191), last_check);
+
+-- Seed with the newest existing row per service
+INSERT INTO service_current_status
+  (hostname, name, status_id, description, enabled, active_status,
+   sub_state, main_pid, n_restarts, memory_current, last_check)
+SELECT s.hostname, s.name, s.id, s.description, s.enabled, s.active_status,
+       s.sub_state, s.main_pid, s.n_restarts, s.memory_current, s.last_check
+FROM service_status AS s
+WHERE s.id = (SELECT t.id FROM service_status AS t
+              WHERE t.hostname = s.hostname AND t.name = s.name
+              AND t.last_check IS NOT NULL
+              ORDER BY t.last_check DESC, t.id DESC LIMIT 1);
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE
+  group_permissions (
+    id BIGINT PRIMARY KEY AUTO_INCREMENT NOT NULL,
+    group_accts_id BIGINT NOT NULL,
+    permission VARCHAR(32) NOT NULL,
+    UNIQUE KEY uq_group_permissions (group_accts_id, permission),
+    CONSTRAINT FOREIGN KEY fk_group_permissions_group_accts_id (group_accts_id)
+      REFERENCES group_accts (id)
+      ON DELETE CASCADE
+  );
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+-- `service` NULL assigns the whole host
+CREATE TABLE
+  group_hosts (
+    id BIGINT PRIMARY KEY AUTO_INCREMENT NOT NULL,
+    group_accts_id BIGINT NOT NULL,
+    hostname VARCHAR(255) NOT NULL,
+    service VARCHAR(255) DEFAULT NULL,
+    INDEX idx_group_hosts_group (group_accts_id),
+    CONSTRAINT FOREIGN KEY fk_group_hosts_group_accts_id (group_accts_id)
+      REFERENCES group_accts (id)
+      ON DELETE CASCADE
+  );
@@ -0,0 +1,19 @@
+-- Your SQL goes here
+-- `scopes` is a comma-separated list of permissions, e.g. `view,operate`
+CREATE TABLE
+  api_tokens (
+    id BIGINT PRIMARY KEY AUTO_INCREMENT NOT NULL,
+    user_id BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    prefix VARCHAR(32) NOT NULL,
+    secret_hash TEXT NOT NULL,
+    scopes TEXT NOT NULL,
+    expires_at DATETIME DEFAULT NULL,
+    last_used DATETIME DEFAULT NULL,
+    revoked BOOLEAN NOT NULL DEFAULT false,
+    created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE KEY uq_api_tokens_prefix (prefix),
+    CONSTRAINT FOREIGN KEY fk_api_tokens_user_id (user_id)
+      REFERENCES users (id)
+      ON DELETE CASCADE
+  );
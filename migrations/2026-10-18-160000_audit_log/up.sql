@@ -0,0 +1,18 @@
+-- Your SQL goes here
+CREATE TABLE
+  audit_log (
+    id BIGINT PRIMARY KEY AUTO_INCREMENT NOT NULL,
+    actor VARCHAR(255) NOT NULL,
+    user_id BIGINT DEFAULT NULL,
+    token_id BIGINT DEFAULT NULL,
+    action VARCHAR(64) NOT NULL,
+    hostname VARCHAR(255) DEFAULT NULL,
+    service VARCHAR(255) DEFAULT NULL,
+    payload TEXT,
+    output TEXT,
+    exit_code INT DEFAULT NULL,
+    success BOOLEAN NOT NULL,
+    created_at DATETIME NOT NULL,
+    INDEX idx_audit_log_created_at (created_at),
+    INDEX idx_audit_log_target (hostname, service)
+  );
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+CREATE TABLE
+  alerts (
+    id BIGINT PRIMARY KEY AUTO_INCREMENT NOT NULL,
+    rule VARCHAR(255) NOT NULL,
+    hostname VARCHAR(255) NOT NULL,
+    service VARCHAR(255) NOT NULL,
+    severity VARCHAR(64) DEFAULT NULL,
+    state VARCHAR(16) NOT NULL,
+    active_state VARCHAR(64) DEFAULT NULL,
+    message TEXT NOT NULL,
+    fired_at DATETIME NOT NULL,
+    resolved_at DATETIME DEFAULT NULL,
+    INDEX idx_alerts_state (state),
+    INDEX idx_alerts_fired_at (fired_at),
+    INDEX idx_alerts_target (hostname, service)
+  );
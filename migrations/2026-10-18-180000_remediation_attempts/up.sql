@@ -0,0 +1,16 @@
+-- Your SQL goes here
+CREATE TABLE
+  remediation_attempts (
+    id BIGINT PRIMARY KEY AUTO_INCREMENT NOT NULL,
+    hostname VARCHAR(255) NOT NULL,
+    service VARCHAR(255) NOT NULL,
+    attempt INT NOT NULL,
+    outcome VARCHAR(16) NOT NULL,
+    exit_code INT DEFAULT NULL,
+    final_state VARCHAR(64) DEFAULT NULL,
+    message TEXT DEFAULT NULL,
+    duration_ms BIGINT NOT NULL,
+    attempted_at DATETIME NOT NULL,
+    INDEX idx_remediation_attempts_target (hostname, service, attempted_at),
+    INDEX idx_remediation_attempts_attempted_at (attempted_at)
+  );
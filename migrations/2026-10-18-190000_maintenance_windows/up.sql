@@ -0,0 +1,21 @@
+-- Your SQL goes here
+-- One-off windows set `starts_at` and `ends_at`; recurring windows set `cron`
+-- and `duration_secs`
+CREATE TABLE
+  maintenance_windows (
+    id BIGINT PRIMARY KEY AUTO_INCREMENT NOT NULL,
+    reason TEXT NOT NULL,
+    hostname VARCHAR(255) DEFAULT NULL,
+    service VARCHAR(255) DEFAULT NULL,
+    group_accts_id BIGINT DEFAULT NULL,
+    starts_at DATETIME DEFAULT NULL,
+    ends_at DATETIME DEFAULT NULL,
+    cron VARCHAR(255) DEFAULT NULL,
+    duration_secs BIGINT DEFAULT NULL,
+    created_by VARCHAR(255) NOT NULL,
+    created_at DATETIME NOT NULL,
+    INDEX idx_maintenance_windows_ends_at (ends_at),
+    CONSTRAINT FOREIGN KEY fk_maintenance_windows_group_accts_id (group_accts_id)
+      REFERENCES group_accts (id)
+      ON DELETE CASCADE
+  );
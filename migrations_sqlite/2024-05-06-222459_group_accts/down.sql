@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER group_accts_modified;
+DROP TABLE group_accts;
@@ -0,0 +1,19 @@
+-- Your SQL goes here
+CREATE TABLE
+  group_accts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    parent_id BIGINT DEFAULT NULL
+      REFERENCES group_accts (id)
+      ON DELETE SET NULL,
+    name TEXT NOT NULL,
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    modified TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+  );
+
+-- SQLite has no `ON UPDATE CURRENT_TIMESTAMP`
+CREATE TRIGGER group_accts_modified
+  AFTER UPDATE ON group_accts
+  FOR EACH ROW
+BEGIN
+  UPDATE group_accts SET modified = CURRENT_TIMESTAMP WHERE id = OLD.id;
+END;
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+CREATE TABLE
+  users (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    name TEXT NOT NULL,
+    password TEXT NOT NULL,
+    group_accts_id BIGINT DEFAULT NULL
+      REFERENCES group_accts (id)
+      ON DELETE SET NULL,
+    active BOOLEAN NOT NULL DEFAULT 1,
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    modified TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+  );
+
+-- SQLite has no `ON UPDATE CURRENT_TIMESTAMP`
+CREATE TRIGGER users_modified
+  AFTER UPDATE ON users
+  FOR EACH ROW
+BEGIN
+  UPDATE users SET modified = CURRENT_TIMESTAMP WHERE id = OLD.id;
+END;
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE
+  service_status (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    hostname TEXT NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    enabled BOOLEAN,
+    active_status TEXT,
+    last_check TIMESTAMP
+  );
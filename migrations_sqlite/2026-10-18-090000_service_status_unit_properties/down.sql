@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE service_status DROP COLUMN sub_state;
+ALTER TABLE service_status DROP COLUMN main_pid;
+ALTER TABLE service_status DROP COLUMN n_restarts;
+ALTER TABLE service_status DROP COLUMN memory_current;
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE service_status ADD COLUMN sub_state TEXT;
+ALTER TABLE service_status ADD COLUMN main_pid BIGINT;
+ALTER TABLE service_status ADD COLUMN n_restarts INTEGER;
+ALTER TABLE service_status ADD COLUMN memory_current BIGINT;
@@ -0,0 +1,16 @@
+-- Your SQL goes here
+CREATE TABLE
+  service_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    hostname TEXT NOT NULL,
+    name TEXT NOT NULL,
+    old_active_status TEXT,
+    new_active_status TEXT,
+    old_enabled BOOLEAN,
+    new_enabled BOOLEAN,
+    old_sub_state TEXT,
+    new_sub_state TEXT,
+    changed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+  );
+
+CREATE INDEX idx_service_events_lookup ON service_events (hostname, name, changed_at);
@@ -0,0 +1,16 @@
+-- Your SQL goes here
+CREATE TABLE
+  service_status_rollups (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    hostname TEXT NOT NULL,
+    name TEXT NOT NULL,
+    period TEXT NOT NULL,
+    period_start TIMESTAMP NOT NULL,
+    samples INTEGER NOT NULL,
+    active_samples INTEGER NOT NULL,
+    percent_active DOUBLE NOT NULL,
+    flaps INTEGER NOT NULL,
+    UNIQUE (hostname, name, period, period_start)
+  );
+
+CREATE INDEX idx_service_status_last_check ON service_status (last_check);
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE
+  group_permissions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    group_accts_id BIGINT NOT NULL
+      REFERENCES group_accts (id)
+      ON DELETE CASCADE,
+    permission TEXT NOT NULL,
+    UNIQUE (group_accts_id, permission)
+  );
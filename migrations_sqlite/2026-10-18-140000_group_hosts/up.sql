@@ -0,0 +1,13 @@
+-- Your SQL goes here
+-- `service` NULL assigns the whole host
+CREATE TABLE
+  group_hosts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    group_accts_id BIGINT NOT NULL
+      REFERENCES group_accts (id)
+      ON DELETE CASCADE,
+    hostname TEXT NOT NULL,
+    service TEXT DEFAULT NULL
+  );
+
+CREATE INDEX idx_group_hosts_group ON group_hosts (group_accts_id);
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+-- `scopes` is a comma-separated list of permissions, e.g. `view,operate`
+CREATE TABLE
+  api_tokens (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    user_id BIGINT NOT NULL
+      REFERENCES users (id)
+      ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    prefix TEXT NOT NULL UNIQUE,
+    secret_hash TEXT NOT NULL,
+    scopes TEXT NOT NULL,
+    expires_at TIMESTAMP DEFAULT NULL,
+    last_used TIMESTAMP DEFAULT NULL,
+    revoked BOOLEAN NOT NULL DEFAULT 0,
+    created TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+  );
@@ -0,0 +1,19 @@
+-- Your SQL goes here
+CREATE TABLE
+  audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    actor TEXT NOT NULL,
+    user_id BIGINT DEFAULT NULL,
+    token_id BIGINT DEFAULT NULL,
+    action TEXT NOT NULL,
+    hostname TEXT DEFAULT NULL,
+    service TEXT DEFAULT NULL,
+    payload TEXT,
+    output TEXT,
+    exit_code INTEGER DEFAULT NULL,
+    success BOOLEAN NOT NULL,
+    created_at TIMESTAMP NOT NULL
+  );
+
+CREATE INDEX idx_audit_log_created_at ON audit_log (created_at);
+CREATE INDEX idx_audit_log_target ON audit_log (hostname, service);
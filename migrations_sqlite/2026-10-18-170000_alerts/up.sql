@@ -0,0 +1,18 @@
+-- Your SQL goes here
+CREATE TABLE
+  alerts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    rule TEXT NOT NULL,
+    hostname TEXT NOT NULL,
+    service TEXT NOT NULL,
+    severity TEXT DEFAULT NULL,
+    state TEXT NOT NULL,
+    active_state TEXT DEFAULT NULL,
+    message TEXT NOT NULL,
+    fired_at TIMESTAMP NOT NULL,
+    resolved_at TIMESTAMP DEFAULT NULL
+  );
+
+CREATE INDEX idx_alerts_state ON alerts (state);
+CREATE INDEX idx_alerts_fired_at ON alerts (fired_at);
+CREATE INDEX idx_alerts_target ON alerts (hostname, service);
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+CREATE TABLE
+  remediation_attempts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    hostname TEXT NOT NULL,
+    service TEXT NOT NULL,
+    attempt INTEGER NOT NULL,
+    outcome TEXT NOT NULL,
+    exit_code INTEGER DEFAULT NULL,
+    final_state TEXT DEFAULT NULL,
+    message TEXT DEFAULT NULL,
+    duration_ms BIGINT NOT NULL,
+    attempted_at TIMESTAMP NOT NULL
+  );
+
+CREATE INDEX idx_remediation_attempts_target ON remediation_attempts (hostname, service, attempted_at);
+CREATE INDEX idx_remediation_attempts_attempted_at ON remediation_attempts (attempted_at);
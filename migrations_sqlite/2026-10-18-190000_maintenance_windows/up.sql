@@ -0,0 +1,21 @@
+-- Your SQL goes here
+-- One-off windows set `starts_at` and `ends_at`; recurring windows set `cron`
+-- and `duration_secs`
+CREATE TABLE
+  maintenance_windows (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    reason TEXT NOT NULL,
+    hostname TEXT DEFAULT NULL,
+    service TEXT DEFAULT NULL,
+    group_accts_id BIGINT DEFAULT NULL
+      REFERENCES group_accts (id)
+      ON DELETE CASCADE,
+    starts_at TIMESTAMP DEFAULT NULL,
+    ends_at TIMESTAMP DEFAULT NULL,
+    cron TEXT DEFAULT NULL,
+    duration_secs BIGINT DEFAULT NULL,
+    created_by TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL
+  );
+
+CREATE INDEX idx_maintenance_windows_ends_at ON maintenance_windows (ends_at);
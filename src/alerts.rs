@@ -0,0 +1,404 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::NaiveDateTime;
+
+use crate::bulk::glob_match;
+use crate::config::{AlertCondition, AlertRule};
+use crate::database::crudops;
+use crate::database::models::{Alert, NewAlert};
+use crate::database::DbConnection;
+use crate::error::{Result, SvcmonError};
+use crate::hosts::Hosts;
+use crate::notify::SharedNotifier;
+use crate::rbac::{owned_by, Scope};
+use crate::unit::ActiveState;
+
+/// How long a rule's `group_accts_id` assignments are cached
+const GROUP_REFRESH: Duration = Duration::from_secs(60);
+
+/// Rule name of the alerts raised when remediation gives up on a service
+pub const REMEDIATION_RULE: &str = "remediation";
+
+/// Rule name, hostname, service
+type AlertKey = (String, String, String);
+
+/// What one poller check of a service found
+#[derive(Debug, Clone, Copy)]
+pub enum Observation<'a> {
+    /// The unit's `ActiveState`; `None` if systemd did not report one
+    State(Option<&'a ActiveState>),
+    /// The check itself failed
+    CheckFailed(&'a SvcmonError),
+}
+
+// ALERT ENGINE
+/******************************************************************************/
+/// Evaluates `[[config.alerts.rules]]` against every check the poller makes
+/// - A rule fires once its condition held for `for_checks` checks in a row,
+///   and resolves on the first check where it no longer holds
+/// - At most one alert per rule, host and service is firing at a time;
+///   alerts still firing in `alerts` are picked up again after a restart
+/// - Also holds the `remediation` alerts raised when a remediation policy
+///   runs out of attempts
+pub struct AlertEngine {
+    rules: Vec<AlertRule>,
+    state: Mutex<EngineState>,
+    notifier: Option<SharedNotifier>,
+}
+
+#[derive(Default)]
+struct EngineState {
+    /// Firing alerts were read from the database
+    loaded: bool,
+    /// Consecutive checks each rule's condition has held
+    streaks: HashMap<AlertKey, u32>,
+    /// Id of the firing alert; `None` while it is being raised or resolved,
+    /// so a concurrent check leaves it alone
+    firing: HashMap<AlertKey, Option<i64>>,
+    groups: HashMap<i64, (Instant, Scope)>,
+}
+
+impl EngineState {
+    /// Reserve `key` for a new alert unless one is firing or being raised
+    fn claim_fire(&mut self, key: &AlertKey) -> bool {
+        if self.firing.contains_key(key) {
+            return false;
+        }
+        self.firing.insert(key.clone(), None);
+        true
+    }
+
+    /// Take the id of the alert firing under `key` so it can be resolved
+    fn claim_resolve(&mut self, key: &AlertKey) -> Option<i64> {
+        self.firing.get_mut(key).and_then(Option::take)
+    }
+}
+
+/// What one rule makes of a check, decided under the lock
+enum Decision {
+    Nothing,
+    Fire(u32),
+    Resolve(i64),
+}
+
+impl AlertEngine {
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        let mut names: Vec<&str> = rules.iter().map(|rule| rule.name.as_str()).collect();
+        names.sort_unstable();
+        if names.contains(&REMEDIATION_RULE) {
+            eprintln!(
+                "WARNING: alert rule name '{}' is also used for remediation escalations",
+                REMEDIATION_RULE
+            );
+        }
+        for pair in names.windows(2) {
+            if pair[0] == pair[1] {
+                eprintln!(
+                    "WARNING: alert rule '{}' is defined more than once; its alerts will be merged",
+                    pair[0]
+                );
+            }
+        }
+        AlertEngine {
+            rules,
+            state: Mutex::new(EngineState::default()),
+            notifier: None,
+        }
+    }
+
+    /// Send every alert that fires or resolves through `notifier`
+    pub fn with_notifier(mut self, notifier: SharedNotifier) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Hand `alert`, as returned by `observe`, to the notifier if there is one
+    pub fn notify(&self, alert: Alert) {
+        if let Some(notifier) = &self.notifier {
+            notifier.dispatch(alert);
+        }
+    }
+
+    /// Evaluate every rule for one check of `service` on `hostname`
+    /// A failed check only counts towards `check_failed` rules; the others
+    /// stay as they were, since nothing new is known about the unit
+    /// Returns the alerts that fired or resolved
+    pub fn observe(
+        &self,
+        conn: &mut DbConnection,
+        hostname: &str,
+        service: &str,
+        observation: Observation<'_>,
+        now: NaiveDateTime,
+    ) -> Result<Vec<Alert>> {
+        self.ensure_loaded(conn, now)?;
+
+        let mut changed = vec![];
+        for rule in &self.rules {
+            if !self.applies(rule, conn, hostname, service)? {
+                continue;
+            }
+
+            let key = (rule.name.clone(), hostname.to_string(), service.to_string());
+            let holds = match observation {
+                Observation::State(active_state) => rule.condition.holds(active_state),
+                Observation::CheckFailed(_) if rule.condition == AlertCondition::CheckFailed => true,
+                Observation::CheckFailed(_) => continue,
+            };
+            // The database is written after the lock is released
+            let decision = {
+                let mut state = self.state.lock().unwrap();
+                if holds {
+                    let streak = state.streaks.entry(key.clone()).or_insert(0);
+                    *streak += 1;
+                    let streak = *streak;
+                    if streak >= rule.for_checks.max(1) && state.claim_fire(&key) {
+                        Decision::Fire(streak)
+                    } else {
+                        Decision::Nothing
+                    }
+                } else {
+                    state.streaks.remove(&key);
+                    state.claim_resolve(&key).map_or(Decision::Nothing, Decision::Resolve)
+                }
+            };
+
+            let streak = match decision {
+                Decision::Nothing => continue,
+                Decision::Resolve(id) => {
+                    changed.push(self.resolve(conn, &key, id, now)?);
+                    continue;
+                }
+                Decision::Fire(streak) => streak,
+            };
+            let active_state = match observation {
+                Observation::State(active_state) => active_state,
+                Observation::CheckFailed(_) => None,
+            };
+            let message = match observation {
+                Observation::State(_) => format!(
+                    "{} on {} has been '{}' for {} check(s)",
+                    service,
+                    hostname,
+                    active_state.map_or("unknown", |state| state.as_str()),
+                    streak
+                ),
+                Observation::CheckFailed(e) => format!(
+                    "{} on {} could not be checked {} time(s) in a row: {}",
+                    service, hostname, streak, e
+                ),
+            };
+            let alert = self.fire(
+                conn,
+                &key,
+                &NewAlert {
+                    rule: &rule.name,
+                    hostname,
+                    service,
+                    severity: rule.severity.as_deref(),
+                    state: "firing",
+                    active_state: active_state.map(|state| state.as_str()),
+                    message: &message,
+                    fired_at: now,
+                },
+            )?;
+            changed.push(alert);
+        }
+
+        Ok(changed)
+    }
+
+    /// Fire a `remediation` alert for `service` on `hostname` unless one is
+    /// already firing; returns the new alert
+    #[allow(clippy::too_many_arguments)]
+    pub fn escalate(
+        &self,
+        conn: &mut DbConnection,
+        hostname: &str,
+        service: &str,
+        severity: Option<&str>,
+        active_state: Option<&ActiveState>,
+        message: &str,
+        now: NaiveDateTime,
+    ) -> Result<Option<Alert>> {
+        self.ensure_loaded(conn, now)?;
+        let key = (REMEDIATION_RULE.to_string(), hostname.to_string(), service.to_string());
+        if !self.state.lock().unwrap().claim_fire(&key) {
+            return Ok(None);
+        }
+
+        let alert = self.fire(
+            conn,
+            &key,
+            &NewAlert {
+                rule: REMEDIATION_RULE,
+                hostname,
+                service,
+                severity,
+                state: "firing",
+                active_state: active_state.map(|state| state.as_str()),
+                message,
+                fired_at: now,
+            },
+        )?;
+        Ok(Some(alert))
+    }
+
+    /// Resolve the `remediation` alert for `service` on `hostname`, if one is
+    /// firing, and return it
+    pub fn clear_escalation(
+        &self,
+        conn: &mut DbConnection,
+        hostname: &str,
+        service: &str,
+        now: NaiveDateTime,
+    ) -> Result<Option<Alert>> {
+        self.ensure_loaded(conn, now)?;
+        let key = (REMEDIATION_RULE.to_string(), hostname.to_string(), service.to_string());
+        let id = match self.state.lock().unwrap().claim_resolve(&key) {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+        self.resolve(conn, &key, id, now).map(Some)
+    }
+
+    /// Resolve every alert on a service that is no longer in `hosts`; no
+    /// further check would ever resolve it
+    /// Alerts that cannot be resolved are reported and kept for the next call
+    pub fn resolve_removed(
+        &self,
+        conn: &mut DbConnection,
+        hosts: &Hosts,
+        now: NaiveDateTime,
+    ) -> Result<Vec<Alert>> {
+        self.ensure_loaded(conn, now)?;
+        let claimed: Vec<(AlertKey, i64)> = {
+            let mut state = self.state.lock().unwrap();
+            state
+                .streaks
+                .retain(|(_, hostname, service), _| hosts.has_service(hostname, service));
+            state
+                .firing
+                .iter_mut()
+                .filter(|((_, hostname, service), _)| !hosts.has_service(hostname, service))
+                .filter_map(|(key, slot)| slot.take().map(|id| (key.clone(), id)))
+                .collect()
+        };
+
+        let mut resolved = vec![];
+        for (key, id) in claimed {
+            match self.resolve(conn, &key, id, now) {
+                Ok(alert) => resolved.push(alert),
+                Err(e) => eprintln!("WARNING: alert {} on a removed service not resolved: {}", id, e),
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// Insert `new_alert`, whose `key` `claim_fire` reserved, and record it as
+    /// firing; the reservation is dropped if the insert fails
+    fn fire(&self, conn: &mut DbConnection, key: &AlertKey, new_alert: &NewAlert) -> Result<Alert> {
+        let result = crudops::insert_alert(conn, new_alert);
+        let mut state = self.state.lock().unwrap();
+        match &result {
+            Ok(alert) => state.firing.insert(key.clone(), Some(alert.id)),
+            Err(_) => state.firing.remove(key),
+        };
+        result
+    }
+
+    /// Resolve alert `id`, which `claim_resolve` took from `key`
+    /// It stays firing if the update fails, so the next check retries it
+    fn resolve(
+        &self,
+        conn: &mut DbConnection,
+        key: &AlertKey,
+        id: i64,
+        now: NaiveDateTime,
+    ) -> Result<Alert> {
+        let result = crudops::resolve_alert(conn, id, now);
+        let mut state = self.state.lock().unwrap();
+        match &result {
+            Ok(_) => state.firing.remove(key),
+            Err(_) => state.firing.insert(key.clone(), Some(id)),
+        };
+        result
+    }
+
+    /// Pick up alerts left firing by an earlier run, once; those whose rule
+    /// has since been removed from `svcmon.toml` are resolved
+    fn ensure_loaded(&self, conn: &mut DbConnection, now: NaiveDateTime) -> Result<()> {
+        if self.state.lock().unwrap().loaded {
+            return Ok(());
+        }
+        let alerts = crudops::get_firing_alerts(conn)?;
+
+        let mut unknown = vec![];
+        {
+            let mut state = self.state.lock().unwrap();
+            // Another check got here first
+            if state.loaded {
+                return Ok(());
+            }
+            for alert in alerts {
+                let known = alert.rule == REMEDIATION_RULE
+                    || self.rules.iter().any(|rule| rule.name == alert.rule);
+                if known {
+                    state
+                        .firing
+                        .entry((alert.rule, alert.hostname, alert.service))
+                        .or_insert(Some(alert.id));
+                } else {
+                    unknown.push(alert.id);
+                }
+            }
+            state.loaded = true;
+        }
+        for id in unknown {
+            crudops::resolve_alert(conn, id, now)?;
+        }
+        Ok(())
+    }
+
+    /// `rule` covers `service` on `hostname`
+    fn applies(
+        &self,
+        rule: &AlertRule,
+        conn: &mut DbConnection,
+        hostname: &str,
+        service: &str,
+    ) -> Result<bool> {
+        if let Some(host) = &rule.host {
+            if !glob_match(host, hostname) {
+                return Ok(false);
+            }
+        }
+        if let Some(pattern) = &rule.service {
+            if !glob_match(pattern, service) {
+                return Ok(false);
+            }
+        }
+        let group_accts_id = match rule.group_accts_id {
+            Some(id) => id,
+            None => return Ok(true),
+        };
+
+        if let Some((loaded, scope)) = self.state.lock().unwrap().groups.get(&group_accts_id) {
+            if loaded.elapsed() < GROUP_REFRESH {
+                return Ok(scope.allows_service(hostname, service));
+            }
+        }
+        // Read without the lock; a concurrent refresh of the same group is
+        // harmless
+        let scope = owned_by(conn, group_accts_id)?;
+        let allowed = scope.allows_service(hostname, service);
+        self.state
+            .lock()
+            .unwrap()
+            .groups
+            .insert(group_accts_id, (Instant::now(), scope));
+        Ok(allowed)
+    }
+}
@@ -3,12 +3,22 @@
 pub mod service_handler;
 pub mod host_handler;
 pub mod db_handler;
+pub mod auth_handler;
+pub mod user_handler;
+pub mod token_handler;
+pub mod audit_handler;
+pub mod alert_handler;
+pub mod notify_handler;
+pub mod remediation_handler;
+pub mod maintenance_handler;
+pub mod metrics_handler;
 pub mod helpers;
 
 
 // BEGIN API MODULE
 /******************************************************************************/
-use rocket::{get, http::Status, serde::json::Json};
+use rocket::{catch, get, http::Status, request::Request, serde::json::Json};
+use crate::auth::GuardFailure;
 use crate::response::GenericResponse;
 
 /// Is server alive
@@ -23,4 +33,17 @@ pub async fn isalive_api() -> Result<Json<GenericResponse>, Status> {
     Ok(Json(response_json))
 }
 
-
+// CATCHERS
+/******************************************************************************/
+/// JSON body for requests rejected by `AuthUser`
+#[catch(401)]
+pub fn unauthorized_catcher(req: &Request) -> Json<GenericResponse> {
+    let failure = req.local_cache(GuardFailure::default);
+    Json(GenericResponse {
+        status: String::from("fail"),
+        message: failure
+            .0
+            .clone()
+            .unwrap_or_else(|| String::from("Unauthorized")),
+    })
+}
@@ -0,0 +1,60 @@
+use crate::api::helpers::query_params::{parse_optional_datetime, Page};
+use crate::auth::AuthUser;
+use crate::database::crudops::{get_alerts, AlertFilter};
+use crate::database::pool::{get_conn, DbPool};
+use crate::error::{Result, SvcmonError};
+use crate::rbac::Permission;
+use crate::response::AlertsResponse;
+use rocket::{get, http::Status, response::status::Custom, serde::json::Json, State};
+
+/// Alert history, newest first, limited to the caller's hosts and services
+/// `state` is `firing` or `resolved`; `since` and `until` apply to `fired_at`
+/// `page` starts at 1; `per_page` defaults to 50 and is capped at 500
+#[allow(clippy::too_many_arguments)]
+#[get("/alerts?<state>&<rule>&<host>&<service>&<since>&<until>&<page>&<per_page>")]
+pub fn get_alerts_api(
+    state: Option<&str>,
+    rule: Option<&str>,
+    host: Option<&str>,
+    service: Option<&str>,
+    since: Option<&str>,
+    until: Option<&str>,
+    page: Option<u32>,
+    per_page: Option<u32>,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<AlertsResponse>>> {
+    user.require(Permission::View)?;
+    if let Some(state) = state {
+        if state != "firing" && state != "resolved" {
+            return Err(SvcmonError::Validation(format!(
+                "`state` must be 'firing' or 'resolved', got '{}'",
+                state
+            )));
+        }
+    }
+    let paging = Page::new(page, per_page)?;
+    let filter = AlertFilter {
+        state,
+        rule,
+        hostname: host,
+        service,
+        since: parse_optional_datetime("since", since)?,
+        until: parse_optional_datetime("until", until)?,
+        scope: Some(&user.scope),
+    };
+
+    let conn = &mut get_conn(pool)?;
+    let (alerts, total) = get_alerts(conn, &filter, paging.offset(), paging.limit())?;
+    Ok(Custom(
+        Status::Ok,
+        Json(AlertsResponse {
+            status: String::from("success"),
+            count: alerts.len(),
+            total,
+            page: paging.page,
+            per_page: paging.per_page,
+            data: alerts,
+        }),
+    ))
+}
@@ -0,0 +1,51 @@
+use crate::api::helpers::query_params::{parse_optional_datetime, Page};
+use crate::auth::AuthUser;
+use crate::database::crudops::{get_audit_entries, AuditFilter};
+use crate::database::pool::{get_conn, DbPool};
+use crate::error::Result;
+use crate::rbac::Permission;
+use crate::response::AuditResponse;
+use rocket::{get, http::Status, response::status::Custom, serde::json::Json, State};
+
+/// Audit log, newest first, optionally narrowed by actor, action, host,
+/// service and time range
+/// `page` starts at 1; `per_page` defaults to 50 and is capped at 500
+#[allow(clippy::too_many_arguments)]
+#[get("/audit?<actor>&<action>&<host>&<service>&<since>&<until>&<page>&<per_page>")]
+pub fn get_audit_api(
+    actor: Option<&str>,
+    action: Option<&str>,
+    host: Option<&str>,
+    service: Option<&str>,
+    since: Option<&str>,
+    until: Option<&str>,
+    page: Option<u32>,
+    per_page: Option<u32>,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<AuditResponse>>> {
+    user.require(Permission::Admin)?;
+    let paging = Page::new(page, per_page)?;
+    let filter = AuditFilter {
+        actor,
+        action,
+        hostname: host,
+        service,
+        since: parse_optional_datetime("since", since)?,
+        until: parse_optional_datetime("until", until)?,
+    };
+
+    let conn = &mut get_conn(pool)?;
+    let (entries, total) = get_audit_entries(conn, &filter, paging.offset(), paging.limit())?;
+    Ok(Custom(
+        Status::Ok,
+        Json(AuditResponse {
+            status: String::from("success"),
+            count: entries.len(),
+            total,
+            page: paging.page,
+            per_page: paging.per_page,
+            data: entries,
+        }),
+    ))
+}
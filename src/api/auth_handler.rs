@@ -0,0 +1,34 @@
+use crate::auth::{authenticate, end_session, start_session};
+use crate::database::pool::{get_conn, DbPool};
+use crate::error::Result;
+use crate::response::{GenericResponse, LoginRequest};
+use rocket::{http::CookieJar, http::Status, post, response::status::Custom, serde::json::Json, State};
+
+/// Check `name` and `password` against `users` and start a session
+#[post("/auth/login", data = "<credentials>")]
+pub fn login_api(
+    credentials: Json<LoginRequest>,
+    cookies: &CookieJar<'_>,
+    pool: &State<DbPool>,
+) -> Result<Custom<Json<GenericResponse>>> {
+    let conn = &mut get_conn(pool)?;
+    let user = authenticate(conn, &credentials.name, &credentials.password)?;
+    start_session(cookies, &user);
+    Ok(Custom(
+        Status::Ok,
+        Json(GenericResponse {
+            status: String::from("success"),
+            message: format!("Logged in as '{}'", user.name),
+        }),
+    ))
+}
+
+/// End the current session, if any
+#[post("/auth/logout")]
+pub fn logout_api(cookies: &CookieJar<'_>) -> Json<GenericResponse> {
+    end_session(cookies);
+    Json(GenericResponse {
+        status: String::from("success"),
+        message: String::from("Logged out"),
+    })
+}
@@ -1,69 +1,56 @@
-use crate::database::crudops::{get_json_statuses, get_status_from_db, insert_status};
-use crate::response::{GenericResponse, StatusRequest, StatusResponse, StatusesResponse};
-use diesel::QueryResult;
-use rocket::{get, http::Status, put, response::status::Custom, serde::json::Json};
+use crate::api::helpers::query_params::{parse_optional_datetime, Page};
+use crate::audit::{self, AuditRecord};
+use crate::auth::AuthUser;
+use crate::database::crudops::{
+    get_events, get_json_statuses, get_status_from_db, insert_status, EventFilter,
+};
+use crate::database::pool::{get_conn, DbPool};
+use crate::database::DbConnection;
+use crate::error::Result;
+use crate::maintenance::Maintenance;
+use crate::rbac::Permission;
+use crate::response::{EventsResponse, GenericResponse, LatestStatus, StatusRequest, StatusResponse,
+    StatusesResponse};
+use chrono::Utc;
+use rocket::{get, http::Status, put, response::status::Custom, serde::json::Json, State};
 
 /// Insert `Status` by object
-pub fn insert_status_by_obj(status: StatusRequest) -> QueryResult<usize> {
-    let mut description_str = String::new();
-    let description: Option<&str> = match status.description {
-        Some(s) => {
-            description_str = s.clone();
-            Some(&description_str)
-        }
-        None => {
-            drop(description_str);
-            None
-        }
-    };
-
-    let mut active_status_str = String::new();
-    let active_status: Option<&str> = match status.active_status {
-        Some(s) => {
-            active_status_str = s.clone();
-            Some(&active_status_str)
-        }
-        None => {
-            drop(active_status_str);
-            None
-        }
-    };
-
+pub fn insert_status_by_obj(conn: &mut DbConnection, status: StatusRequest) -> Result<usize> {
     insert_status(
+        conn,
         &status.hostname,
         &status.name,
-        description,
+        status.description.as_deref(),
         status.enabled,
-        active_status,
+        status.active_status.as_deref(),
         status.last_check,
     )
 }
 
 /// Update the `status_service` table with latest service status
+/// Admin only, since a made-up row misleads everyone reading statuses
 #[put("/svc/update_status", data = "<status>")]
 pub async fn update_status_api(
     status: Json<StatusRequest>,
-) -> Result<Custom<Json<GenericResponse>>, Custom<Json<GenericResponse>>> {
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<GenericResponse>>> {
+    user.require(Permission::Admin)?;
     let new_status = status.0;
     let servicename = new_status.name.clone();
     let hostname = new_status.hostname.clone();
+    let record = AuditRecord::new("update_status", Some(&hostname), Some(&servicename))
+        .payload(&new_status);
 
-    match insert_status_by_obj(new_status) {
-        Ok(_sz) => {
-            let response = GenericResponse {
-                status: String::from("success"),
-                message: format!("'{}' on {} successfully updated.", servicename, hostname),
-            };
-            return Ok(Custom(Status::Created, Json(response)));
-        }
-        Err(err) => {
-            let error_response = GenericResponse {
-                status: String::from("fail"),
-                message: format!("{:?}", err),
-            };
-            return Err(Custom(Status::InternalServerError, Json(error_response)));
-        }
-    }
+    let conn = &mut get_conn(pool)?;
+    let result = insert_status_by_obj(conn, new_status);
+    audit::record(conn, &user, record.outcome(&result, |_| None));
+    result?;
+    let response = GenericResponse {
+        status: String::from("success"),
+        message: format!("'{}' on {} successfully updated.", servicename, hostname),
+    };
+    Ok(Custom(Status::Created, Json(response)))
 }
 
 /// Get `status_service` by `servicename` and `host`
@@ -71,42 +58,87 @@ pub async fn update_status_api(
 pub fn get_latest_status_api(
     host: &str,
     service: &str,
-) -> Result<Custom<Json<StatusResponse>>, Custom<Json<GenericResponse>>> {
-    match get_status_from_db(host, service) {
-        Ok(status) => Ok(Custom(
-            Status::Ok,
-            Json(StatusResponse {
-                status: String::from("success"),
-                data: status,
-            }),
-        )),
-        Err(err) => {
-            let response = GenericResponse {
-                status: String::from("success"),
-                message: format!("{:?}", err),
-            };
-            Err(Custom(Status::InternalServerError, Json(response)))
-        }
-    }
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<StatusResponse>>> {
+    user.require(Permission::View)?;
+    user.require_service(host, service)?;
+    let conn = &mut get_conn(pool)?;
+    let status = get_status_from_db(conn, host, service)?;
+    Ok(Custom(
+        Status::Ok,
+        Json(StatusResponse {
+            status: String::from("success"),
+            data: status,
+        }),
+    ))
 }
 
 /// Get latetest statuses for every service listed in `host.json`
+/// Each carries `in_maintenance` when a maintenance window covers it now
 #[get("/svc/get_latest_statuses")]
-pub fn get_latest_statuses_api() -> Result<Custom<Json<StatusesResponse>>, Custom<Json<GenericResponse>>> {
-    match get_json_statuses() {
-        Ok(statuses) => Ok(Custom(
-            Status::Ok,
-            Json(StatusesResponse {
-                status: String::from("success"),
-                data: statuses,
-            }),
-        )),
-        Err(err) => {
-            let response = GenericResponse {
-                status: String::from("success"),
-                message: format!("{:?}", err),
-            };
-            Err(Custom(Status::InternalServerError, Json(response)))
-        }
-    }
+pub fn get_latest_statuses_api(
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<StatusesResponse>>> {
+    user.require(Permission::View)?;
+    let conn = &mut get_conn(pool)?;
+    let mut statuses = get_json_statuses(conn)?;
+    statuses.retain(|status| user.scope.allows_service(&status.hostname, &status.name));
+    let maintenance = Maintenance::load(conn, Utc::now().naive_utc())?;
+    let statuses = statuses
+        .into_iter()
+        .map(|status| LatestStatus {
+            in_maintenance: maintenance.covers(&status.hostname, &status.name),
+            status,
+        })
+        .collect();
+    Ok(Custom(
+        Status::Ok,
+        Json(StatusesResponse {
+            status: String::from("success"),
+            data: statuses,
+        }),
+    ))
 }
+
+/// Get state changes recorded by the collector, newest first
+/// `since` and `until` take RFC 3339 timestamps or `YYYY-MM-DD`
+/// `page` starts at 1; `per_page` defaults to 50 and is capped at 500
+#[allow(clippy::too_many_arguments)]
+#[get("/svc/events?<host>&<service>&<since>&<until>&<page>&<per_page>")]
+pub fn get_events_api(
+    host: Option<&str>,
+    service: Option<&str>,
+    since: Option<&str>,
+    until: Option<&str>,
+    page: Option<u32>,
+    per_page: Option<u32>,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<EventsResponse>>> {
+    user.require(Permission::View)?;
+    let paging = Page::new(page, per_page)?;
+    let filter = EventFilter {
+        hostname: host,
+        name: service,
+        since: parse_optional_datetime("since", since)?,
+        until: parse_optional_datetime("until", until)?,
+        scope: Some(&user.scope),
+    };
+
+    let conn = &mut get_conn(pool)?;
+    let (events, total) = get_events(conn, &filter, paging.offset(), paging.limit())?;
+    Ok(Custom(
+        Status::Ok,
+        Json(EventsResponse {
+            status: String::from("success"),
+            count: events.len(),
+            total,
+            page: paging.page,
+            per_page: paging.per_page,
+            data: events,
+        }),
+    ))
+}
+
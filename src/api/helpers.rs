@@ -1 +1,2 @@
 pub mod request_types;
+pub mod query_params;
@@ -0,0 +1,70 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+use crate::error::{Result, SvcmonError};
+
+/// Parse a timestamp passed in a query string
+/// Accepts RFC 3339 (`2024-05-06T22:29:24Z`), `2024-05-06T22:29:24`,
+/// `2024-05-06 22:29:24` or a bare date (`2024-05-06`, midnight UTC)
+pub fn parse_datetime(name: &str, value: &str) -> Result<NaiveDateTime> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.naive_utc());
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S"] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(datetime);
+        }
+    }
+    if let Some(datetime) = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+    {
+        return Ok(datetime);
+    }
+    Err(SvcmonError::Validation(format!(
+        "'{}' is not a valid timestamp for `{}`",
+        value, name
+    )))
+}
+
+/// `parse_datetime` for an optional query parameter
+pub fn parse_optional_datetime(name: &str, value: Option<&str>) -> Result<Option<NaiveDateTime>> {
+    value.map(|value| parse_datetime(name, value)).transpose()
+}
+
+/// `per_page` unless the request sets it
+pub const DEFAULT_PER_PAGE: u32 = 50;
+/// Larger `per_page` values are capped to this
+pub const MAX_PER_PAGE: u32 = 500;
+
+/// The `page` and `per_page` query parameters of a paginated route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    /// Starts at 1
+    pub page: u32,
+    pub per_page: u32,
+}
+
+impl Page {
+    /// `page` defaults to 1 and must not be 0; `per_page` defaults to
+    /// `DEFAULT_PER_PAGE` and is clamped to `1..=MAX_PER_PAGE`
+    pub fn new(page: Option<u32>, per_page: Option<u32>) -> Result<Page> {
+        let page = page.unwrap_or(1);
+        if page == 0 {
+            return Err(SvcmonError::Validation(String::from("`page` starts at 1")));
+        }
+        Ok(Page {
+            page,
+            per_page: per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE),
+        })
+    }
+
+    /// Rows to skip
+    pub fn offset(&self) -> i64 {
+        i64::from(self.page - 1) * i64::from(self.per_page)
+    }
+
+    /// Rows to return
+    pub fn limit(&self) -> i64 {
+        i64::from(self.per_page)
+    }
+}
@@ -1,8 +1,79 @@
 use serde::{Serialize, Deserialize};
 
+use crate::bulk::{BulkMode, Selector};
+use crate::service::ControlAction;
+
+
 /// Request for hostname with service name
 #[derive(Deserialize, Serialize, Debug)]
 pub struct HostnameWithService {
     pub hostname: String,
     pub service: String,
 }
+
+/// Body of `POST /api/systemctl/{start,stop,restart}`
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ControlRequest {
+    pub hostname: String,
+    pub service: String,
+    /// Report what would run, and the current state, without running it
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Retries with the same key get the first response instead of running
+    /// the command again
+    pub idempotency_key: Option<String>,
+    /// Poll until the unit reaches the state expected after the action
+    #[serde(default)]
+    pub wait: bool,
+    /// Overrides `verify_timeout_secs` under `[config.control]` for `wait`,
+    /// up to `max_verify_timeout_secs`
+    pub timeout_secs: Option<u64>,
+}
+
+/// Body of `POST /api/systemctl/bulk`
+#[derive(Deserialize, Serialize, Debug)]
+pub struct BulkRequest {
+    pub action: ControlAction,
+    #[serde(flatten)]
+    pub selector: Selector,
+    #[serde(default)]
+    pub mode: BulkMode,
+    /// Lowers `bulk_max_parallel` under `[config.control]`; larger values are
+    /// cut down to it
+    pub max_parallel: Option<usize>,
+    /// Only report the targets and commands
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Poll each target until it settles, as for single actions
+    #[serde(default)]
+    pub wait: bool,
+    pub timeout_secs: Option<u64>,
+}
+
+/// Body of `POST /api/notify/test`
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct NotifyTestRequest {
+    /// Every channel if left out
+    pub channel: Option<String>,
+}
+
+/// Body of `POST /api/maintenance`
+/// At least one of `hostname`, `service` and `group_accts_id` is required;
+/// the window applies to services matching all that are given
+#[derive(Deserialize, Serialize, Debug)]
+pub struct MaintenanceRequest {
+    pub reason: String,
+    /// Hostname, or a glob for admins
+    pub hostname: Option<String>,
+    /// Service name, or a glob for admins
+    pub service: Option<String>,
+    pub group_accts_id: Option<i64>,
+    /// One-off window: defaults to now
+    pub starts_at: Option<String>,
+    /// One-off window: give this or `duration_secs`
+    pub ends_at: Option<String>,
+    /// Recurring window: five-field cron expression in UTC, with
+    /// `duration_secs`
+    pub cron: Option<String>,
+    pub duration_secs: Option<i64>,
+}
@@ -1,187 +1,184 @@
 use crate::api::helpers::request_types::HostnameWithService;
-use crate::hosts::{add_host_to_hosts_file, rem_svc_from_hosts_file, Host, Hosts};
+use crate::audit::{self, AuditRecord};
+use crate::auth::AuthUser;
+use crate::database::crudops::assign_host;
+use crate::database::pool::{get_conn, DbPool};
+use crate::error::Result;
+use crate::hosts::{
+    add_host_to_hosts_file, add_svc_to_hosts_file, rem_host_from_hosts_file,
+    rem_svc_from_hosts_file, update_host_in_hosts_file, Host, Hosts,
+};
+use crate::rbac::Permission;
 use crate::response::{GenericResponse, HostsResponse};
 
 use rocket::{
     delete, get, http::Status, patch, post, put, response::status::Custom, serde::json::Json,
+    State,
 };
 
 /// Get all hosts
 /// Returns reference to the static object created that represents the
 /// `hosts.json` file
 #[get("/hosts/all")]
-pub async fn get_all_hosts_api() -> Result<Custom<Json<HostsResponse>>, Status> {
-    match Hosts::init_from_file() {
-        Ok(hosts) => {
-            let response = HostsResponse {
-                status: String::from("success"),
-                data: hosts,
-            };
-            return Ok(Custom(Status::Ok, Json(response)));
-        }
-        Err(_) => {
-            return Err(Status::Conflict);
-        }
+pub async fn get_all_hosts_api(user: AuthUser) -> Result<Custom<Json<HostsResponse>>> {
+    user.require(Permission::View)?;
+    let mut hosts = Hosts::init_from_file()?;
+    user.scope.filter_hosts(&mut hosts);
+    let response = HostsResponse {
+        status: String::from("success"),
+        data: hosts,
     };
+    Ok(Custom(Status::Ok, Json(response)))
 }
 
-/// Add a host to 'hosts.json'
+/// Add a host to 'hosts.json' and assign it to the caller's group
+/// Answers 409 if the hostname is already there
 #[post("/hosts/add_host", data = "<host>")]
 pub async fn add_host_api(
     host: Json<Host>,
-) -> Result<Custom<Json<GenericResponse>>, Custom<Json<GenericResponse>>> {
-    let hostname = host.0.hostname.clone();
-    match add_host_to_hosts_file(host.0) {
-        Ok(_) => {
-            let msg = format!("Host, '{}', successfully added.", hostname);
-            return Ok(Custom(
-                Status::Ok,
-                Json(GenericResponse {
-                    status: String::from("success"),
-                    message: msg,
-                }),
-            ));
-        }
-        Err(e) => {
-            let err_msg = format!("Error adding host, '{}': {}", hostname, e);
-            return Err(Custom(
-                Status::InternalServerError,
-                Json(GenericResponse {
-                    status: String::from("fail"),
-                    message: err_msg,
-                }),
-            ));
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<GenericResponse>>> {
+    user.require(Permission::EditHosts)?;
+    let new_host = host.0;
+    let hostname = new_host.hostname.clone();
+    let record = AuditRecord::new("add_host", Some(&hostname), None).payload(&new_host);
+    let result = add_host_to_hosts_file(new_host).and_then(|_| match user.group_accts_id {
+        // Otherwise the caller could not see the host they just added
+        Some(group_accts_id) => {
+            let conn = &mut get_conn(pool)?;
+            assign_host(conn, group_accts_id, &hostname, None).map(|_| ())
         }
-    };
+        None => Ok(()),
+    });
+    audit::record_with_pool(pool, &user, record.outcome(&result, |_| None));
+    result?;
+    Ok(Custom(
+        Status::Ok,
+        Json(GenericResponse {
+            status: String::from("success"),
+            message: format!("Host, '{}', successfully added.", hostname),
+        }),
+    ))
 }
 
-/// Add a service to `AllHosts`
+/// Replace a `Host` in 'hosts.json'
+/// Answers 404 if the hostname is not there
 #[put("/hosts/update_host", data = "<host>")]
-pub async fn update_host_api(host: Json<Host>) -> Result<Custom<Json<GenericResponse>>, Status> {
-    let mut hosts = match Hosts::init_from_file() {
-        Ok(hosts) => hosts,
-        Err(_) => {
-            return Err(Status::Conflict);
-        }
-    };
-
+pub async fn update_host_api(
+    host: Json<Host>,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<GenericResponse>>> {
+    user.require(Permission::EditHosts)?;
     let new_host = host.0;
     let hostname = new_host.hostname.clone();
-    hosts.remove_host(&new_host);
-    match hosts.write_to_file() {
-        Ok(()) => Ok(Custom(
-            Status::Created,
-            Json(GenericResponse {
-                status: String::from("success"),
-                message: format!("Host, '{}', successfully updated.", hostname),
-            }),
-        )),
-        Err(_e) => Err(Status::Conflict),
-    }
+    user.require_host(&hostname)?;
+    let record = AuditRecord::new("update_host", Some(&hostname), None).payload(&new_host);
+    let result = update_host_in_hosts_file(new_host);
+    audit::record_with_pool(pool, &user, record.outcome(&result, |_| None));
+    result?;
+    Ok(Custom(
+        Status::Created,
+        Json(GenericResponse {
+            status: String::from("success"),
+            message: format!("Host, '{}', successfully updated.", hostname),
+        }),
+    ))
 }
 
-/// Remove service from `Host`
+/// Remove a `Host` from 'hosts.json'
+/// Answers 404 if the hostname is not there
 #[delete("/hosts/remove_host", data = "<hostname>")]
 pub async fn remove_host_api(
     hostname: &str,
-) -> Result<Custom<Json<GenericResponse>>, Custom<Json<GenericResponse>>> {
-    match Hosts::init_from_file() {
-        Ok(mut hosts) => match hosts.remove_host_by_hostname(hostname) {
-            Some(_host) => {
-                let success_response = GenericResponse {
-                    status: String::from("sucess"),
-                    message: format!("Host, '{}', successfully removed", hostname),
-                };
-                return Ok(Custom(Status::Created, Json(success_response)));
-            }
-            None => {
-                let success_response = GenericResponse {
-                    status: String::from("sucess"),
-                    message: format!("Host, '{}', does not exist", hostname),
-                };
-                return Ok(Custom(Status::Ok, Json(success_response)));
-            }
-        },
-        Err(e) => {
-            let error_response = GenericResponse {
-                status: String::from("failure"),
-                message: format!("Interal error loading `hosts.json` file: {:?}", e),
-            };
-            return Err(Custom(Status::InternalServerError, Json(error_response)));
-        }
-    }
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<GenericResponse>>> {
+    user.require(Permission::EditHosts)?;
+    user.require_host(hostname)?;
+    let result = rem_host_from_hosts_file(hostname);
+    audit::record_with_pool(
+        pool,
+        &user,
+        AuditRecord::new("remove_host", Some(hostname), None).outcome(&result, |_| None),
+    );
+    result?;
+    Ok(Custom(
+        Status::Ok,
+        Json(GenericResponse {
+            status: String::from("success"),
+            message: format!("Host, '{}', successfully removed", hostname),
+        }),
+    ))
 }
 
 /// Add a service to a `Host`
+/// Answers 404 if the hostname is not in 'hosts.json'
 #[post("/hosts/add_service", data = "<hostname_w_svc>")]
 pub async fn add_service_api(
     hostname_w_svc: Json<HostnameWithService>,
-) -> Result<Custom<Json<GenericResponse>>, Custom<Json<GenericResponse>>> {
-    let hostname = hostname_w_svc.hostname.clone();
-    let service = hostname_w_svc.service.clone();
-    match rem_svc_from_hosts_file(&hostname, &service) {
-        Ok(_) => {
-            let msg = format!(
-                "Service, '{}', successfully added to host, '{}'",
-                hostname_w_svc.hostname, hostname_w_svc.service
-            );
-            return Ok(Custom(
-                Status::Ok,
-                Json(GenericResponse {
-                    status: String::from("success"),
-                    message: msg,
-                }),
-            ));
-        }
-        Err(e) => {
-            let err_msg = format!(
-                "Did not remove service, '{}', from host, '{}': {:?}",
-                hostname_w_svc.hostname, hostname_w_svc.service, e
-            );
-            return Err(Custom(
-                Status::Conflict,
-                Json(GenericResponse {
-                    status: String::from("fail"),
-                    message: err_msg,
-                }),
-            ));
-        }
-    };
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<GenericResponse>>> {
+    user.require(Permission::EditHosts)?;
+    user.require_host(&hostname_w_svc.hostname)?;
+    let result = add_svc_to_hosts_file(&hostname_w_svc.hostname, &hostname_w_svc.service);
+    audit::record_with_pool(
+        pool,
+        &user,
+        AuditRecord::new(
+            "add_service",
+            Some(&hostname_w_svc.hostname),
+            Some(&hostname_w_svc.service),
+        )
+        .outcome(&result, |_| None),
+    );
+    result?;
+    let msg = format!(
+        "Service, '{}', successfully added to host, '{}'",
+        hostname_w_svc.service, hostname_w_svc.hostname
+    );
+    Ok(Custom(
+        Status::Ok,
+        Json(GenericResponse {
+            status: String::from("success"),
+            message: msg,
+        }),
+    ))
 }
 
 /// Remove a service from a `host`
+/// Answers 404 if the host or the service is not in 'hosts.json'
 #[patch("/hosts/remove_service", data = "<hostname_w_svc>")]
 pub async fn remove_service_api(
     hostname_w_svc: Json<HostnameWithService>,
-) -> Result<Custom<Json<GenericResponse>>, Custom<Json<GenericResponse>>> {
-    let hostname = hostname_w_svc.hostname.to_owned();
-    let service = hostname_w_svc.service.to_owned();
-    match rem_svc_from_hosts_file(&hostname, &service) {
-        Ok(_) => {
-            let msg = format!(
-                "Service, '{}', successfully removed from host, '{}'",
-                hostname_w_svc.hostname, hostname_w_svc.service
-            );
-            return Ok(Custom(
-                Status::Ok,
-                Json(GenericResponse {
-                    status: String::from("success"),
-                    message: msg,
-                }),
-            ));
-        }
-        Err(e) => {
-            let err_msg = format!(
-                "Did not remove service, '{}', from host, '{}': {:?}",
-                hostname_w_svc.hostname, hostname_w_svc.service, e
-            );
-            return Err(Custom(
-                Status::Conflict,
-                Json(GenericResponse {
-                    status: String::from("fail"),
-                    message: err_msg,
-                }),
-            ));
-        }
-    };
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<GenericResponse>>> {
+    user.require(Permission::EditHosts)?;
+    user.require_host(&hostname_w_svc.hostname)?;
+    let result = rem_svc_from_hosts_file(&hostname_w_svc.hostname, &hostname_w_svc.service);
+    audit::record_with_pool(
+        pool,
+        &user,
+        AuditRecord::new(
+            "remove_service",
+            Some(&hostname_w_svc.hostname),
+            Some(&hostname_w_svc.service),
+        )
+        .outcome(&result, |_| None),
+    );
+    result?;
+    let msg = format!(
+        "Service, '{}', successfully removed from host, '{}'",
+        hostname_w_svc.service, hostname_w_svc.hostname
+    );
+    Ok(Custom(
+        Status::Ok,
+        Json(GenericResponse {
+            status: String::from("success"),
+            message: msg,
+        }),
+    ))
 }
@@ -0,0 +1,247 @@
+use crate::api::helpers::query_params::parse_optional_datetime;
+use crate::api::helpers::request_types::MaintenanceRequest;
+use crate::audit::{self, AuditRecord};
+use crate::auth::AuthUser;
+use crate::database::crudops::{
+    delete_maintenance_window, get_group_subtree, get_maintenance_window,
+    get_maintenance_windows, insert_maintenance_window,
+};
+use crate::database::models::{MaintenanceWindow, NewMaintenanceWindow};
+use crate::database::pool::{get_conn, DbPool};
+use crate::database::DbConnection;
+use crate::error::{Result, SvcmonError};
+use crate::maintenance::{is_active, Cron, MAX_RECURRING_SECS};
+use crate::rbac::{Permission, Scope};
+use crate::response::{
+    GenericResponse, MaintenanceEntry, MaintenanceWindowResponse, MaintenanceWindowsResponse,
+};
+use chrono::{Duration, Utc};
+use rocket::{delete, get, http::Status, post, response::status::Custom, serde::json::Json, State};
+
+/// `SvcmonError::Forbidden` unless `user` may manage a window on these
+/// targets
+/// - Admins may use any target, including globs
+/// - Others need a group at or below their own, or a hostname they own
+///   outright, or one of their services on it
+fn check_targets(
+    conn: &mut DbConnection,
+    user: &AuthUser,
+    hostname: Option<&str>,
+    service: Option<&str>,
+    group_accts_id: Option<i64>,
+) -> Result<()> {
+    if hostname.is_none() && service.is_none() && group_accts_id.is_none() {
+        return Err(SvcmonError::Validation(String::from(
+            "give at least one of `hostname`, `service` and `group_accts_id`",
+        )));
+    }
+    if let Scope::All = user.scope {
+        return Ok(());
+    }
+
+    let has_glob = |value: Option<&str>| value.is_some_and(|value| value.contains(['*', '?']));
+    if has_glob(hostname) || has_glob(service) {
+        return Err(SvcmonError::Forbidden(String::from(
+            "only admins may use globs in maintenance windows",
+        )));
+    }
+    let allowed = match (group_accts_id, hostname, service) {
+        (Some(group_accts_id), _, _) => match user.group_accts_id {
+            Some(own) => get_group_subtree(conn, own)?.contains(&group_accts_id),
+            None => false,
+        },
+        (None, Some(hostname), Some(service)) => user.scope.allows_service(hostname, service),
+        (None, Some(hostname), None) => user.scope.owns_host(hostname),
+        (None, None, _) => false,
+    };
+    if !allowed {
+        return Err(SvcmonError::Forbidden(String::from(
+            "the maintenance window covers hosts or services you do not own",
+        )));
+    }
+    Ok(())
+}
+
+/// Visible to the caller: windows on a host outside their scope are hidden
+fn visible(window: &MaintenanceWindow, scope: &Scope) -> bool {
+    match &window.hostname {
+        Some(hostname) if !hostname.contains(['*', '?']) => scope.allows_host(hostname),
+        _ => true,
+    }
+}
+
+/// Maintenance windows, oldest first, each with whether it is running now
+/// `active=true` lists only running windows; ended one-off windows are left
+/// out unless `all=true`
+#[get("/maintenance?<active>&<all>")]
+pub fn get_maintenance_api(
+    active: Option<bool>,
+    all: Option<bool>,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<MaintenanceWindowsResponse>>> {
+    user.require(Permission::View)?;
+    let now = Utc::now().naive_utc();
+    let conn = &mut get_conn(pool)?;
+    let after = if all.unwrap_or(false) { None } else { Some(now) };
+
+    let data: Vec<MaintenanceEntry> = get_maintenance_windows(conn, after)?
+        .into_iter()
+        .filter(|window| visible(window, &user.scope))
+        .map(|window| MaintenanceEntry {
+            active: is_active(&window, now),
+            window,
+        })
+        .filter(|entry| entry.active || !active.unwrap_or(false))
+        .collect();
+    Ok(Custom(
+        Status::Ok,
+        Json(MaintenanceWindowsResponse {
+            status: String::from("success"),
+            count: data.len(),
+            data,
+        }),
+    ))
+}
+
+/// Create a one-off or recurring maintenance window
+/// While it runs, the poller neither evaluates alert rules nor remediates the
+/// services it covers
+#[post("/maintenance", data = "<request>")]
+pub fn add_maintenance_api(
+    request: Json<MaintenanceRequest>,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<MaintenanceWindowResponse>>> {
+    user.require(Permission::Operate)?;
+    let now = Utc::now().naive_utc();
+    let conn = &mut get_conn(pool)?;
+    check_targets(
+        conn,
+        &user,
+        request.hostname.as_deref(),
+        request.service.as_deref(),
+        request.group_accts_id,
+    )?;
+    if request.reason.trim().is_empty() {
+        return Err(SvcmonError::Validation(String::from("`reason` is required")));
+    }
+
+    let starts_at = parse_optional_datetime("starts_at", request.starts_at.as_deref())?;
+    let ends_at = parse_optional_datetime("ends_at", request.ends_at.as_deref())?;
+    let (starts_at, ends_at, duration_secs) = match &request.cron {
+        Some(cron) => {
+            cron.parse::<Cron>()?;
+            if starts_at.is_some() || ends_at.is_some() {
+                return Err(SvcmonError::Validation(String::from(
+                    "recurring windows take `duration_secs`, not `starts_at` or `ends_at`",
+                )));
+            }
+            match request.duration_secs {
+                Some(secs) if (60..=MAX_RECURRING_SECS).contains(&secs) => (None, None, Some(secs)),
+                _ => {
+                    return Err(SvcmonError::Validation(format!(
+                        "recurring windows need `duration_secs` from 60 to {}",
+                        MAX_RECURRING_SECS
+                    )))
+                }
+            }
+        }
+        None => {
+            let starts_at = starts_at.unwrap_or(now);
+            let ends_at = match (ends_at, request.duration_secs) {
+                (Some(ends_at), None) => ends_at,
+                (None, Some(secs)) if secs > 0 => starts_at + Duration::seconds(secs),
+                _ => {
+                    return Err(SvcmonError::Validation(String::from(
+                        "one-off windows need either `ends_at` or a positive `duration_secs`",
+                    )))
+                }
+            };
+            if ends_at <= starts_at || ends_at <= now {
+                return Err(SvcmonError::Validation(String::from(
+                    "`ends_at` must be after `starts_at` and in the future",
+                )));
+            }
+            (Some(starts_at), Some(ends_at), None)
+        }
+    };
+
+    let result = insert_maintenance_window(
+        conn,
+        &NewMaintenanceWindow {
+            reason: request.reason.trim(),
+            hostname: request.hostname.as_deref(),
+            service: request.service.as_deref(),
+            group_accts_id: request.group_accts_id,
+            starts_at,
+            ends_at,
+            cron: request.cron.as_deref(),
+            duration_secs,
+            created_by: &user.name,
+            created_at: now,
+        },
+    );
+    audit::record(
+        conn,
+        &user,
+        AuditRecord::new(
+            "add_maintenance",
+            request.hostname.as_deref(),
+            request.service.as_deref(),
+        )
+        .payload(&request.0)
+        .outcome(&result, |window| Some(format!("window {}", window.id))),
+    );
+    let window = result?;
+    Ok(Custom(
+        Status::Created,
+        Json(MaintenanceWindowResponse {
+            status: String::from("success"),
+            data: MaintenanceEntry {
+                active: is_active(&window, now),
+                window,
+            },
+        }),
+    ))
+}
+
+/// End a maintenance window early, or drop a recurring one
+#[delete("/maintenance/<id>")]
+pub fn delete_maintenance_api(
+    id: i64,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<GenericResponse>>> {
+    user.require(Permission::Operate)?;
+    let conn = &mut get_conn(pool)?;
+    let window = get_maintenance_window(conn, id)?;
+    check_targets(
+        conn,
+        &user,
+        window.hostname.as_deref(),
+        window.service.as_deref(),
+        window.group_accts_id,
+    )?;
+
+    let result = delete_maintenance_window(conn, id);
+    audit::record(
+        conn,
+        &user,
+        AuditRecord::new(
+            "delete_maintenance",
+            window.hostname.as_deref(),
+            window.service.as_deref(),
+        )
+        .payload(&window)
+        .outcome(&result, |_| None),
+    );
+    result?;
+    Ok(Custom(
+        Status::Ok,
+        Json(GenericResponse {
+            status: String::from("success"),
+            message: format!("Maintenance window, {}, successfully removed", id),
+        }),
+    ))
+}
@@ -0,0 +1,26 @@
+use crate::auth::AuthUser;
+use crate::database::crudops::get_json_statuses;
+use crate::database::pool::{get_conn, DbPool};
+use crate::error::Result;
+use crate::metrics::{render, SharedMetrics};
+use crate::rbac::Permission;
+use rocket::{get, http::ContentType, State};
+
+/// Unit states and collector metrics in Prometheus text format, limited to
+/// the caller's hosts and services
+/// Scrape with an API token: `Authorization: Bearer <token>`
+#[get("/metrics")]
+pub fn metrics_api(
+    pool: &State<DbPool>,
+    metrics: &State<SharedMetrics>,
+    user: AuthUser,
+) -> Result<(ContentType, String)> {
+    user.require(Permission::View)?;
+    let conn = &mut get_conn(pool)?;
+    let mut statuses = get_json_statuses(conn)?;
+    statuses.retain(|status| user.scope.allows_service(&status.hostname, &status.name));
+
+    let body = render(&statuses, metrics, &user.scope);
+    let content_type = ContentType::new("text", "plain").with_params(("version", "0.0.4"));
+    Ok((content_type, body))
+}
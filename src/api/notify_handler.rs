@@ -0,0 +1,94 @@
+use crate::api::helpers::request_types::NotifyTestRequest;
+use crate::audit::{self, AuditRecord};
+use crate::auth::AuthUser;
+use crate::database::models::Alert;
+use crate::database::pool::DbPool;
+use crate::error::{Result, SvcmonError};
+use crate::notify::SharedNotifier;
+use crate::rbac::Permission;
+use crate::response::{ChannelResult, NotifyTestResponse};
+use chrono::Utc;
+use rocket::tokio::task;
+use rocket::{http::Status, post, response::status::Custom, serde::json::Json, State};
+use std::sync::Arc;
+
+/// Send a made-up alert to one channel, or to all of them, once and without
+/// retries, and report how each delivery went
+#[post("/notify/test", data = "<request>")]
+pub async fn test_notify_api(
+    request: Json<NotifyTestRequest>,
+    notifier: &State<SharedNotifier>,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<NotifyTestResponse>>> {
+    user.require(Permission::Admin)?;
+    let channels: Vec<String> = match &request.channel {
+        Some(channel) if !notifier.channel_names().contains(&channel.as_str()) => {
+            return Err(SvcmonError::NotFound(format!(
+                "no notification channel named '{}'",
+                channel
+            )))
+        }
+        Some(channel) => vec![channel.clone()],
+        None => notifier
+            .channel_names()
+            .into_iter()
+            .map(String::from)
+            .collect(),
+    };
+    if channels.is_empty() {
+        return Err(SvcmonError::NotFound(String::from(
+            "no notification channels are configured",
+        )));
+    }
+
+    let alert = Alert {
+        id: 0,
+        rule: String::from("test"),
+        hostname: String::from("svcmon"),
+        service: String::from("svcmon"),
+        severity: None,
+        state: String::from("firing"),
+        active_state: None,
+        message: format!("Test notification sent by {}", user.name),
+        fired_at: Utc::now().naive_utc(),
+        resolved_at: None,
+    };
+    let notifier = Arc::clone(notifier.inner());
+    let results = task::spawn_blocking(move || {
+        channels
+            .into_iter()
+            .map(|channel| {
+                let sent = notifier.send_to(&channel, &alert);
+                (channel, sent)
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| SvcmonError::Unavailable(format!("test notification panicked: {}", e)))?;
+
+    let mut data = vec![];
+    for (channel, sent) in results {
+        audit::record_with_pool(
+            pool,
+            &user,
+            AuditRecord::new("test_notify", None, None)
+                .payload(&channel)
+                .outcome(&sent, |_| None),
+        );
+        data.push(ChannelResult {
+            channel,
+            success: sent.is_ok(),
+            message: sent.err().map(|e| e.to_string()),
+        });
+    }
+
+    let failed = data.iter().any(|result| !result.success);
+    Ok(Custom(
+        Status::Ok,
+        Json(NotifyTestResponse {
+            status: String::from(if failed { "fail" } else { "success" }),
+            data,
+        }),
+    ))
+}
@@ -0,0 +1,60 @@
+use crate::api::helpers::query_params::{parse_optional_datetime, Page};
+use crate::auth::AuthUser;
+use crate::database::crudops::{get_remediation_attempts, RemediationFilter};
+use crate::database::pool::{get_conn, DbPool};
+use crate::error::{Result, SvcmonError};
+use crate::rbac::Permission;
+use crate::response::RemediationAttemptsResponse;
+use rocket::{get, http::Status, response::status::Custom, serde::json::Json, State};
+
+/// Automatic restarts made by remediation policies, newest first, limited to
+/// the caller's hosts and services
+/// `outcome` is `success` or `failed`; `since` and `until` apply to
+/// `attempted_at`
+/// `page` starts at 1; `per_page` defaults to 50 and is capped at 500
+#[allow(clippy::too_many_arguments)]
+#[get("/remediation/attempts?<host>&<service>&<outcome>&<since>&<until>&<page>&<per_page>")]
+pub fn get_remediation_attempts_api(
+    host: Option<&str>,
+    service: Option<&str>,
+    outcome: Option<&str>,
+    since: Option<&str>,
+    until: Option<&str>,
+    page: Option<u32>,
+    per_page: Option<u32>,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<RemediationAttemptsResponse>>> {
+    user.require(Permission::View)?;
+    if let Some(outcome) = outcome {
+        if outcome != "success" && outcome != "failed" {
+            return Err(SvcmonError::Validation(format!(
+                "`outcome` must be 'success' or 'failed', got '{}'",
+                outcome
+            )));
+        }
+    }
+    let paging = Page::new(page, per_page)?;
+    let filter = RemediationFilter {
+        hostname: host,
+        service,
+        outcome,
+        since: parse_optional_datetime("since", since)?,
+        until: parse_optional_datetime("until", until)?,
+        scope: Some(&user.scope),
+    };
+
+    let conn = &mut get_conn(pool)?;
+    let (attempts, total) = get_remediation_attempts(conn, &filter, paging.offset(), paging.limit())?;
+    Ok(Custom(
+        Status::Ok,
+        Json(RemediationAttemptsResponse {
+            status: String::from("success"),
+            count: attempts.len(),
+            total,
+            page: paging.page,
+            per_page: paging.per_page,
+            data: attempts,
+        }),
+    ))
+}
@@ -1,153 +1,316 @@
-use crate::response::{GenericResponse, SystemctlResponse, SystemctlShowResponse};
-use crate::service::{get_status, list_unit_files, restart_service, show_service, start_service, stop_service};
-use rocket::{get, http::Status, response::status::Custom, serde::json::Json};
+use crate::api::helpers::request_types::{BulkRequest, ControlRequest};
+use crate::audit::{self, AuditRecord};
+use crate::auth::AuthUser;
+use crate::bulk::{resolve_targets, run_bulk, TargetRun, TargetStatus};
+use crate::config::read_config_file;
+use crate::database::pool::{get_conn, DbPool};
+use crate::error::Result;
+use crate::hosts::lookup_host;
+use crate::idempotency::{Claim, IdempotencyCache};
+use crate::rbac::Permission;
+use crate::response::{
+    BulkResponse, BulkTargetResult, ControlResponse, SystemctlResponse, SystemctlShowResponse,
+};
+use crate::service::{
+    active_status, describe_command, get_status, list_unit_files, show_service, spawn_action,
+    spawn_call, ControlAction, WaitFor,
+};
+use crate::transport::SharedTransport;
+use rocket::{get, http::Status, post, response::status::Custom, serde::json::Json, State};
+use std::sync::Arc;
 
 /// `systemctl -H <host> -l status` API
 #[get("/systemctl/status?<host>&<service>")]
 pub async fn service_status_api(
     host: &str,
     service: &str,
-) -> Result<Custom<Json<SystemctlResponse>>, Custom<Json<GenericResponse>>> {
-    let status = get_status(&host, &service);
-    match status {
-        Ok(v) => {
-            return Ok(Custom(
-                Status::Ok,
-                Json(SystemctlResponse {
-                    status: String::from("success"),
-                    count: v.len(),
-                    data: v,
-                }),
-            ));
-        }
-        _ => {
-            let error_response = GenericResponse {
-                status: "fail".to_string(),
-                message: format!("Could not get the status from {}", host),
-            };
-            return Err(Custom(Status::InternalServerError, Json(error_response)));
-        }
-    }
+    transport: &State<SharedTransport>,
+    user: AuthUser,
+) -> Result<Custom<Json<SystemctlResponse>>> {
+    user.require(Permission::View)?;
+    user.require_service(host, service)?;
+    let target = lookup_host(host)?;
+    let service = service.to_string();
+    let status = spawn_call(Arc::clone(transport.inner()), move |transport| {
+        get_status(transport, &target, &service)
+    })
+    .await?;
+    // 1-3 mean the unit is not running, which is still a status
+    let status = status.expect_exit(&[0, 1, 2, 3])?;
+    Ok(Custom(Status::Ok, Json(SystemctlResponse::from(status))))
 }
 
 /// `systemctl -H <host> show <service>`
 #[get("/systemctl/show?<host>&<service>")]
 pub async fn show_service_api(
     host: &str,
-    service: &str
-    ) -> Result<Json<SystemctlShowResponse>, Custom<Json<GenericResponse>>> {
-    let sysctl_show = show_service(host, service);
-    match sysctl_show {
-        Ok(hashmap) => {
-            let response = SystemctlShowResponse {
-                status: String::from("success"),
-                count: hashmap.len(),
-                data: hashmap,
-            };
-            return Ok(Json(response));
-        },
-        Err(err) => {
-            let response = GenericResponse {
-                status: String::from("fail"),
-                message: format!("{:?}", err),
-            };
-            return Err(Custom(Status::InternalServerError, Json(response)));
-        }
-    }
+    service: &str,
+    transport: &State<SharedTransport>,
+    user: AuthUser,
+) -> Result<Json<SystemctlShowResponse>> {
+    user.require(Permission::View)?;
+    user.require_service(host, service)?;
+    let target = lookup_host(host)?;
+    let service = service.to_string();
+    let props = spawn_call(Arc::clone(transport.inner()), move |transport| {
+        show_service(transport, &target, &service)
+    })
+    .await?;
+    Ok(Json(SystemctlShowResponse {
+        status: String::from("success"),
+        count: props.count(),
+        data: props,
+    }))
 }
 
 /// `systemctl start -H <host> <service>`
-/// NOTE: A successful start does not mean that the service started
-/// successfully. It means that the command was sent successfully.
-/// - To check for successful start, call `service_status_api`
-#[get("/systemctl/start?<host>&<service>")]
+/// NOTE: Without `wait`, a successful start only means that the command was
+/// sent successfully; with it, the unit reached the expected `ActiveState`
+#[post("/systemctl/start", data = "<request>")]
 pub async fn start_service_api(
-    host: &str,
-    service: &str,
-) -> Result<Custom<Json<SystemctlResponse>>, Custom<Json<GenericResponse>>> {
-    let start = start_service(&host, &service);
-    match start {
-        Ok(vec) => {
-            return Ok(Custom(
-                Status::Ok,
-                Json(SystemctlResponse {
-                    status: String::from("success"),
-                    count: vec.len(),
-                    data: vec,
-                }),
-            ));
-        }
-        _ => {
-            let error_response = GenericResponse {
-                status: String::from("fail"),
-                message: format!("Failed to start '{}' on '{}'", service, host),
-            };
-            return Err(Custom(Status::InternalServerError, Json(error_response)));
-        }
-    }
+    request: Json<ControlRequest>,
+    transport: &State<SharedTransport>,
+    pool: &State<DbPool>,
+    idempotency: &State<IdempotencyCache>,
+    user: AuthUser,
+) -> Result<Custom<Json<ControlResponse>>> {
+    control(ControlAction::Start, &request, transport, pool, idempotency, &user).await
 }
 
 /// `systemctl stop -H <host> <service>`
-/// NOTE: A successful stop does not mean that the service stoped
-/// successfully. It means that the command was sent successfully.
-/// - To check for successful stop, call `service_status_api`
-#[get("/systemctl/stop?<host>&<service>")]
+/// NOTE: Without `wait`, a successful stop only means that the command was
+/// sent successfully; with it, the unit reached the expected `ActiveState`
+#[post("/systemctl/stop", data = "<request>")]
 pub async fn stop_service_api(
-    host: &str,
-    service: &str,
-) -> Result<Custom<Json<SystemctlResponse>>, Custom<Json<GenericResponse>>> {
-    let stop = stop_service(&host, &service);
-    match stop {
-        Ok(vec) => {
-            return Ok(Custom(
-                Status::Ok,
-                Json(SystemctlResponse {
-                    status: String::from("success"),
-                    count: vec.len(),
-                    data: vec,
-                }),
-            ));
+    request: Json<ControlRequest>,
+    transport: &State<SharedTransport>,
+    pool: &State<DbPool>,
+    idempotency: &State<IdempotencyCache>,
+    user: AuthUser,
+) -> Result<Custom<Json<ControlResponse>>> {
+    control(ControlAction::Stop, &request, transport, pool, idempotency, &user).await
+}
+
+/// `systemctl restart -H <host> <service>`
+/// NOTE: Without `wait`, a successful restart only means that the command was
+/// sent successfully; with it, the unit reached the expected `ActiveState`
+#[post("/systemctl/restart", data = "<request>")]
+pub async fn restart_service_api(
+    request: Json<ControlRequest>,
+    transport: &State<SharedTransport>,
+    pool: &State<DbPool>,
+    idempotency: &State<IdempotencyCache>,
+    user: AuthUser,
+) -> Result<Custom<Json<ControlResponse>>> {
+    control(ControlAction::Restart, &request, transport, pool, idempotency, &user).await
+}
+
+/// Shared body of the `start`, `stop` and `restart` routes
+/// - `dry_run`: only report the command and the current `is-active` state
+/// - `wait`: poll `systemctl show` until the unit settles; see `wait_for_state`
+/// - `idempotency_key`: a repeat of a finished request gets its response back
+///   with `replayed` set, and nothing is run or audited again
+async fn control(
+    action: ControlAction,
+    request: &ControlRequest,
+    transport: &State<SharedTransport>,
+    pool: &State<DbPool>,
+    idempotency: &State<IdempotencyCache>,
+    user: &AuthUser,
+) -> Result<Custom<Json<ControlResponse>>> {
+    user.require(Permission::Operate)?;
+    user.require_service(&request.hostname, &request.service)?;
+    let target = lookup_host(&request.hostname)?;
+    let command = describe_command(&target, &request.service, action);
+
+    if request.dry_run {
+        let service = request.service.clone();
+        let current_state = spawn_call(Arc::clone(transport.inner()), move |transport| {
+            active_status(transport, &target, &service)
+        })
+        .await?
+        .answer()?;
+        return Ok(Custom(
+            Status::Ok,
+            Json(ControlResponse {
+                status: String::from("success"),
+                action,
+                hostname: request.hostname.clone(),
+                service: request.service.clone(),
+                dry_run: true,
+                replayed: false,
+                command,
+                current_state: Some(current_state),
+                final_state: None,
+                elapsed_ms: None,
+                exit_code: None,
+                count: 0,
+                data: vec![],
+                stderr: vec![],
+            }),
+        ));
+    }
+
+    let key = request.idempotency_key.as_deref();
+    if let Some(key) = key {
+        let fingerprint = format!("{} {} {}", action, request.hostname, request.service);
+        if let Claim::Replay(mut response) = idempotency.claim(user.id, key, &fingerprint)? {
+            response.replayed = true;
+            return Ok(Custom(Status::Ok, Json(*response)));
         }
-        _ => {
-            let error_response = GenericResponse {
-                status: String::from("fail"),
-                message: format!("Failed to stop '{}' on '{}'", service, host),
-            };
-            return Err(Custom(Status::InternalServerError, Json(error_response)));
+    }
+
+    let wait = request.wait.then(|| WaitFor::from_config(request.timeout_secs));
+    let outcome = spawn_action(
+        Arc::clone(transport.inner()),
+        target,
+        request.service.clone(),
+        action,
+        wait,
+    )
+    .await;
+    audit::record_with_pool(
+        pool,
+        user,
+        AuditRecord::new(action.as_str(), Some(&request.hostname), Some(&request.service))
+            .payload(request)
+            .exit_code(outcome.exit_code)
+            .outcome(&outcome.result, |(output, _)| Some(output.stdout.join("\n"))),
+    );
+    let (output, settled) = match outcome.result {
+        Ok(done) => done,
+        Err(e) => {
+            if let Some(key) = key {
+                idempotency.release(user.id, key);
+            }
+            return Err(e);
         }
+    };
+
+    let response = ControlResponse {
+        status: String::from("success"),
+        action,
+        hostname: request.hostname.clone(),
+        service: request.service.clone(),
+        dry_run: false,
+        replayed: false,
+        command,
+        current_state: None,
+        final_state: settled.map(|settled| settled.final_state.to_string()),
+        elapsed_ms: Some(outcome.elapsed_ms),
+        exit_code: output.exit_code,
+        count: output.stdout.len(),
+        data: output.stdout,
+        stderr: output.stderr,
+    };
+    if let Some(key) = key {
+        idempotency.complete(user.id, key, &response);
     }
+    Ok(Custom(Status::Ok, Json(response)))
 }
 
+/// Run `start`, `stop` or `restart` on every service matching a selector
+/// - `mode: parallel`: all targets, `max_parallel` at a time
+/// - `mode: rolling`: batches of `max_parallel`, stopping after a batch with a
+///   failure; the remaining targets are reported as `skipped`
+/// Each target that ran gets its own `audit_log` row
+#[post("/systemctl/bulk", data = "<request>")]
+pub async fn bulk_control_api(
+    request: Json<BulkRequest>,
+    transport: &State<SharedTransport>,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<BulkResponse>>> {
+    user.require(Permission::Operate)?;
+    let targets = {
+        let conn = &mut get_conn(pool)?;
+        resolve_targets(conn, &request.selector, &user.scope)?
+    };
+    let action = request.action;
 
-/// `systemctl restart -H <host> <service>`
-/// NOTE: A successful restart does not mean that the service restarted
-/// successfully. It means that the command was sent successfully.
-/// - To check for successful restart, call `service_status_api`
-#[get("/systemctl/restart?<host>&<service>")]
-pub async fn restart_service_api(
-    host: &str,
-    service: &str,
-) -> Result<Custom<Json<SystemctlResponse>>, Custom<Json<GenericResponse>>> {
-    let restart = restart_service(&host, &service);
-    match restart {
-        Ok(vec) => {
-            return Ok(Custom(
-                Status::Ok,
-                Json(SystemctlResponse {
-                    status: String::from("success"),
-                    count: vec.len(),
-                    data: vec,
-                }),
-            ));
-        }
-        _ => {
-            let error_response = GenericResponse {
-                status: String::from("fail"),
-                message: format!("Failed to restart '{}' on '{}'", service, host),
-            };
-            return Err(Custom(Status::InternalServerError, Json(error_response)));
+    let runs = if request.dry_run {
+        targets
+            .into_iter()
+            .map(|target| TargetRun {
+                target,
+                status: TargetStatus::Planned,
+                outcome: None,
+            })
+            .collect()
+    } else {
+        let limit = read_config_file().control.bulk_max_parallel;
+        let max_parallel = request.max_parallel.map_or(limit, |max| max.min(limit));
+        let wait = request.wait.then(|| WaitFor::from_config(request.timeout_secs));
+        run_bulk(
+            Arc::clone(transport.inner()),
+            targets,
+            action,
+            request.mode,
+            max_parallel,
+            wait,
+        )
+        .await
+    };
+
+    let mut data = Vec::with_capacity(runs.len());
+    for run in runs {
+        let target = run.target;
+        let mut result = BulkTargetResult {
+            hostname: target.host.hostname.clone(),
+            service: target.service.clone(),
+            status: run.status,
+            command: describe_command(&target.host, &target.service, action),
+            exit_code: None,
+            final_state: None,
+            elapsed_ms: None,
+            message: None,
+            data: vec![],
+            stderr: vec![],
+        };
+        if let Some(outcome) = run.outcome {
+            audit::record_with_pool(
+                pool,
+                &user,
+                AuditRecord::new(action.as_str(), Some(&result.hostname), Some(&result.service))
+                    .payload(&request.0)
+                    .exit_code(outcome.exit_code)
+                    .outcome(&outcome.result, |(output, _)| Some(output.stdout.join("\n"))),
+            );
+            result.exit_code = outcome.exit_code;
+            result.elapsed_ms = Some(outcome.elapsed_ms);
+            match outcome.result {
+                Ok((output, settled)) => {
+                    result.final_state =
+                        settled.map(|settled| settled.final_state.to_string());
+                    result.data = output.stdout;
+                    result.stderr = output.stderr;
+                }
+                Err(e) => result.message = Some(e.to_string()),
+            }
         }
+        data.push(result);
     }
+
+    let count = |status: TargetStatus| data.iter().filter(|r| r.status == status).count();
+    let (succeeded, failed, skipped) = (
+        count(TargetStatus::Success),
+        count(TargetStatus::Failed),
+        count(TargetStatus::Skipped),
+    );
+    Ok(Custom(
+        Status::Ok,
+        Json(BulkResponse {
+            status: String::from(if failed == 0 { "success" } else { "fail" }),
+            action,
+            mode: request.mode,
+            dry_run: request.dry_run,
+            total: data.len(),
+            succeeded,
+            failed,
+            skipped,
+            data,
+        }),
+    ))
 }
 
 /// `systemctl -H <host> list-unit-files [grep enabled | awk '{print $1}']`
@@ -155,24 +318,16 @@ pub async fn restart_service_api(
 pub async fn unit_files_api(
     host: &str,
     enabled_only: Option<bool>,
-) -> Result<Custom<Json<SystemctlResponse>>, Custom<Json<GenericResponse>>> {
-    match list_unit_files(&host, enabled_only) {
-        Ok(vec) => {
-            return Ok(Custom(
-                Status::Ok,
-                Json(SystemctlResponse {
-                    status: String::from("success"),
-                    count: vec.len(),
-                    data: vec,
-                }),
-            ));
-        }
-        _ => {
-            let error_response = GenericResponse {
-                status: String::from("fail"),
-                message: format!("Failed to get unit files on '{}'", host),
-            };
-            return Err(Custom(Status::InternalServerError, Json(error_response)));
-        }
-    }
+    transport: &State<SharedTransport>,
+    user: AuthUser,
+) -> Result<Custom<Json<SystemctlResponse>>> {
+    user.require(Permission::View)?;
+    user.require_host(host)?;
+    let target = lookup_host(host)?;
+    let unit_files = spawn_call(Arc::clone(transport.inner()), move |transport| {
+        list_unit_files(transport, &target, enabled_only)
+    })
+    .await?
+    .expect_exit(&[0])?;
+    Ok(Custom(Status::Ok, Json(SystemctlResponse::from(unit_files))))
 }
@@ -0,0 +1,107 @@
+use crate::audit::{self, AuditRecord};
+use crate::auth::{mint_token, AuthUser};
+use crate::database::crudops::{get_api_token_by_id, get_api_tokens_for_user, revoke_api_token};
+use crate::database::pool::{get_conn, DbPool};
+use crate::error::{Result, SvcmonError};
+use crate::response::{GenericResponse, TokenRequest, TokenResponse, TokensResponse};
+use chrono::{Duration, Utc};
+use rocket::{delete, get, http::Status, post, response::status::Custom, serde::json::Json, State};
+use serde_json::json;
+
+/// Mint an API token for the caller
+/// Only from a login session, and only with scopes the caller holds
+#[post("/tokens/mint", data = "<request>")]
+pub fn mint_token_api(
+    request: Json<TokenRequest>,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<TokenResponse>>> {
+    if user.token_id.is_some() {
+        return Err(SvcmonError::Forbidden(String::from(
+            "API tokens cannot mint other tokens; log in first",
+        )));
+    }
+    if request.name.is_empty() || request.scopes.is_empty() {
+        return Err(SvcmonError::Validation(String::from(
+            "`name` and `scopes` must not be empty",
+        )));
+    }
+    for scope in &request.scopes {
+        user.require(*scope)?;
+    }
+    let expires_at = request
+        .expires_in_days
+        .map(|days| Utc::now().naive_utc() + Duration::days(i64::from(days)));
+
+    let conn = &mut get_conn(pool)?;
+    let result = mint_token(conn, user.id, &request.name, &request.scopes, expires_at);
+    audit::record(
+        conn,
+        &user,
+        AuditRecord::new("mint_token", None, None)
+            .payload(&json!({
+                "name": request.name,
+                "scopes": request.scopes,
+                "expires_in_days": request.expires_in_days,
+            }))
+            .outcome(&result, |(_, api_token)| Some(format!("prefix {}", api_token.prefix))),
+    );
+    let (token, api_token) = result?;
+    Ok(Custom(
+        Status::Created,
+        Json(TokenResponse {
+            status: String::from("success"),
+            token,
+            data: api_token,
+        }),
+    ))
+}
+
+/// The caller's API tokens, newest first
+#[get("/tokens/all")]
+pub fn get_tokens_api(
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<TokensResponse>>> {
+    let conn = &mut get_conn(pool)?;
+    let tokens = get_api_tokens_for_user(conn, user.id)?;
+    Ok(Custom(
+        Status::Ok,
+        Json(TokensResponse {
+            status: String::from("success"),
+            count: tokens.len(),
+            data: tokens,
+        }),
+    ))
+}
+
+/// Revoke one of the caller's tokens; admins may revoke anyone's
+#[delete("/tokens/<id>")]
+pub fn revoke_token_api(
+    id: i64,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<GenericResponse>>> {
+    let conn = &mut get_conn(pool)?;
+    let api_token = get_api_token_by_id(conn, id)?;
+    if api_token.user_id != user.id && !user.permissions.is_admin() {
+        return Err(SvcmonError::NotFound(format!("no API token with `id` = {}", id)));
+    }
+
+    let result = revoke_api_token(conn, id);
+    audit::record(
+        conn,
+        &user,
+        AuditRecord::new("revoke_token", None, None)
+            .payload(&json!({ "id": id, "name": api_token.name }))
+            .outcome(&result, |_| None),
+    );
+    result?;
+    Ok(Custom(
+        Status::Ok,
+        Json(GenericResponse {
+            status: String::from("success"),
+            message: format!("API token, '{}', revoked", api_token.name),
+        }),
+    ))
+}
@@ -0,0 +1,410 @@
+use crate::audit::{self, AuditRecord};
+use crate::auth::AuthUser;
+use crate::database::crudops::{
+    add_group_to_groups, add_user_to_users, delete_group, delete_user, get_all_groups,
+    get_all_users, get_group_by_id, get_group_by_name, get_user_by_id, get_user_by_username,
+    update_group, update_user,
+};
+use crate::database::pool::{get_conn, DbPool};
+use crate::error::{Result, SvcmonError};
+use crate::rbac::Permission;
+use crate::response::{
+    GenericResponse, GroupRequest, GroupResponse, GroupUpdateRequest, GroupsResponse,
+    MoveUserRequest, UserRequest, UserResponse, UserUpdateRequest, UsersResponse,
+};
+use rocket::{
+    delete, get, http::Status, patch, post, response::status::Custom, serde::json::Json, State,
+};
+use serde_json::json;
+
+/*----------------------------------USERS-------------------------------------*/
+
+/// Get all users
+#[get("/users/all")]
+pub fn get_all_users_api(
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<UsersResponse>>> {
+    user.require(Permission::Admin)?;
+    let conn = &mut get_conn(pool)?;
+    let users = get_all_users(conn)?;
+    Ok(Custom(
+        Status::Ok,
+        Json(UsersResponse {
+            status: String::from("success"),
+            data: users,
+        }),
+    ))
+}
+
+/// Get a user by `id`
+#[get("/users/<id>")]
+pub fn get_user_api(
+    id: i64,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<UserResponse>>> {
+    user.require(Permission::Admin)?;
+    let conn = &mut get_conn(pool)?;
+    let found = get_user_by_id(conn, id)?;
+    Ok(Custom(
+        Status::Ok,
+        Json(UserResponse {
+            status: String::from("success"),
+            data: found,
+        }),
+    ))
+}
+
+/// Create a user; `password` is stored as an argon2 hash
+/// Answers 409 if the name is taken
+#[post("/users/add_user", data = "<new_user>")]
+pub fn add_user_api(
+    new_user: Json<UserRequest>,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<UserResponse>>> {
+    user.require(Permission::Admin)?;
+    let new_user = new_user.0;
+    if new_user.name.is_empty() || new_user.password.is_empty() {
+        return Err(SvcmonError::Validation(String::from(
+            "`name` and `password` must not be empty",
+        )));
+    }
+
+    let conn = &mut get_conn(pool)?;
+    match get_user_by_username(conn, &new_user.name) {
+        Ok(_) => {
+            return Err(SvcmonError::Conflict(format!(
+                "User, '{}', already exists",
+                new_user.name
+            )))
+        }
+        Err(SvcmonError::NotFound(_)) => {}
+        Err(e) => return Err(e),
+    }
+    if let Some(group_accts_id) = new_user.group_accts_id {
+        get_group_by_id(conn, group_accts_id)?;
+    }
+
+    let result = add_user_to_users(
+        conn,
+        &new_user.name,
+        &new_user.password,
+        new_user.group_accts_id,
+        new_user.active,
+    );
+    // Never the password
+    let payload = json!({
+        "name": new_user.name,
+        "group_accts_id": new_user.group_accts_id,
+        "active": new_user.active,
+    });
+    audit::record(
+        conn,
+        &user,
+        AuditRecord::new("add_user", None, None)
+            .payload(&payload)
+            .outcome(&result, |_| None),
+    );
+    result?;
+    let created = get_user_by_username(conn, &new_user.name)?;
+    Ok(Custom(
+        Status::Created,
+        Json(UserResponse {
+            status: String::from("success"),
+            data: created,
+        }),
+    ))
+}
+
+/// Change a user's name, password, group or `active` flag
+#[patch("/users/<id>", data = "<changes>")]
+pub fn update_user_api(
+    id: i64,
+    changes: Json<UserUpdateRequest>,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<UserResponse>>> {
+    user.require(Permission::Admin)?;
+    if id == user.id && changes.active == Some(false) {
+        return Err(SvcmonError::Validation(String::from(
+            "you cannot deactivate your own account",
+        )));
+    }
+
+    let conn = &mut get_conn(pool)?;
+    let result = update_user(
+        conn,
+        id,
+        changes.name.as_deref(),
+        changes.password.as_deref(),
+        changes.group_accts_id,
+        changes.active,
+    );
+    // Only whether the password changed, never the password
+    let payload = json!({
+        "id": id,
+        "name": changes.name,
+        "password_changed": changes.password.is_some(),
+        "group_accts_id": changes.group_accts_id,
+        "active": changes.active,
+    });
+    audit::record(
+        conn,
+        &user,
+        AuditRecord::new("update_user", None, None)
+            .payload(&payload)
+            .outcome(&result, |_| None),
+    );
+    result?;
+    let updated = get_user_by_id(conn, id)?;
+    Ok(Custom(
+        Status::Ok,
+        Json(UserResponse {
+            status: String::from("success"),
+            data: updated,
+        }),
+    ))
+}
+
+/// Deactivate a user; its sessions stop working on the next request
+#[post("/users/<id>/deactivate")]
+pub fn deactivate_user_api(
+    id: i64,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<GenericResponse>>> {
+    user.require(Permission::Admin)?;
+    if id == user.id {
+        return Err(SvcmonError::Validation(String::from(
+            "you cannot deactivate your own account",
+        )));
+    }
+
+    let conn = &mut get_conn(pool)?;
+    let result = update_user(conn, id, None, None, None, Some(false));
+    audit::record(
+        conn,
+        &user,
+        AuditRecord::new("deactivate_user", None, None)
+            .payload(&json!({ "id": id }))
+            .outcome(&result, |_| None),
+    );
+    result?;
+    Ok(Custom(
+        Status::Ok,
+        Json(GenericResponse {
+            status: String::from("success"),
+            message: format!("User, {}, deactivated", id),
+        }),
+    ))
+}
+
+/// Move a user to another group, or out of any group with `null`
+#[patch("/users/<id>/move", data = "<target>")]
+pub fn move_user_api(
+    id: i64,
+    target: Json<MoveUserRequest>,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<GenericResponse>>> {
+    user.require(Permission::Admin)?;
+    let conn = &mut get_conn(pool)?;
+    let result = update_user(conn, id, None, None, Some(target.group_accts_id), None);
+    audit::record(
+        conn,
+        &user,
+        AuditRecord::new("move_user", None, None)
+            .payload(&json!({ "id": id, "group_accts_id": target.group_accts_id }))
+            .outcome(&result, |_| None),
+    );
+    result?;
+    let message = match target.group_accts_id {
+        Some(group_accts_id) => format!("User, {}, moved to group {}", id, group_accts_id),
+        None => format!("User, {}, removed from its group", id),
+    };
+    Ok(Custom(
+        Status::Ok,
+        Json(GenericResponse {
+            status: String::from("success"),
+            message,
+        }),
+    ))
+}
+
+/// Delete a user
+#[delete("/users/<id>")]
+pub fn delete_user_api(
+    id: i64,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<GenericResponse>>> {
+    user.require(Permission::Admin)?;
+    if id == user.id {
+        return Err(SvcmonError::Validation(String::from(
+            "you cannot delete your own account",
+        )));
+    }
+
+    let conn = &mut get_conn(pool)?;
+    let result = delete_user(conn, id);
+    audit::record(
+        conn,
+        &user,
+        AuditRecord::new("delete_user", None, None)
+            .payload(&json!({ "id": id }))
+            .outcome(&result, |_| None),
+    );
+    result?;
+    Ok(Custom(
+        Status::Ok,
+        Json(GenericResponse {
+            status: String::from("success"),
+            message: format!("User, {}, successfully removed", id),
+        }),
+    ))
+}
+
+/*----------------------------------GROUPS------------------------------------*/
+
+/// Get all groups
+#[get("/groups/all")]
+pub fn get_all_groups_api(
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<GroupsResponse>>> {
+    user.require(Permission::Admin)?;
+    let conn = &mut get_conn(pool)?;
+    let groups = get_all_groups(conn)?;
+    Ok(Custom(
+        Status::Ok,
+        Json(GroupsResponse {
+            status: String::from("success"),
+            data: groups,
+        }),
+    ))
+}
+
+/// Get a group by `id`
+#[get("/groups/<id>")]
+pub fn get_group_api(
+    id: i64,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<GroupResponse>>> {
+    user.require(Permission::Admin)?;
+    let conn = &mut get_conn(pool)?;
+    let group = get_group_by_id(conn, id)?;
+    Ok(Custom(
+        Status::Ok,
+        Json(GroupResponse {
+            status: String::from("success"),
+            data: group,
+        }),
+    ))
+}
+
+/// Create a group, optionally below `parent_id`
+/// Answers 409 if the name is taken
+#[post("/groups/add_group", data = "<new_group>")]
+pub fn add_group_api(
+    new_group: Json<GroupRequest>,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<GroupResponse>>> {
+    user.require(Permission::Admin)?;
+    let new_group = new_group.0;
+    if new_group.name.is_empty() {
+        return Err(SvcmonError::Validation(String::from("`name` must not be empty")));
+    }
+
+    let conn = &mut get_conn(pool)?;
+    match get_group_by_name(conn, &new_group.name) {
+        Ok(_) => {
+            return Err(SvcmonError::Conflict(format!(
+                "Group, '{}', already exists",
+                new_group.name
+            )))
+        }
+        Err(SvcmonError::NotFound(_)) => {}
+        Err(e) => return Err(e),
+    }
+    if let Some(parent_id) = new_group.parent_id {
+        get_group_by_id(conn, parent_id)?;
+    }
+
+    let result = add_group_to_groups(conn, new_group.parent_id, &new_group.name);
+    audit::record(
+        conn,
+        &user,
+        AuditRecord::new("add_group", None, None)
+            .payload(&json!({ "name": new_group.name, "parent_id": new_group.parent_id }))
+            .outcome(&result, |_| None),
+    );
+    result?;
+    let created = get_group_by_name(conn, &new_group.name)?;
+    Ok(Custom(
+        Status::Created,
+        Json(GroupResponse {
+            status: String::from("success"),
+            data: created,
+        }),
+    ))
+}
+
+/// Rename a group or move it below another one
+#[patch("/groups/<id>", data = "<changes>")]
+pub fn update_group_api(
+    id: i64,
+    changes: Json<GroupUpdateRequest>,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<GroupResponse>>> {
+    user.require(Permission::Admin)?;
+    let conn = &mut get_conn(pool)?;
+    let result = update_group(conn, id, changes.name.as_deref(), changes.parent_id);
+    audit::record(
+        conn,
+        &user,
+        AuditRecord::new("update_group", None, None)
+            .payload(&json!({ "id": id, "name": changes.name, "parent_id": changes.parent_id }))
+            .outcome(&result, |_| None),
+    );
+    result?;
+    let updated = get_group_by_id(conn, id)?;
+    Ok(Custom(
+        Status::Ok,
+        Json(GroupResponse {
+            status: String::from("success"),
+            data: updated,
+        }),
+    ))
+}
+
+/// Delete a group; its members and subgroups are left without one
+#[delete("/groups/<id>")]
+pub fn delete_group_api(
+    id: i64,
+    pool: &State<DbPool>,
+    user: AuthUser,
+) -> Result<Custom<Json<GenericResponse>>> {
+    user.require(Permission::Admin)?;
+    let conn = &mut get_conn(pool)?;
+    let result = delete_group(conn, id);
+    audit::record(
+        conn,
+        &user,
+        AuditRecord::new("delete_group", None, None)
+            .payload(&json!({ "id": id }))
+            .outcome(&result, |_| None),
+    );
+    result?;
+    Ok(Custom(
+        Status::Ok,
+        Json(GenericResponse {
+            status: String::from("success"),
+            message: format!("Group, {}, successfully removed", id),
+        }),
+    ))
+}
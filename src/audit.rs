@@ -0,0 +1,112 @@
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::auth::AuthUser;
+use crate::database::crudops;
+use crate::database::models::NewAuditEntry;
+use crate::database::pool::{get_conn, DbPool};
+use crate::database::DbConnection;
+use crate::error::{Result, SvcmonError};
+
+// AUDIT RECORD
+/******************************************************************************/
+/// What one mutating request did, written to `audit_log` by `record`
+#[derive(Debug)]
+pub struct AuditRecord<'a> {
+    /// e.g. `restart`, `update_host`, `add_user`
+    pub action: &'a str,
+    pub hostname: Option<&'a str>,
+    pub service: Option<&'a str>,
+    pub payload: Option<String>,
+    pub output: Option<String>,
+    pub exit_code: Option<i32>,
+    pub success: bool,
+}
+
+impl<'a> AuditRecord<'a> {
+    pub fn new(action: &'a str, hostname: Option<&'a str>, service: Option<&'a str>) -> Self {
+        AuditRecord {
+            action,
+            hostname,
+            service,
+            payload: None,
+            output: None,
+            exit_code: None,
+            success: false,
+        }
+    }
+
+    /// Store the request body or parameters as JSON
+    pub fn payload<T: Serialize>(mut self, payload: &T) -> Self {
+        self.payload = serde_json::to_string(payload).ok();
+        self
+    }
+
+    /// Store the exit code of the command the action ran
+    pub fn exit_code(mut self, exit_code: Option<i32>) -> Self {
+        self.exit_code = exit_code;
+        self
+    }
+
+    /// Store whether the action succeeded; `output` describes a success and
+    /// the error message describes a failure
+    pub fn outcome<T>(
+        mut self,
+        result: &Result<T>,
+        output: impl FnOnce(&T) -> Option<String>,
+    ) -> Self {
+        match result {
+            Ok(value) => {
+                self.success = true;
+                self.output = output(value);
+            }
+            Err(e) => {
+                self.success = false;
+                self.output = Some(e.to_string());
+            }
+        }
+        self
+    }
+}
+
+/// Append `record` to `audit_log` on behalf of `user`, on the handler's own
+/// connection
+/// The action has already happened, so a failed write is reported on stderr
+/// rather than failing the request
+pub fn record(conn: &mut DbConnection, user: &AuthUser, record: AuditRecord) {
+    let written = crudops::insert_audit_entry(conn, &new_entry(user, &record));
+    if let Err(e) = written {
+        warn(user, &record, e);
+    }
+}
+
+/// `record` for handlers that hold no connection of their own
+pub fn record_with_pool(pool: &DbPool, user: &AuthUser, record: AuditRecord) {
+    match get_conn(pool) {
+        Ok(mut conn) => self::record(&mut conn, user, record),
+        Err(e) => warn(user, &record, e),
+    }
+}
+
+fn new_entry<'a>(user: &'a AuthUser, record: &'a AuditRecord) -> NewAuditEntry<'a> {
+    NewAuditEntry {
+        actor: &user.name,
+        user_id: Some(user.id),
+        token_id: user.token_id,
+        action: record.action,
+        hostname: record.hostname,
+        service: record.service,
+        payload: record.payload.as_deref(),
+        output: record.output.as_deref(),
+        exit_code: record.exit_code,
+        success: record.success,
+        created_at: Utc::now().naive_utc(),
+    }
+}
+
+fn warn(user: &AuthUser, record: &AuditRecord, e: SvcmonError) {
+    eprintln!(
+        "WARNING: could not write audit entry '{}' by {}: {}",
+        record.action, user.name, e
+    );
+}
@@ -0,0 +1,360 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chrono::{NaiveDateTime, Utc};
+use rocket::http::{Cookie, CookieJar, SameSite};
+use rocket::request::{FromRequest, Outcome, Request};
+use std::sync::OnceLock;
+use sha2::{Digest, Sha256};
+
+use crate::config::read_config_file;
+use crate::database::crudops;
+use crate::database::models::{ApiToken, NewApiToken, User};
+use crate::database::pool::{get_conn, DbPool};
+use crate::database::DbConnection;
+use crate::error::{Result, SvcmonError};
+use crate::rbac::{
+    effective_permissions, join_scopes, parse_scopes, scope_for, Permission, Permissions, Scope,
+};
+
+/// Private (encrypted and signed) cookie holding
+/// `<user id>:<expiry>:<password stamp>`
+pub const SESSION_COOKIE: &str = "svcmon_session";
+/// API tokens look like `svcmon_<prefix>_<secret>`
+pub const TOKEN_PREFIX: &str = "svcmon_";
+/// `last_used` is only written when older than this, so a busy token does
+/// not cost a write on every request
+const TOUCH_INTERVAL_SECS: i64 = 60;
+
+// PASSWORDS
+/******************************************************************************/
+/// Argon2id hash in PHC string format, with a fresh random salt
+pub fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| SvcmonError::Validation(format!("cannot hash password: {}", e)))
+}
+
+/// `false` for a wrong password and for anything that is not a PHC hash,
+/// such as passwords stored in plain text before hashing was introduced
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    match PasswordHash::new(hash) {
+        Ok(parsed) => Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Hash checked for unknown usernames, so they take as long as real ones
+fn dummy_hash() -> &'static str {
+    static DUMMY: OnceLock<String> = OnceLock::new();
+    DUMMY.get_or_init(|| hash_password("svcmon-dummy-password").unwrap_or_default())
+}
+
+// LOGIN
+/******************************************************************************/
+/// Check `name` and `password` against `users`
+/// Unknown users, wrong passwords and inactive accounts get the same error,
+/// after the same argon2 verification
+pub fn authenticate(conn: &mut DbConnection, name: &str, password: &str) -> Result<User> {
+    let invalid = || SvcmonError::Unauthorized(String::from("invalid username or password"));
+
+    let user = match crudops::get_user_by_username(conn, name) {
+        Ok(user) => user,
+        Err(SvcmonError::NotFound(_)) => {
+            verify_password(password, dummy_hash());
+            return Err(invalid());
+        }
+        Err(e) => return Err(e),
+    };
+    let verified = verify_password(password, &user.password);
+    if !user.active || !verified {
+        return Err(invalid());
+    }
+
+    Ok(user)
+}
+
+/// Set the session cookie for `user`
+pub fn start_session(cookies: &CookieJar<'_>, user: &User) {
+    let config = &read_config_file().auth;
+    let ttl = config.session_ttl_secs as i64;
+    let expires = Utc::now().timestamp() + ttl;
+    let value = format!("{}:{}:{}", user.id, expires, password_stamp(&user.password));
+    let cookie = Cookie::build((SESSION_COOKIE, value))
+        .http_only(true)
+        .secure(config.secure_cookie)
+        .same_site(SameSite::Strict)
+        .max_age(rocket::time::Duration::seconds(ttl));
+    cookies.add_private(cookie);
+}
+
+/// Only removes the cookie from this client; a copy stays valid until it
+/// expires, the password changes or the user is disabled or deleted
+pub fn end_session(cookies: &CookieJar<'_>) {
+    cookies.remove_private(SESSION_COOKIE);
+}
+
+/// Ties a session to the password hash it was started with; every password
+/// change hashes with a new salt, which ends the user's other sessions
+fn password_stamp(password_hash: &str) -> String {
+    token_digest(password_hash)[..16].to_string()
+}
+
+// API TOKENS
+/******************************************************************************/
+/// Create a token for `user_id` limited to `scopes`
+/// Returns the full token, which is never stored and cannot be shown again,
+/// and its row in `api_tokens`
+pub fn mint_token(
+    conn: &mut DbConnection,
+    user_id: i64,
+    name: &str,
+    scopes: &[Permission],
+    expires_at: Option<NaiveDateTime>,
+) -> Result<(String, ApiToken)> {
+    let prefix = random_hex(6);
+    let secret = random_hex(32);
+    let secret_hash = token_digest(&secret);
+    let scopes = join_scopes(scopes);
+
+    let new_token = NewApiToken {
+        user_id,
+        name,
+        prefix: &prefix,
+        secret_hash: &secret_hash,
+        scopes: &scopes,
+        expires_at,
+    };
+    let api_token = crudops::insert_api_token(conn, &new_token)?;
+    Ok((format!("{}{}_{}", TOKEN_PREFIX, prefix, secret), api_token))
+}
+
+fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+    to_hex(&bytes)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Hex SHA-256 of a token secret
+/// Secrets are 32 random bytes, so a fast hash is enough and keeps Bearer
+/// requests cheap; argon2 is for passwords people choose
+fn token_digest(secret: &str) -> String {
+    to_hex(&Sha256::digest(secret.as_bytes()))
+}
+
+/// `secret` matches `secret_hash`, compared in constant time
+/// Tokens minted before digests were introduced still hold an argon2 hash
+fn verify_token(secret: &str, secret_hash: &str) -> bool {
+    if secret_hash.starts_with('$') {
+        return verify_password(secret, secret_hash);
+    }
+    let digest = token_digest(secret);
+    digest.len() == secret_hash.len()
+        && digest
+            .bytes()
+            .zip(secret_hash.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+// REQUEST GUARD
+/******************************************************************************/
+/// The logged-in caller
+/// Add it as a route argument to require an active user with either a valid
+/// session cookie or an `Authorization: Bearer` API token; otherwise the
+/// route answers 401
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub id: i64,
+    pub name: String,
+    pub group_accts_id: Option<i64>,
+    /// The API token used for this request, if any
+    pub token_id: Option<i64>,
+    /// Inherited from the user's group and its ancestors, and narrowed to the
+    /// token's scopes for token requests
+    pub permissions: Permissions,
+    /// Hosts and services owned by the user's group and its subgroups; not
+    /// narrowed by token scopes
+    pub scope: Scope,
+}
+
+impl AuthUser {
+    /// `SvcmonError::Forbidden` (403) unless the user holds `permission`
+    pub fn require(&self, permission: Permission) -> Result<()> {
+        if self.permissions.allows(permission) {
+            Ok(())
+        } else {
+            Err(SvcmonError::Forbidden(format!(
+                "'{}' lacks the '{}' permission",
+                self.name, permission
+            )))
+        }
+    }
+
+    /// `SvcmonError::Forbidden` (403) unless `hostname` is in the user's scope
+    pub fn require_host(&self, hostname: &str) -> Result<()> {
+        if self.scope.allows_host(hostname) {
+            Ok(())
+        } else {
+            Err(SvcmonError::Forbidden(format!(
+                "'{}' has no access to host '{}'",
+                self.name, hostname
+            )))
+        }
+    }
+
+    /// `SvcmonError::Forbidden` (403) unless `service` on `hostname` is in the
+    /// user's scope
+    pub fn require_service(&self, hostname: &str, service: &str) -> Result<()> {
+        if self.scope.allows_service(hostname, service) {
+            Ok(())
+        } else {
+            Err(SvcmonError::Forbidden(format!(
+                "'{}' has no access to '{}' on host '{}'",
+                self.name, service, hostname
+            )))
+        }
+    }
+}
+
+/// Why a guard rejected the request, for the JSON catchers in `api`
+#[derive(Debug, Default)]
+pub struct GuardFailure(pub Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AuthUser {
+    type Error = SvcmonError;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let user = match req.headers().get_one("Authorization") {
+            Some(authorization) => user_from_token(req, authorization),
+            None => user_from_session(req),
+        };
+        match user {
+            Ok(user) => Outcome::Success(user),
+            Err(e) => {
+                req.local_cache(|| GuardFailure(Some(e.to_string())));
+                Outcome::Error((e.status(), e))
+            }
+        }
+    }
+}
+
+fn user_from_session(req: &Request<'_>) -> Result<AuthUser> {
+    let cookie = req
+        .cookies()
+        .get_private(SESSION_COOKIE)
+        .ok_or_else(|| SvcmonError::Unauthorized(String::from("log in at /api/auth/login")))?;
+
+    let malformed = || SvcmonError::Unauthorized(String::from("malformed session"));
+    let mut fields = cookie.value().splitn(3, ':');
+    let user_id = fields.next().and_then(|id| id.parse::<i64>().ok()).ok_or_else(malformed)?;
+    let expires = fields
+        .next()
+        .and_then(|expires| expires.parse::<i64>().ok())
+        .ok_or_else(malformed)?;
+    let stamp = fields.next().ok_or_else(malformed)?;
+    if expires < Utc::now().timestamp() {
+        return Err(SvcmonError::Unauthorized(String::from("session expired")));
+    }
+
+    let conn = &mut get_conn(request_pool(req)?)?;
+    let user = load_user(conn, user_id)?;
+    if stamp != password_stamp(&user.password) {
+        return Err(SvcmonError::Unauthorized(String::from(
+            "password changed; log in again",
+        )));
+    }
+    auth_user(conn, user, None)
+}
+
+fn user_from_token(req: &Request<'_>, authorization: &str) -> Result<AuthUser> {
+    let invalid = || SvcmonError::Unauthorized(String::from("invalid API token"));
+
+    let token = authorization
+        .strip_prefix("Bearer ")
+        .ok_or_else(|| {
+            SvcmonError::Unauthorized(String::from("expected `Authorization: Bearer <token>`"))
+        })?
+        .trim();
+    let (prefix, secret) = token
+        .strip_prefix(TOKEN_PREFIX)
+        .and_then(|token| token.split_once('_'))
+        .ok_or_else(invalid)?;
+
+    let conn = &mut get_conn(request_pool(req)?)?;
+    let api_token = match crudops::get_api_token_by_prefix(conn, prefix) {
+        Ok(api_token) => api_token,
+        Err(SvcmonError::NotFound(_)) => return Err(invalid()),
+        Err(e) => return Err(e),
+    };
+    if !verify_token(secret, &api_token.secret_hash) {
+        return Err(invalid());
+    }
+    if api_token.revoked {
+        return Err(SvcmonError::Unauthorized(String::from("API token has been revoked")));
+    }
+    let now = Utc::now().naive_utc();
+    if api_token.expires_at.is_some_and(|expires_at| expires_at < now) {
+        return Err(SvcmonError::Unauthorized(String::from("API token has expired")));
+    }
+
+    let stale = api_token
+        .last_used
+        .is_none_or(|last_used| now - last_used >= chrono::Duration::seconds(TOUCH_INTERVAL_SECS));
+    if stale {
+        crudops::touch_api_token(conn, api_token.id, now)?;
+    }
+    let user = load_user(conn, api_token.user_id)?;
+    auth_user(conn, user, Some(&api_token))
+}
+
+fn request_pool<'r>(req: &'r Request<'_>) -> Result<&'r DbPool> {
+    req.rocket()
+        .state::<DbPool>()
+        .ok_or_else(|| SvcmonError::Unavailable(String::from("no database pool")))
+}
+
+/// The user behind a session or token, if they still exist and are active
+fn load_user(conn: &mut DbConnection, user_id: i64) -> Result<User> {
+    let user = match crudops::get_user_by_id(conn, user_id) {
+        Ok(user) => user,
+        Err(SvcmonError::NotFound(_)) => {
+            return Err(SvcmonError::Unauthorized(String::from("user no longer exists")))
+        }
+        Err(e) => return Err(e),
+    };
+    if !user.active {
+        return Err(SvcmonError::Unauthorized(String::from("account is disabled")));
+    }
+    Ok(user)
+}
+
+/// Build the `AuthUser` for `user`, narrowed to `api_token`'s scopes when the
+/// request used one
+fn auth_user(conn: &mut DbConnection, user: User, api_token: Option<&ApiToken>) -> Result<AuthUser> {
+
+    // Token scopes narrow what the caller may do, not which hosts they see
+    let permissions = effective_permissions(conn, user.group_accts_id)?;
+    let scope = scope_for(conn, user.group_accts_id, &permissions)?;
+    let permissions = match api_token {
+        Some(api_token) => permissions.restrict(&parse_scopes(&api_token.scopes)),
+        None => permissions,
+    };
+    Ok(AuthUser {
+        id: user.id,
+        name: user.name,
+        group_accts_id: user.group_accts_id,
+        token_id: api_token.map(|api_token| api_token.id),
+        permissions,
+        scope,
+    })
+}
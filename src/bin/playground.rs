@@ -1,4 +1,5 @@
 use svcmon::database::crudops::get_json_statuses;
+use svcmon::database::pool::{get_conn, init_pool};
 use svcmon::hosts::Hosts;
 
 pub fn main() {
@@ -11,7 +12,9 @@ pub fn main() {
         }
     }
 
-    let statuses = get_json_statuses();
+    let pool = init_pool().unwrap();
+    let conn = &mut get_conn(&pool).unwrap();
+    let statuses = get_json_statuses(conn);
 
     for status in statuses.unwrap() {
         println!("hostname: {}, service: {}, status: {}", 
@@ -1,12 +1,35 @@
+use std::process;
+use std::sync::Arc;
+
 use rocket_cors::{AllowedHeaders, AllowedOrigins, CorsOptions};
 
-use svcmon::api::host_handler::{get_all_hosts_api, update_host_api, 
+use svcmon::api::host_handler::{get_all_hosts_api, add_host_api, update_host_api, 
     remove_host_api, add_service_api, remove_service_api};
-use svcmon::api::isalive_api;
+use svcmon::api::{isalive_api, unauthorized_catcher};
+use svcmon::api::auth_handler::{login_api, logout_api};
+use svcmon::api::alert_handler::get_alerts_api;
+use svcmon::api::notify_handler::test_notify_api;
+use svcmon::api::remediation_handler::get_remediation_attempts_api;
+use svcmon::api::metrics_handler::metrics_api;
+use svcmon::api::maintenance_handler::{get_maintenance_api, add_maintenance_api,
+    delete_maintenance_api};
+use svcmon::api::audit_handler::get_audit_api;
+use svcmon::api::token_handler::{mint_token_api, get_tokens_api, revoke_token_api};
+use svcmon::api::user_handler::{get_all_users_api, get_user_api, add_user_api,
+    update_user_api, deactivate_user_api, move_user_api, delete_user_api,
+    get_all_groups_api, get_group_api, add_group_api, update_group_api,
+    delete_group_api};
+use svcmon::database::pool::init_pool;
+use svcmon::idempotency::IdempotencyCache;
+use svcmon::metrics::{CollectorMetrics, SharedMetrics};
+use svcmon::notify::{Notifier, SharedNotifier};
+use svcmon::scheduler::Scheduler;
+use svcmon::transport::{ConfiguredTransport, SharedTransport};
 use svcmon::api::service_handler::{restart_service_api, service_status_api,
-    unit_files_api, start_service_api, stop_service_api, show_service_api};
+    unit_files_api, start_service_api, stop_service_api, show_service_api,
+    bulk_control_api};
 use svcmon::api::db_handler::{update_status_api, get_latest_status_api,
-    get_latest_statuses_api};
+    get_latest_statuses_api, get_events_api};
 use svcmon::site::page_handler::{serve_files, serve_home_page,
     serve_index_page};
 
@@ -22,7 +45,7 @@ fn rocket() -> _ {
     let cors = CorsOptions::default()
         .allowed_origins(AllowedOrigins::all())
         .allowed_methods(
-            vec![Method::Get, Method::Post, Method::Put, Method::Patch]
+            vec![Method::Get, Method::Post, Method::Put, Method::Patch, Method::Delete]
                 .into_iter()
                 .map(From::from)
                 .collect(),
@@ -30,6 +53,14 @@ fn rocket() -> _ {
         .allowed_headers(AllowedHeaders::all())
         .allow_credentials(true);
 
+    let pool = match init_pool() {
+        Ok(pool) => pool,
+        Err(e) => {
+            eprintln!("ERROR: could not configure the database pool: {}", e);
+            process::exit(1);
+        }
+    };
+
     rocket::build()
         .configure(
             rocket::Config::figment()
@@ -37,13 +68,23 @@ fn rocket() -> _ {
                 .merge(("port", 8888)),
         )
         .manage(cors)
+        .manage(Arc::new(ConfiguredTransport::default()) as SharedTransport)
+        .manage(pool)
+        .manage(IdempotencyCache::default())
+        .manage(Arc::new(Notifier::default()) as SharedNotifier)
+        .manage(Arc::new(CollectorMetrics::default()) as SharedMetrics)
+        .attach(Scheduler::default())
+        .register("/api", catchers![unauthorized_catcher])
         .mount("/api", routes![isalive_api,])
+        .mount("/api", routes![login_api, logout_api])
         .mount("/api", routes![start_service_api,])
         .mount("/api", routes![stop_service_api,])
         .mount("/api", routes![restart_service_api,])
+        .mount("/api", routes![bulk_control_api,])
         .mount("/api", routes![service_status_api,])
         .mount("/api", routes![unit_files_api,])
         .mount("/api", routes![get_all_hosts_api,])
+        .mount("/api", routes![add_host_api,])
         .mount("/api", routes![update_host_api,])
         .mount("/api", routes![remove_host_api,])
         .mount("/api", routes![add_service_api,])
@@ -52,6 +93,19 @@ fn rocket() -> _ {
         .mount("/api", routes![update_status_api,])
         .mount("/api", routes![get_latest_status_api,])
         .mount("/api", routes![get_latest_statuses_api,])
+        .mount("/api", routes![get_events_api,])
+        .mount("/api", routes![get_all_users_api, get_user_api, add_user_api,
+            update_user_api, deactivate_user_api, move_user_api, delete_user_api])
+        .mount("/api", routes![get_audit_api,])
+        .mount("/api", routes![get_alerts_api,])
+        .mount("/api", routes![test_notify_api,])
+        .mount("/api", routes![get_remediation_attempts_api,])
+        .mount("/api", routes![get_maintenance_api, add_maintenance_api, delete_maintenance_api])
+        .mount("/api", routes![mint_token_api, get_tokens_api, revoke_token_api])
+        .mount("/api", routes![get_all_groups_api, get_group_api, add_group_api,
+            update_group_api, delete_group_api])
+        // Outside `/api` for Prometheus' default path; still needs a token
+        .mount("/", routes![metrics_api,])
         .mount("/", routes![serve_home_page,])
         .mount("/", routes![serve_index_page, serve_files])
 }
@@ -0,0 +1,131 @@
+use std::env;
+use std::io::{self, BufRead};
+use std::process;
+
+use chrono::Utc;
+use svcmon::config::read_config_file;
+use svcmon::database::crudops::{
+    add_group_to_groups, add_user_to_users, assign_host, grant_permission,
+};
+use svcmon::database::pool::{get_conn, init_pool};
+use svcmon::error::{Result, SvcmonError};
+use svcmon::rbac::Permission;
+use svcmon::retention::run_retention;
+
+const USAGE: &str = "\
+usage: svcmon <command>
+
+commands:
+  retention                 roll up and prune status history per [config.retention], then exit
+  add-user <name> [group]   add an active user; the password is read from stdin
+  add-group <name> [parent] add a group, optionally below a parent group id
+  grant <group> <perm>      grant view, operate, edit_hosts or admin to a group id
+                            and every group below it
+  assign <group> <host> [service]
+                            let a group id and the groups below it see and act on a
+                            host, or on one service of it";
+
+pub fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args.as_slice() {
+        ["retention"] => retention(),
+        ["add-user", name] => add_user(name, None),
+        ["add-user", name, group] => add_user(name, Some(*group)),
+        ["add-group", name] => add_group(name, None),
+        ["add-group", name, parent] => add_group(name, Some(*parent)),
+        ["grant", group, permission] => grant(group, permission),
+        ["assign", group, host] => assign(group, host, None),
+        ["assign", group, host, service] => assign(group, host, Some(*service)),
+        ["-h"] | ["--help"] | ["help"] => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("ERROR: {}", e);
+        process::exit(1);
+    }
+}
+
+/// `svcmon retention`
+fn retention() -> Result<()> {
+    let pool = init_pool()?;
+    let conn = &mut get_conn(&pool)?;
+    let report = run_retention(conn, &read_config_file().retention, Utc::now().naive_utc())?;
+    println!("{}", report);
+    Ok(())
+}
+
+/// `svcmon add-user <name> [group]`
+fn add_user(name: &str, group: Option<&str>) -> Result<()> {
+    let group_accts_id = group.map(parse_group_id).transpose()?;
+
+    let mut password = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut password)
+        .map_err(|e| SvcmonError::Validation(format!("cannot read password: {}", e)))?;
+    let password = password.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        return Err(SvcmonError::Validation(String::from("empty password")));
+    }
+
+    let pool = init_pool()?;
+    let conn = &mut get_conn(&pool)?;
+    add_user_to_users(conn, name, password, group_accts_id, Some(true))?;
+    println!("added user '{}'", name);
+    Ok(())
+}
+
+/// `svcmon add-group <name> [parent]`
+fn add_group(name: &str, parent: Option<&str>) -> Result<()> {
+    let parent_id = parent.map(parse_group_id).transpose()?;
+
+    let pool = init_pool()?;
+    let conn = &mut get_conn(&pool)?;
+    add_group_to_groups(conn, parent_id, name)?;
+    println!("added group '{}'", name);
+    Ok(())
+}
+
+/// `svcmon grant <group> <permission>`
+fn grant(group: &str, permission: &str) -> Result<()> {
+    let group_accts_id = parse_group_id(group)?;
+    let permission: Permission = permission.parse()?;
+
+    let pool = init_pool()?;
+    let conn = &mut get_conn(&pool)?;
+    match grant_permission(conn, group_accts_id, permission.as_str())? {
+        0 => println!("group {} already has '{}'", group_accts_id, permission),
+        _ => println!("granted '{}' to group {}", permission, group_accts_id),
+    }
+    Ok(())
+}
+
+/// `svcmon assign <group> <host> [service]`
+fn assign(group: &str, host: &str, service: Option<&str>) -> Result<()> {
+    let group_accts_id = parse_group_id(group)?;
+    let target = match service {
+        Some(service) => format!("'{}' on '{}'", service, host),
+        None => format!("'{}'", host),
+    };
+
+    let pool = init_pool()?;
+    let conn = &mut get_conn(&pool)?;
+    match assign_host(conn, group_accts_id, host, service)? {
+        0 => println!("group {} already owns {}", group_accts_id, target),
+        _ => println!("assigned {} to group {}", target, group_accts_id),
+    }
+    Ok(())
+}
+
+fn parse_group_id(id: &str) -> Result<i64> {
+    id.parse::<i64>()
+        .map_err(|_| SvcmonError::Validation(format!("group must be a `group_accts` id, got '{}'", id)))
+}
@@ -0,0 +1,249 @@
+use std::sync::Arc;
+
+use rocket::tokio::sync::Semaphore;
+use rocket::tokio::{self, task::JoinHandle};
+use serde::{Deserialize, Serialize};
+
+use crate::database::DbConnection;
+use crate::error::{Result, SvcmonError};
+use crate::hosts::{Host, Hosts};
+use crate::rbac::{owned_by, Scope};
+use crate::service::{spawn_action, ActionOutcome, ControlAction, WaitFor};
+use crate::transport::SharedTransport;
+
+// SELECTOR
+/******************************************************************************/
+/// Which services in `hosts.json` a bulk action applies to
+/// A host must match every field that is given; at least one of `hosts`,
+/// `tag` and `group_accts_id` is required
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Selector {
+    /// Hostnames
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    /// One of the host's `tags`
+    pub tag: Option<String>,
+    /// Hosts and services assigned to this group or any group below it
+    pub group_accts_id: Option<i64>,
+    /// Service name, or a glob with `*` and `?`, matched against the host's
+    /// `services`
+    pub service: String,
+}
+
+/// One service on one host
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub host: Host,
+    pub service: String,
+}
+
+/// Every service matching `selector` that `scope` allows, in `hosts.json`
+/// order
+pub fn resolve_targets(
+    conn: &mut DbConnection,
+    selector: &Selector,
+    scope: &Scope,
+) -> Result<Vec<Target>> {
+    if selector.hosts.is_empty() && selector.tag.is_none() && selector.group_accts_id.is_none() {
+        return Err(SvcmonError::Validation(String::from(
+            "select hosts with `hosts`, `tag` or `group_accts_id`",
+        )));
+    }
+    let group = selector
+        .group_accts_id
+        .map(|id| owned_by(conn, id))
+        .transpose()?;
+
+    let mut targets = vec![];
+    for host in Hosts::init_from_file()?.get_hosts() {
+        if !selector.hosts.is_empty() && !selector.hosts.contains(&host.hostname) {
+            continue;
+        }
+        if let Some(tag) = &selector.tag {
+            if !host.tags.contains(tag) {
+                continue;
+            }
+        }
+        for service in &host.services {
+            let selected = glob_match(&selector.service, service)
+                && group
+                    .as_ref()
+                    .is_none_or(|group| group.allows_service(&host.hostname, service))
+                && scope.allows_service(&host.hostname, service);
+            if selected {
+                targets.push(Target {
+                    host: host.clone(),
+                    service: service.clone(),
+                });
+            }
+        }
+    }
+
+    if targets.is_empty() {
+        return Err(SvcmonError::NotFound(format!(
+            "no service you may act on matches '{}'",
+            selector.service
+        )));
+    }
+    Ok(targets)
+}
+
+/// Shell-style match: `*` is any run of characters, `?` is any one
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // Let the last `*` swallow one more character
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+// RUN
+/******************************************************************************/
+/// How a bulk action works through its targets
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BulkMode {
+    /// Every target, `max_parallel` at a time, whatever fails
+    #[default]
+    Parallel,
+    /// Batches of `max_parallel`; no new batch starts after a failure
+    Rolling,
+}
+
+/// What happened to one target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetStatus {
+    Success,
+    Failed,
+    /// Not run because an earlier rolling batch failed
+    Skipped,
+    /// Dry run; would have been run
+    Planned,
+}
+
+/// Outcome of one target, in the order of `resolve_targets`
+#[derive(Debug)]
+pub struct TargetRun {
+    pub target: Target,
+    pub status: TargetStatus,
+    /// `None` for `Skipped` and `Planned`
+    pub outcome: Option<ActionOutcome>,
+}
+
+/// Run `action` on every target
+pub async fn run_bulk(
+    transport: SharedTransport,
+    targets: Vec<Target>,
+    action: ControlAction,
+    mode: BulkMode,
+    max_parallel: usize,
+    wait: Option<WaitFor>,
+) -> Vec<TargetRun> {
+    let max_parallel = max_parallel.max(1);
+    let mut runs = Vec::with_capacity(targets.len());
+    let batches: Vec<Vec<Target>> = match mode {
+        BulkMode::Parallel => vec![targets],
+        BulkMode::Rolling => targets.chunks(max_parallel).map(<[Target]>::to_vec).collect(),
+    };
+
+    let mut failed = false;
+    for batch in batches {
+        if failed {
+            runs.extend(batch.into_iter().map(|target| TargetRun {
+                target,
+                status: TargetStatus::Skipped,
+                outcome: None,
+            }));
+            continue;
+        }
+
+        let permits = Arc::new(Semaphore::new(max_parallel));
+        let handles: Vec<(Target, JoinHandle<ActionOutcome>)> = batch
+            .into_iter()
+            .map(|target| {
+                let transport = Arc::clone(&transport);
+                let permits = Arc::clone(&permits);
+                let (host, service) = (target.host.clone(), target.service.clone());
+                let handle = tokio::spawn(async move {
+                    // The semaphore is never closed
+                    let _permit = permits.acquire_owned().await.ok();
+                    spawn_action(transport, host, service, action, wait).await
+                });
+                (target, handle)
+            })
+            .collect();
+
+        for (target, handle) in handles {
+            let outcome = handle.await.unwrap_or_else(|e| ActionOutcome {
+                exit_code: None,
+                elapsed_ms: 0,
+                result: Err(SvcmonError::Unavailable(format!("{} panicked: {}", action, e))),
+            });
+            let status = match outcome.result {
+                Ok(_) => TargetStatus::Success,
+                Err(_) => TargetStatus::Failed,
+            };
+            failed |= status == TargetStatus::Failed;
+            runs.push(TargetRun {
+                target,
+                status,
+                outcome: Some(outcome),
+            });
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_literals_and_question_marks() {
+        assert!(glob_match("nginx", "nginx"));
+        assert!(!glob_match("nginx", "nginx2"));
+        assert!(!glob_match("nginx2", "nginx"));
+        assert!(glob_match("web?", "web1"));
+        assert!(!glob_match("web?", "web"));
+        assert!(!glob_match("web?", "web12"));
+    }
+
+    #[test]
+    fn glob_stars() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("web*", "web"));
+        assert!(glob_match("web*", "web01"));
+        assert!(glob_match("*.service", "nginx.service"));
+        assert!(glob_match("php*-fpm", "php8.2-fpm"));
+        assert!(!glob_match("php*-fpm", "php8.2-fpm.socket"));
+        assert!(glob_match("**a", "a"));
+    }
+
+    #[test]
+    fn glob_star_backtracks() {
+        // The first `-` tried for `*-` is not the one that lets the rest match
+        assert!(glob_match("*-worker-?", "queue-worker-worker-3"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+    }
+}
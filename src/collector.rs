@@ -1,65 +1,119 @@
-use std::error::Error;
+use std::sync::Arc;
 use std::thread;
 use chrono::Utc;
+use crate::alerts::{AlertEngine, Observation};
 use crate::database::crudops;
-use crate::hosts::Hosts;
+use crate::database::pool::{get_conn, DbPool};
+use crate::error::Result;
+use crate::hosts::{Host, Hosts};
+use crate::maintenance::Maintenance;
+use crate::remediation::{self, Remediation};
 use crate::service::show_service;
-use crate::GenericError;
+use crate::transport::{HostTransport, SharedTransport};
 
 // TODO: Make async and call `show_service` via API
-pub fn update_service_status(hostname: &str, service: &str) -> Result<(), Box<dyn Error>> {
-    let status = show_service(hostname, service)?;
-    let enabled: Option<bool> = match &status.get("UnitFileState") {
-        Some(state) => {
-            if (&state[..]).eq("enabled") {
-                Some(true)
-            } else {
-                Some(false)
-            }
+/// Record the current state of `service`, then, given an `AlertEngine`, run
+/// the alert rules on it and, with `remediate`, apply its remediation policy
+/// Neither happens while a window in `maintenance`, loaded once per polling
+/// cycle, covers the service
+/// A failed check still goes through the alert rules before its error is
+/// returned
+pub fn update_service_status(
+    transport: &dyn HostTransport,
+    pool: &DbPool,
+    host: &Host,
+    service: &str,
+    alerts: Option<&AlertEngine>,
+    maintenance: Option<&Maintenance>,
+    remediate: bool,
+) -> Result<()> {
+    let checked = show_service(transport, host, service);
+    let now = Utc::now().naive_utc();
+    if checked.is_err() && alerts.is_none() {
+        return checked.map(|_| ());
+    }
+    {
+        let conn = &mut get_conn(pool)?;
+        if let Ok(props) = &checked {
+            crudops::record_unit_status(conn, &host.hostname, service, props, now)?;
+        }
+        if alerts.is_none() && !remediate {
+            return Ok(());
         }
-        _ => Some(false),
-    };
 
-    let description: Option<&str> = match status.get("Description") {
-        Some(descrip) => Some(descrip),
-        None => None,
-    };
+        // Services under maintenance are stopped on purpose
+        if maintenance.is_some_and(|maintenance| maintenance.covers(&host.hostname, service)) {
+            return checked.map(|_| ());
+        }
 
-    let status: Option<&str> = match status.get("ActiveState") {
-        Some(state) => Some(state),
-        None => None,
-    };
+        if let Some(alerts) = alerts {
+            let observation = match &checked {
+                Ok(props) => Observation::State(props.active_state.as_ref()),
+                // Unreachable hosts and the like fire `check_failed` rules
+                Err(e) => Observation::CheckFailed(e),
+            };
+            // The status is already stored, so a rule failing is only reported
+            match alerts.observe(conn, &host.hostname, service, observation, now) {
+                Ok(changed) => {
+                    for alert in changed {
+                        println!("Alert {}: [{}] {}", alert.state, alert.rule, alert.message);
+                        alerts.notify(alert);
+                    }
+                }
+                Err(e) => eprintln!(
+                    "WARNING: alert rules not evaluated for {} on {}: {}",
+                    service, host.hostname, e
+                ),
+            }
+        }
+    }
 
-    crudops::insert_status(
-        hostname,                       // hostname: &'a str
-        service,                        // name: &'a str
-        description,                    // description: Option<&'a str>
-        enabled,                        // enabled: Option<&'a str>
-        status,                         // active_status: Option<&'a str>
-        Some(Utc::now().naive_utc()),   // last_check: Option<NaiveDateTime
-    )?;
+    let props = checked?;
+    let policy = match host.remediation.get(service) {
+        Some(policy) if remediate => policy,
+        _ => return Ok(()),
+    };
+    let active_state = props.active_state.as_ref();
+    let remediated =
+        remediation::remediate(transport, pool, host, service, policy, active_state, alerts, now);
+    match remediated {
+        Ok(Remediation::Attempted(attempt)) => println!(
+            "Remediation: restarted {} on {} (attempt {}, {})",
+            service, host.hostname, attempt.attempt, attempt.outcome
+        ),
+        Ok(Remediation::Healthy(Some(alert))) | Ok(Remediation::Exhausted(Some(alert))) => {
+            println!("Alert {}: [{}] {}", alert.state, alert.rule, alert.message);
+            if let Some(alerts) = alerts {
+                alerts.notify(alert);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!(
+            "WARNING: remediation not applied to {} on {}: {}",
+            service, host.hostname, e
+        ),
+    }
     Ok(())
 }
 
 /// Updates every service found within `hosts.json`
 /// Spawns a thread for each `Host` as opposed to each service
-pub fn update_host_json_services() -> Result<(), Box<dyn Error>> {
-    let hosts = match Hosts::init_from_file() {
-        Ok(hosts) => hosts,
-        Err(_) => {
-            return Err(Box::new(GenericError("error".to_string())));
-        }
-    };
+pub fn update_host_json_services(transport: SharedTransport, pool: DbPool) -> Result<()> {
+    let hosts = Hosts::init_from_file()?;
     let mut thread_handles = vec![];
     for host in hosts.get_hosts() {
+        let transport = Arc::clone(&transport);
+        let pool = pool.clone();
         let handle = thread::spawn(move || {
-            for svc in host.services {
-                match update_service_status(&host.hostname, &svc) {
+            for svc in &host.services {
+                let result =
+                    update_service_status(transport.as_ref(), &pool, &host, svc, None, None, false);
+                match result {
                     Ok(()) => {
                         println!("SUCCESS: updated {} on {}", host.hostname, svc);
                     }
-                    Err(_err) => {
-                        eprintln!("WARNING: did not update {} on {}", host.hostname, svc);
+                    Err(err) => {
+                        eprintln!("WARNING: did not update {} on {}: {}", host.hostname, svc, err);
                     }
                 }
             }
@@ -1,8 +1,11 @@
+use std::collections::HashMap;
 use std::fs;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use toml;
 use std::sync::OnceLock;
 
+use crate::unit::ActiveState;
+
 #[derive(Deserialize)]
 struct ConfigJson {
     config: Config,
@@ -11,6 +14,328 @@ struct ConfigJson {
 #[derive(Deserialize)]
 pub struct Config {
     pub hostsfile: String,
+    #[serde(default)]
+    pub collector: CollectorConfig,
+    #[serde(default)]
+    pub transport: TransportConfig,
+    #[serde(default)]
+    pub database: DatabaseConfig,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub control: ControlConfig,
+    #[serde(default)]
+    pub alerts: AlertsConfig,
+    #[serde(default)]
+    pub notify: NotifyConfig,
+}
+
+/// Settings for the background poller under `[config.collector]`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CollectorConfig {
+    /// Run the poller inside `runserver`
+    pub enabled: bool,
+    /// Seconds between the start of each polling cycle
+    pub interval_secs: u64,
+    /// Maximum number of services checked at once on a single host
+    pub max_concurrent_per_host: usize,
+    /// Upper bound of the random delay added before each check
+    pub jitter_ms: u64,
+}
+
+impl Default for CollectorConfig {
+    fn default() -> Self {
+        CollectorConfig {
+            enabled: true,
+            interval_secs: 60,
+            max_concurrent_per_host: 2,
+            jitter_ms: 2000,
+        }
+    }
+}
+
+/// Timeouts for `systemctl` calls under `[config.transport]`
+/// A host's `timeout_secs` and `control_timeout_secs` in `hosts.json`
+/// override them
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TransportConfig {
+    /// Seconds allowed for read-only calls: `show`, `status`, `is-active`, ...
+    pub timeout_secs: u64,
+    /// Seconds allowed for `start`, `stop` and `restart`
+    pub control_timeout_secs: u64,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        TransportConfig {
+            timeout_secs: 10,
+            control_timeout_secs: 90,
+        }
+    }
+}
+
+/// Connection pool settings under `[config.database]`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    /// Used when `DATABASE_URL` is not set: `mysql://...` or a SQLite file
+    pub url: Option<String>,
+    /// Maximum number of open connections
+    pub pool_size: u32,
+    /// Seconds to wait for a free connection before answering 503
+    pub connection_timeout_secs: u64,
+    /// Close connections idle for this many seconds
+    pub idle_timeout_secs: Option<u64>,
+    /// Ping each connection before handing it out
+    pub test_on_check_out: bool,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: None,
+            pool_size: 10,
+            connection_timeout_secs: 5,
+            idle_timeout_secs: Some(600),
+            test_on_check_out: true,
+        }
+    }
+}
+
+/// History retention under `[config.retention]`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RetentionConfig {
+    /// Run the retention job from `runserver`'s scheduler
+    pub enabled: bool,
+    /// Days of raw `service_status` rows to keep before rolling them up
+    pub raw_days: u32,
+    /// Days of hourly rollups to keep; daily rollups are kept forever.
+    /// `0` keeps hourly rollups forever too
+    pub hourly_days: u32,
+    /// Seconds between scheduled runs
+    pub interval_secs: u64,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        RetentionConfig {
+            enabled: true,
+            raw_days: 30,
+            hourly_days: 180,
+            interval_secs: 3600,
+        }
+    }
+}
+
+/// Login sessions under `[config.auth]`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// Seconds a login session stays valid
+    pub session_ttl_secs: u64,
+    /// Mark the session cookie `Secure`, so browsers only send it over HTTPS
+    pub secure_cookie: bool,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            session_ttl_secs: 43200,
+            secure_cookie: false,
+        }
+    }
+}
+
+/// `start`, `stop` and `restart` requests under `[config.control]`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ControlConfig {
+    /// Seconds a response is kept for replay under its idempotency key
+    pub idempotency_ttl_secs: u64,
+    /// Seconds `wait` polls for the expected state, unless the request sets
+    /// `timeout_secs`
+    pub verify_timeout_secs: u64,
+    /// Upper bound of a request's `timeout_secs`
+    pub max_verify_timeout_secs: u64,
+    /// Milliseconds between `systemctl show` polls while waiting
+    pub verify_poll_ms: u64,
+    /// Targets a bulk action runs at once; a request's `max_parallel` may
+    /// only lower it
+    pub bulk_max_parallel: usize,
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        ControlConfig {
+            idempotency_ttl_secs: 86400,
+            verify_timeout_secs: 30,
+            max_verify_timeout_secs: 300,
+            verify_poll_ms: 500,
+            bulk_max_parallel: 5,
+        }
+    }
+}
+
+/// Alert rules evaluated on every poller check, under `[config.alerts]`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AlertsConfig {
+    pub enabled: bool,
+    /// `[[config.alerts.rules]]`
+    pub rules: Vec<AlertRule>,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        AlertsConfig {
+            enabled: true,
+            rules: vec![],
+        }
+    }
+}
+
+/// One `[[config.alerts.rules]]` entry
+/// A rule applies to every service matching all of `host`, `service` and
+/// `group_accts_id` that are given
+#[derive(Deserialize, Debug, Clone)]
+pub struct AlertRule {
+    /// Unique; alerts are deduplicated by rule name, host and service
+    pub name: String,
+    /// Hostname or glob
+    pub host: Option<String>,
+    /// Service name or glob
+    pub service: Option<String>,
+    /// Services assigned to this group or any group below it
+    pub group_accts_id: Option<i64>,
+    #[serde(default)]
+    pub condition: AlertCondition,
+    /// Consecutive checks the condition must hold before the alert fires
+    #[serde(default = "default_for_checks")]
+    pub for_checks: u32,
+    /// Free text, e.g. `warning` or `critical`
+    pub severity: Option<String>,
+}
+
+fn default_for_checks() -> u32 {
+    1
+}
+
+/// What an `AlertRule` fires on, or a remediation policy restarts on
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// `ActiveState` is anything but `active`
+    #[default]
+    NotActive,
+    /// `ActiveState` is `failed`
+    Failed,
+    /// The poller could not check the unit, e.g. the host is unreachable;
+    /// never restarts anything as a remediation condition
+    CheckFailed,
+}
+
+impl AlertCondition {
+    /// The condition holds for a unit the poller checked and found in
+    /// `active_state`; `None` is unknown
+    pub fn holds(self, active_state: Option<&ActiveState>) -> bool {
+        match self {
+            AlertCondition::NotActive => active_state != Some(&ActiveState::Active),
+            AlertCondition::Failed => active_state == Some(&ActiveState::Failed),
+            AlertCondition::CheckFailed => false,
+        }
+    }
+}
+
+/// Where firing and resolved alerts are sent, under `[config.notify]`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct NotifyConfig {
+    /// Further attempts after a failed delivery
+    pub retries: u32,
+    /// Wait before the first retry; doubled for every further one
+    pub backoff_ms: u64,
+    /// Seconds allowed for one delivery attempt
+    pub timeout_secs: u64,
+    /// `[[config.notify.channels]]`
+    pub channels: Vec<ChannelConfig>,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        NotifyConfig {
+            retries: 3,
+            backoff_ms: 1000,
+            timeout_secs: 10,
+            channels: vec![],
+        }
+    }
+}
+
+/// One `[[config.notify.channels]]` entry
+#[derive(Deserialize, Debug, Clone)]
+pub struct ChannelConfig {
+    /// Unique; used by `POST /api/notify/test`
+    pub name: String,
+    /// Alert rule names sent to this channel; every rule if empty
+    #[serde(default)]
+    pub rules: Vec<String>,
+    #[serde(flatten)]
+    pub kind: ChannelKind,
+}
+
+/// Channel type, from the `kind` key
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ChannelKind {
+    /// `POST` of `body` to `url`
+    Webhook {
+        url: String,
+        /// JSON template with `{{field}}` placeholders for the alert's
+        /// fields; the alert itself as JSON if left out
+        body: Option<String>,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+    /// Slack or Mattermost incoming webhook
+    Slack {
+        url: String,
+        channel: Option<String>,
+        username: Option<String>,
+    },
+    /// Plain-text email over SMTP
+    Email {
+        smtp_host: String,
+        #[serde(default = "default_smtp_port")]
+        smtp_port: u16,
+        #[serde(default)]
+        security: SmtpSecurity,
+        username: Option<String>,
+        password: Option<String>,
+        from: String,
+        to: Vec<String>,
+    },
+}
+
+fn default_smtp_port() -> u16 {
+    25
+}
+
+/// How an `email` channel talks to its SMTP server
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Unencrypted, e.g. a local relay or test sink
+    #[default]
+    Plain,
+    /// Upgrade with `STARTTLS`, usually port 587
+    Starttls,
+    /// TLS from the start, usually port 465
+    Tls,
 }
 
 pub fn read_config_file() -> &'static Config {
@@ -23,4 +348,3 @@ pub fn read_config_file() -> &'static Config {
         config_json.config
     })
 }
-
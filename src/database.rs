@@ -1,2 +1,41 @@
 pub mod crudops;
 pub mod models;
+pub mod pool;
+
+// BACKEND SELECTION
+/******************************************************************************/
+// Exactly one of the `mysql` (default) or `sqlite` features must be enabled
+#[cfg(all(feature = "mysql", feature = "sqlite"))]
+compile_error!("features `mysql` and `sqlite` are mutually exclusive; build with `--no-default-features --features sqlite`");
+
+#[cfg(not(any(feature = "mysql", feature = "sqlite")))]
+compile_error!("enable one database backend feature: `mysql` or `sqlite`");
+
+/// Connection type for the backend svcmon was built with
+#[cfg(feature = "mysql")]
+pub type DbConnection = diesel::mysql::MysqlConnection;
+#[cfg(feature = "sqlite")]
+pub type DbConnection = diesel::sqlite::SqliteConnection;
+
+/// Diesel backend svcmon was built with
+#[cfg(feature = "mysql")]
+pub type DbBackend = diesel::mysql::Mysql;
+#[cfg(feature = "sqlite")]
+pub type DbBackend = diesel::sqlite::Sqlite;
+
+/// Id of the row `conn` inserted last; unlike re-reading the newest row, it
+/// cannot pick up another connection's insert
+#[cfg(feature = "mysql")]
+pub fn last_insert_id(conn: &mut DbConnection) -> diesel::QueryResult<i64> {
+    use diesel::RunQueryDsl;
+    diesel::select(diesel::dsl::sql::<diesel::sql_types::BigInt>(
+        "CAST(LAST_INSERT_ID() AS SIGNED)",
+    ))
+    .get_result(conn)
+}
+#[cfg(feature = "sqlite")]
+pub fn last_insert_id(conn: &mut DbConnection) -> diesel::QueryResult<i64> {
+    use diesel::RunQueryDsl;
+    diesel::select(diesel::dsl::sql::<diesel::sql_types::BigInt>("last_insert_rowid()"))
+        .get_result(conn)
+}
@@ -1,41 +1,56 @@
 use chrono::prelude::*;
-use diesel::{mysql::MysqlConnection, sql_query};
 use diesel::prelude::*;
-use dotenvy::dotenv;
-use std::env;
-use std::error::Error;
-use std::thread;
-use std::sync::mpsc;
+use diesel::sql_types::Bool;
+use std::collections::HashSet;
 
 use super::models;
+use super::{last_insert_id, DbBackend, DbConnection};
+use crate::auth::hash_password;
 use crate::hosts::Hosts;
+use crate::rbac::Scope;
 use crate::schema::{self};
-use crate::GenericError;
+use crate::error::{Result, SvcmonError};
+use crate::unit::UnitProperties;
 
 pub const TABLES: &[&str] = &["users", "group_accts", "service_status"];
 pub const CRUD_IPS: &[&str] = &["insert", "update", "insert", "delete"];
 
-// ESTABLISH CONNECTION TO THE DATABASE
+// SCOPE PREDICATE
 /******************************************************************************/
-pub fn establish_connection() -> MysqlConnection {
-    dotenv().ok();
-
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    MysqlConnection::establish(&database_url)
-        .unwrap_or_else(|_| panic!("Error connecting to {}", database_url))
+/// Predicate on `$table` matching the rows an owned scope allows, given the
+/// table's hostname and service columns
+/// One `OR` branch per owned host; an empty scope matches nothing
+macro_rules! scope_predicate {
+    ($table:ty, $owned:expr, $hostname:expr, $service:expr) => {{
+        let mut allowed: Box<dyn BoxableExpression<$table, DbBackend, SqlType = Bool> + '_> =
+            Box::new(false.into_sql::<Bool>());
+        for (hostname, services) in $owned {
+            let on_host = $hostname.eq(hostname.as_str());
+            allowed = match services {
+                None => Box::new(allowed.or(on_host)),
+                Some(services) => {
+                    let services: Vec<&str> = services.iter().map(String::as_str).collect();
+                    Box::new(allowed.or(on_host.and($service.eq_any(services))))
+                }
+            };
+        }
+        allowed
+    }};
 }
 
 /*----------------------------------USERS-------------------------------------*/
 
 // INSERT SINGLE USER
 /******************************************************************************/
+/// `password` is plain text; it is stored as an argon2 hash
 pub fn insert_user(
-    conn: &mut MysqlConnection,
+    conn: &mut DbConnection,
     name: &str,
     password: &str,
     group_accts_id: Option<i64>,
     active: Option<bool>,
-) -> QueryResult<usize> {
+) -> Result<usize> {
+    let password = &hash_password(password)?;
     let new_user = models::NewUser {
         name,
         password,
@@ -43,15 +58,14 @@ pub fn insert_user(
         active,
     };
 
-    diesel::insert_into(schema::users::table)
+    Ok(diesel::insert_into(schema::users::table)
         .values(&new_user)
-        .execute(conn)
+        .execute(conn)?)
 }
 
 // RETRIEVE ALL USERS
 /******************************************************************************/
-pub fn get_all_users() -> Result<Vec<models::User>, Box<dyn Error>> {
-    let conn = &mut establish_connection();
+pub fn get_all_users(conn: &mut DbConnection) -> Result<Vec<models::User>> {
     let results: Vec<models::User> = schema::users::table
         .select(models::User::as_select())
         .load(conn)?;
@@ -61,50 +75,49 @@ pub fn get_all_users() -> Result<Vec<models::User>, Box<dyn Error>> {
 
 // RETRIEVE USER BY NAME
 /******************************************************************************/
-pub fn get_user_by_username(username: &str) -> Result<models::User, Box<dyn Error>> {
-    let conn = &mut establish_connection();
+pub fn get_user_by_username(conn: &mut DbConnection, username: &str) -> Result<models::User> {
     let mut result: Vec<models::User> = schema::users::table
         .filter(schema::users::name.eq(username))
         .limit(1)
         .select(models::User::as_select())
         .load(conn)?;
 
-    let result: models::User = result.pop().ok_or(format!(
+    let result: models::User = result.pop().ok_or_else(|| SvcmonError::NotFound(format!(
         "Error retrieving '{}' from table, `users`",
         username
-    ))?;
+    )))?;
 
     Ok(result)
 }
 
 // GET USER BY ID
 /******************************************************************************/
-pub fn get_user_by_id(id: i64) -> Result<models::User, Box<dyn Error>> {
-    let conn = &mut establish_connection();
+pub fn get_user_by_id(conn: &mut DbConnection, id: i64) -> Result<models::User> {
     let mut result: Vec<models::User> = schema::users::table
         .filter(schema::users::id.eq(id))
         .limit(1)
         .select(models::User::as_select())
         .load(conn)?;
 
-    let result: models::User = result.pop().ok_or(format!(
+    let result: models::User = result.pop().ok_or_else(|| SvcmonError::NotFound(format!(
         "Error retrieving `User` by `id` = {} from  `users`",
         id
-    ))?;
+    )))?;
 
     Ok(result)
 }
 
 // ADD USER
 /******************************************************************************/
+/// `password` is plain text; it is stored as an argon2 hash
 pub fn add_user_to_users(
+    conn: &mut DbConnection,
     name: &str,
     password: &str,
     group_accts_id: Option<i64>,
     active: Option<bool>,
-) -> QueryResult<usize> {
-    let conn = &mut establish_connection();
-
+) -> Result<usize> {
+    let password = &hash_password(password)?;
     let new_user = models::NewUser {
         name,
         password,
@@ -112,9 +125,71 @@ pub fn add_user_to_users(
         active,
     };
 
-    diesel::insert_into(schema::users::table)
+    Ok(diesel::insert_into(schema::users::table)
         .values(&new_user)
-        .execute(conn)
+        .execute(conn)?)
+}
+
+// UPDATE USER
+/******************************************************************************/
+/// Change any of a user's columns; `None` leaves a column as it is
+/// `password` is plain text; it is stored as an argon2 hash
+pub fn update_user(
+    conn: &mut DbConnection,
+    id: i64,
+    name: Option<&str>,
+    password: Option<&str>,
+    group_accts_id: Option<Option<i64>>,
+    active: Option<bool>,
+) -> Result<usize> {
+    if name.is_none() && password.is_none() && group_accts_id.is_none() && active.is_none() {
+        return Err(SvcmonError::Validation(String::from("nothing to update")));
+    }
+    if name.is_some_and(str::is_empty) || password.is_some_and(str::is_empty) {
+        return Err(SvcmonError::Validation(String::from(
+            "`name` and `password` must not be empty",
+        )));
+    }
+    if let Some(name) = name {
+        match get_user_by_username(conn, name) {
+            Ok(existing) if existing.id != id => {
+                return Err(SvcmonError::Conflict(format!("User, '{}', already exists", name)))
+            }
+            Ok(_) | Err(SvcmonError::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    if let Some(Some(group_accts_id)) = group_accts_id {
+        get_group_by_id(conn, group_accts_id)?;
+    }
+
+    let password = password.map(hash_password).transpose()?;
+    let changes = models::UserChanges {
+        name,
+        password: password.as_deref(),
+        group_accts_id,
+        active,
+    };
+
+    let updated = diesel::update(schema::users::table.find(id))
+        .set(&changes)
+        .execute(conn)?;
+    if updated == 0 {
+        return Err(SvcmonError::NotFound(format!("no user with `id` = {}", id)));
+    }
+
+    Ok(updated)
+}
+
+// DELETE USER
+/******************************************************************************/
+pub fn delete_user(conn: &mut DbConnection, id: i64) -> Result<usize> {
+    let deleted = diesel::delete(schema::users::table.find(id)).execute(conn)?;
+    if deleted == 0 {
+        return Err(SvcmonError::NotFound(format!("no user with `id` = {}", id)));
+    }
+
+    Ok(deleted)
 }
 
 /*----------------------------------GROUPS------------------------------------*/
@@ -122,7 +197,7 @@ pub fn add_user_to_users(
 // INSERT SINGLE GROUP
 /******************************************************************************/
 pub fn insert_group(
-    conn: &mut MysqlConnection,
+    conn: &mut DbConnection,
     parent_id: Option<i64>,
     name: &str,
 ) -> QueryResult<usize> {
@@ -135,8 +210,7 @@ pub fn insert_group(
 
 // RETRIEVE ALL GROUPS
 /******************************************************************************/
-pub fn get_all_groups() -> Result<Vec<models::Group>, Box<dyn Error>> {
-    let conn = &mut establish_connection();
+pub fn get_all_groups(conn: &mut DbConnection) -> Result<Vec<models::Group>> {
     let results: Vec<models::Group> = schema::group_accts::table
         .select(models::Group::as_select())
         .load(conn)?;
@@ -146,50 +220,738 @@ pub fn get_all_groups() -> Result<Vec<models::Group>, Box<dyn Error>> {
 
 // RETRIEVE GROUP BY NAME
 /******************************************************************************/
-pub fn get_group_by_name(groupname: &str) -> Result<models::Group, Box<dyn Error>> {
-    let conn = &mut establish_connection();
+pub fn get_group_by_name(conn: &mut DbConnection, groupname: &str) -> Result<models::Group> {
     let mut result: Vec<models::Group> = schema::group_accts::table
         .filter(schema::group_accts::name.eq(groupname))
         .limit(1)
         .select(models::Group::as_select())
         .load(conn)?;
 
-    let result: models::Group = result.pop().ok_or(format!(
+    let result: models::Group = result.pop().ok_or_else(|| SvcmonError::NotFound(format!(
         "Error retrieving '{}' from table, `group_accts`",
         groupname
-    ))?;
+    )))?;
 
     Ok(result)
 }
 
 // GET GROUP BY ID
 /******************************************************************************/
-pub fn get_group_by_id(id: i64) -> Result<models::Group, Box<dyn Error>> {
-    let conn = &mut establish_connection();
+pub fn get_group_by_id(conn: &mut DbConnection, id: i64) -> Result<models::Group> {
     let mut result: Vec<models::Group> = schema::group_accts::table
         .filter(schema::group_accts::id.eq(id))
         .limit(1)
         .select(models::Group::as_select())
         .load(conn)?;
 
-    let result: models::Group = result.pop().ok_or(format!(
+    let result: models::Group = result.pop().ok_or_else(|| SvcmonError::NotFound(format!(
         "Error retrieving `Group` by `id` = {} from  `group_accts`",
         id
-    ))?;
+    )))?;
 
     Ok(result)
 }
 
 // ADD GROUP
 /******************************************************************************/
-pub fn add_group_to_groups(parent_id: Option<i64>, name: &str) -> QueryResult<usize> {
-    let conn = &mut establish_connection();
-
+pub fn add_group_to_groups(conn: &mut DbConnection, parent_id: Option<i64>, name: &str) -> Result<usize> {
     let new_group = models::NewGroup { parent_id, name };
 
-    diesel::insert_into(schema::group_accts::table)
+    Ok(diesel::insert_into(schema::group_accts::table)
         .values(&new_group)
-        .execute(conn)
+        .execute(conn)?)
+}
+
+// UPDATE GROUP
+/******************************************************************************/
+/// Rename a group or move it below another one
+/// A group cannot become its own ancestor or take another group's name
+pub fn update_group(
+    conn: &mut DbConnection,
+    id: i64,
+    name: Option<&str>,
+    parent_id: Option<Option<i64>>,
+) -> Result<usize> {
+    if name.is_none() && parent_id.is_none() {
+        return Err(SvcmonError::Validation(String::from("nothing to update")));
+    }
+    if let Some(name) = name {
+        if name.is_empty() {
+            return Err(SvcmonError::Validation(String::from("`name` must not be empty")));
+        }
+        match get_group_by_name(conn, name) {
+            Ok(existing) if existing.id != id => {
+                return Err(SvcmonError::Conflict(format!("Group, '{}', already exists", name)))
+            }
+            Ok(_) | Err(SvcmonError::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    if let Some(Some(parent_id)) = parent_id {
+        get_group_by_id(conn, parent_id)?;
+        if get_group_subtree(conn, id)?.contains(&parent_id) {
+            return Err(SvcmonError::Validation(format!(
+                "group {} cannot be moved below its own subgroup {}",
+                id, parent_id
+            )));
+        }
+    }
+
+    let changes = models::GroupChanges { name, parent_id };
+    let updated = diesel::update(schema::group_accts::table.find(id))
+        .set(&changes)
+        .execute(conn)?;
+    if updated == 0 {
+        return Err(SvcmonError::NotFound(format!("no group with `id` = {}", id)));
+    }
+
+    Ok(updated)
+}
+
+// DELETE GROUP
+/******************************************************************************/
+/// Members and subgroups are kept, with their group or parent set to NULL
+pub fn delete_group(conn: &mut DbConnection, id: i64) -> Result<usize> {
+    let deleted = diesel::delete(schema::group_accts::table.find(id)).execute(conn)?;
+    if deleted == 0 {
+        return Err(SvcmonError::NotFound(format!("no group with `id` = {}", id)));
+    }
+
+    Ok(deleted)
+}
+
+/*-------------------------------PERMISSIONS----------------------------------*/
+
+// RETRIEVE GROUP PERMISSIONS
+/******************************************************************************/
+/// Permissions granted directly to one group, without inheritance
+pub fn get_group_permissions(
+    conn: &mut DbConnection,
+    group_accts_id: i64,
+) -> Result<Vec<models::GroupPermission>> {
+    let results: Vec<models::GroupPermission> = schema::group_permissions::table
+        .filter(schema::group_permissions::group_accts_id.eq(group_accts_id))
+        .select(models::GroupPermission::as_select())
+        .load(conn)?;
+
+    Ok(results)
+}
+
+// GRANT PERMISSION
+/******************************************************************************/
+/// Returns `0` when the group already has `permission`
+pub fn grant_permission(
+    conn: &mut DbConnection,
+    group_accts_id: i64,
+    permission: &str,
+) -> Result<usize> {
+    use schema::group_permissions::dsl;
+
+    let existing: i64 = dsl::group_permissions
+        .filter(dsl::group_accts_id.eq(group_accts_id))
+        .filter(dsl::permission.eq(permission))
+        .count()
+        .get_result(conn)?;
+    if existing > 0 {
+        return Ok(0);
+    }
+
+    let new_permission = models::NewGroupPermission {
+        group_accts_id,
+        permission,
+    };
+    Ok(diesel::insert_into(dsl::group_permissions)
+        .values(&new_permission)
+        .execute(conn)?)
+}
+
+// REVOKE PERMISSION
+/******************************************************************************/
+pub fn revoke_permission(
+    conn: &mut DbConnection,
+    group_accts_id: i64,
+    permission: &str,
+) -> Result<usize> {
+    use schema::group_permissions::dsl;
+
+    let deleted = diesel::delete(
+        dsl::group_permissions
+            .filter(dsl::group_accts_id.eq(group_accts_id))
+            .filter(dsl::permission.eq(permission)),
+    )
+    .execute(conn)?;
+
+    Ok(deleted)
+}
+
+// GROUP ANCESTRY
+/******************************************************************************/
+/// `group_accts_id` followed by its parent, grandparent, ... up to the root
+/// Stops early if the `parent_id` chain loops back on itself
+pub fn get_group_ancestry(conn: &mut DbConnection, group_accts_id: i64) -> Result<Vec<i64>> {
+    use schema::group_accts::dsl;
+
+    let mut ancestry = vec![group_accts_id];
+    let mut current = group_accts_id;
+    loop {
+        let parent_id: Option<Option<i64>> = dsl::group_accts
+            .find(current)
+            .select(dsl::parent_id)
+            .first(conn)
+            .optional()?;
+        match parent_id.flatten() {
+            Some(parent_id) if !ancestry.contains(&parent_id) => {
+                ancestry.push(parent_id);
+                current = parent_id;
+            }
+            _ => break,
+        }
+    }
+
+    Ok(ancestry)
+}
+
+// GROUP SUBTREE
+/******************************************************************************/
+/// `group_accts_id` followed by every group below it in the `parent_id` tree
+pub fn get_group_subtree(conn: &mut DbConnection, group_accts_id: i64) -> Result<Vec<i64>> {
+    use schema::group_accts::dsl;
+
+    let groups: Vec<(i64, Option<i64>)> = dsl::group_accts
+        .select((dsl::id, dsl::parent_id))
+        .load(conn)?;
+
+    let mut subtree = vec![group_accts_id];
+    let mut next = 0;
+    while next < subtree.len() {
+        let parent = subtree[next];
+        for (id, parent_id) in &groups {
+            if *parent_id == Some(parent) && !subtree.contains(id) {
+                subtree.push(*id);
+            }
+        }
+        next += 1;
+    }
+
+    Ok(subtree)
+}
+
+/*---------------------------------OWNERSHIP----------------------------------*/
+
+// RETRIEVE GROUP HOSTS
+/******************************************************************************/
+/// Hosts and services owned by any of `group_ids`
+pub fn get_group_hosts(
+    conn: &mut DbConnection,
+    group_ids: &[i64],
+) -> Result<Vec<models::GroupHost>> {
+    let results: Vec<models::GroupHost> = schema::group_hosts::table
+        .filter(schema::group_hosts::group_accts_id.eq_any(group_ids))
+        .select(models::GroupHost::as_select())
+        .load(conn)?;
+
+    Ok(results)
+}
+
+// ASSIGN HOST
+/******************************************************************************/
+/// Give `group_accts_id` a host, or a single service on it when `service` is
+/// set. Returns `0` when the assignment already exists
+pub fn assign_host(
+    conn: &mut DbConnection,
+    group_accts_id: i64,
+    hostname: &str,
+    service: Option<&str>,
+) -> Result<usize> {
+    use schema::group_hosts::dsl;
+
+    let mut query = dsl::group_hosts
+        .filter(dsl::group_accts_id.eq(group_accts_id))
+        .filter(dsl::hostname.eq(hostname))
+        .into_boxed();
+    query = match service {
+        Some(service) => query.filter(dsl::service.eq(service)),
+        None => query.filter(dsl::service.is_null()),
+    };
+    let existing: i64 = query.count().get_result(conn)?;
+    if existing > 0 {
+        return Ok(0);
+    }
+
+    let new_group_host = models::NewGroupHost {
+        group_accts_id,
+        hostname,
+        service,
+    };
+    Ok(diesel::insert_into(dsl::group_hosts)
+        .values(&new_group_host)
+        .execute(conn)?)
+}
+
+// UNASSIGN HOST
+/******************************************************************************/
+/// Remove an assignment made by `assign_host`
+pub fn unassign_host(
+    conn: &mut DbConnection,
+    group_accts_id: i64,
+    hostname: &str,
+    service: Option<&str>,
+) -> Result<usize> {
+    use schema::group_hosts::dsl;
+
+    let target = dsl::group_hosts
+        .filter(dsl::group_accts_id.eq(group_accts_id))
+        .filter(dsl::hostname.eq(hostname));
+    let deleted = match service {
+        Some(service) => diesel::delete(target.filter(dsl::service.eq(service))).execute(conn)?,
+        None => diesel::delete(target.filter(dsl::service.is_null())).execute(conn)?,
+    };
+
+    Ok(deleted)
+}
+
+/*----------------------------------TOKENS------------------------------------*/
+
+// INSERT API TOKEN
+/******************************************************************************/
+pub fn insert_api_token(
+    conn: &mut DbConnection,
+    new_token: &models::NewApiToken,
+) -> Result<models::ApiToken> {
+    diesel::insert_into(schema::api_tokens::table)
+        .values(new_token)
+        .execute(conn)?;
+
+    get_api_token_by_prefix(conn, new_token.prefix)
+}
+
+// RETRIEVE API TOKEN BY PREFIX
+/******************************************************************************/
+pub fn get_api_token_by_prefix(conn: &mut DbConnection, prefix: &str) -> Result<models::ApiToken> {
+    let result: Option<models::ApiToken> = schema::api_tokens::table
+        .filter(schema::api_tokens::prefix.eq(prefix))
+        .select(models::ApiToken::as_select())
+        .first(conn)
+        .optional()?;
+
+    result.ok_or_else(|| SvcmonError::NotFound(format!("no API token with prefix '{}'", prefix)))
+}
+
+// RETRIEVE API TOKEN BY ID
+/******************************************************************************/
+pub fn get_api_token_by_id(conn: &mut DbConnection, id: i64) -> Result<models::ApiToken> {
+    let result: Option<models::ApiToken> = schema::api_tokens::table
+        .find(id)
+        .select(models::ApiToken::as_select())
+        .first(conn)
+        .optional()?;
+
+    result.ok_or_else(|| SvcmonError::NotFound(format!("no API token with `id` = {}", id)))
+}
+
+// RETRIEVE A USER'S API TOKENS
+/******************************************************************************/
+pub fn get_api_tokens_for_user(
+    conn: &mut DbConnection,
+    user_id: i64,
+) -> Result<Vec<models::ApiToken>> {
+    let results: Vec<models::ApiToken> = schema::api_tokens::table
+        .filter(schema::api_tokens::user_id.eq(user_id))
+        .order(schema::api_tokens::created.desc())
+        .select(models::ApiToken::as_select())
+        .load(conn)?;
+
+    Ok(results)
+}
+
+// REVOKE API TOKEN
+/******************************************************************************/
+pub fn revoke_api_token(conn: &mut DbConnection, id: i64) -> Result<usize> {
+    let updated = diesel::update(schema::api_tokens::table.find(id))
+        .set(schema::api_tokens::revoked.eq(true))
+        .execute(conn)?;
+
+    Ok(updated)
+}
+
+// TOUCH API TOKEN
+/******************************************************************************/
+/// Record that a token was just used
+pub fn touch_api_token(conn: &mut DbConnection, id: i64, now: NaiveDateTime) -> Result<usize> {
+    let updated = diesel::update(schema::api_tokens::table.find(id))
+        .set(schema::api_tokens::last_used.eq(Some(now)))
+        .execute(conn)?;
+
+    Ok(updated)
+}
+
+/*----------------------------------AUDIT-------------------------------------*/
+
+// INSERT AUDIT ENTRY
+/******************************************************************************/
+pub fn insert_audit_entry(conn: &mut DbConnection, entry: &models::NewAuditEntry) -> Result<usize> {
+    Ok(diesel::insert_into(schema::audit_log::table)
+        .values(entry)
+        .execute(conn)?)
+}
+
+/// Narrows `get_audit_entries`; `None` matches everything
+#[derive(Debug, Default)]
+pub struct AuditFilter<'a> {
+    pub actor: Option<&'a str>,
+    pub action: Option<&'a str>,
+    pub hostname: Option<&'a str>,
+    pub service: Option<&'a str>,
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+}
+
+fn audit_query<'a>(filter: &AuditFilter<'a>) -> schema::audit_log::BoxedQuery<'a, DbBackend> {
+    use schema::audit_log::dsl;
+
+    let mut query = dsl::audit_log.into_boxed();
+    if let Some(actor) = filter.actor {
+        query = query.filter(dsl::actor.eq(actor));
+    }
+    if let Some(action) = filter.action {
+        query = query.filter(dsl::action.eq(action));
+    }
+    if let Some(hostname) = filter.hostname {
+        query = query.filter(dsl::hostname.eq(hostname));
+    }
+    if let Some(service) = filter.service {
+        query = query.filter(dsl::service.eq(service));
+    }
+    if let Some(since) = filter.since {
+        query = query.filter(dsl::created_at.ge(since));
+    }
+    if let Some(until) = filter.until {
+        query = query.filter(dsl::created_at.le(until));
+    }
+    query
+}
+
+// RETRIEVE AUDIT ENTRIES
+/******************************************************************************/
+/// One page of matching entries, newest first, and the total number of
+/// matching entries
+pub fn get_audit_entries(
+    conn: &mut DbConnection,
+    filter: &AuditFilter,
+    offset: i64,
+    limit: i64,
+) -> Result<(Vec<models::AuditEntry>, i64)> {
+    use schema::audit_log::dsl;
+
+    let total: i64 = audit_query(filter).count().get_result(conn)?;
+    let results: Vec<models::AuditEntry> = audit_query(filter)
+        .order((dsl::created_at.desc(), dsl::id.desc()))
+        .offset(offset)
+        .limit(limit)
+        .select(models::AuditEntry::as_select())
+        .load(conn)?;
+
+    Ok((results, total))
+}
+
+/*----------------------------------ALERTS------------------------------------*/
+
+// INSERT ALERT
+/******************************************************************************/
+/// Store a newly firing alert and return it
+pub fn insert_alert(conn: &mut DbConnection, new_alert: &models::NewAlert) -> Result<models::Alert> {
+    use schema::alerts::dsl;
+
+    conn.transaction::<_, SvcmonError, _>(|conn| {
+        diesel::insert_into(dsl::alerts)
+            .values(new_alert)
+            .execute(conn)?;
+
+        let id = last_insert_id(conn)?;
+        Ok(dsl::alerts
+            .find(id)
+            .select(models::Alert::as_select())
+            .first(conn)?)
+    })
+}
+
+// RETRIEVE FIRING ALERTS
+/******************************************************************************/
+pub fn get_firing_alerts(conn: &mut DbConnection) -> Result<Vec<models::Alert>> {
+    use schema::alerts::dsl;
+
+    Ok(dsl::alerts
+        .filter(dsl::state.eq("firing"))
+        .select(models::Alert::as_select())
+        .load(conn)?)
+}
+
+// RESOLVE ALERT
+/******************************************************************************/
+/// Mark alert `id` resolved at `now` and return it
+pub fn resolve_alert(conn: &mut DbConnection, id: i64, now: NaiveDateTime) -> Result<models::Alert> {
+    use schema::alerts::dsl;
+
+    let updated = diesel::update(dsl::alerts.find(id))
+        .set((dsl::state.eq("resolved"), dsl::resolved_at.eq(Some(now))))
+        .execute(conn)?;
+    if updated == 0 {
+        return Err(SvcmonError::NotFound(format!("no alert with id {}", id)));
+    }
+
+    Ok(dsl::alerts
+        .find(id)
+        .select(models::Alert::as_select())
+        .first(conn)?)
+}
+
+/// Narrows `get_alerts`; `None` matches everything
+#[derive(Debug, Default)]
+pub struct AlertFilter<'a> {
+    pub state: Option<&'a str>,
+    pub rule: Option<&'a str>,
+    pub hostname: Option<&'a str>,
+    pub service: Option<&'a str>,
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+    /// Only alerts on targets this scope allows
+    pub scope: Option<&'a Scope>,
+}
+
+fn alert_query<'a>(filter: &AlertFilter<'a>) -> schema::alerts::BoxedQuery<'a, DbBackend> {
+    use schema::alerts::dsl;
+
+    let mut query = dsl::alerts.into_boxed();
+    if let Some(state) = filter.state {
+        query = query.filter(dsl::state.eq(state));
+    }
+    if let Some(rule) = filter.rule {
+        query = query.filter(dsl::rule.eq(rule));
+    }
+    if let Some(hostname) = filter.hostname {
+        query = query.filter(dsl::hostname.eq(hostname));
+    }
+    if let Some(service) = filter.service {
+        query = query.filter(dsl::service.eq(service));
+    }
+    if let Some(since) = filter.since {
+        query = query.filter(dsl::fired_at.ge(since));
+    }
+    if let Some(until) = filter.until {
+        query = query.filter(dsl::fired_at.le(until));
+    }
+    if let Some(Scope::Owned(owned)) = filter.scope {
+        query = query.filter(scope_predicate!(
+            schema::alerts::table,
+            owned,
+            dsl::hostname,
+            dsl::service
+        ));
+    }
+    query
+}
+
+// RETRIEVE ALERTS
+/******************************************************************************/
+/// One page of matching alerts, newest first, and the total number of
+/// matching alerts
+pub fn get_alerts(
+    conn: &mut DbConnection,
+    filter: &AlertFilter,
+    offset: i64,
+    limit: i64,
+) -> Result<(Vec<models::Alert>, i64)> {
+    use schema::alerts::dsl;
+
+    let total: i64 = alert_query(filter).count().get_result(conn)?;
+    let results: Vec<models::Alert> = alert_query(filter)
+        .order((dsl::fired_at.desc(), dsl::id.desc()))
+        .offset(offset)
+        .limit(limit)
+        .select(models::Alert::as_select())
+        .load(conn)?;
+
+    Ok((results, total))
+}
+
+/*-------------------------------MAINTENANCE----------------------------------*/
+
+// INSERT MAINTENANCE WINDOW
+/******************************************************************************/
+/// Store a maintenance window and return it
+pub fn insert_maintenance_window(
+    conn: &mut DbConnection,
+    new_window: &models::NewMaintenanceWindow,
+) -> Result<models::MaintenanceWindow> {
+    use schema::maintenance_windows::dsl;
+
+    conn.transaction::<_, SvcmonError, _>(|conn| {
+        diesel::insert_into(dsl::maintenance_windows)
+            .values(new_window)
+            .execute(conn)?;
+
+        let id = last_insert_id(conn)?;
+        Ok(dsl::maintenance_windows
+            .find(id)
+            .select(models::MaintenanceWindow::as_select())
+            .first(conn)?)
+    })
+}
+
+// RETRIEVE MAINTENANCE WINDOW
+/******************************************************************************/
+pub fn get_maintenance_window(conn: &mut DbConnection, id: i64) -> Result<models::MaintenanceWindow> {
+    use schema::maintenance_windows::dsl;
+
+    dsl::maintenance_windows
+        .find(id)
+        .select(models::MaintenanceWindow::as_select())
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| SvcmonError::NotFound(format!("no maintenance window with id {}", id)))
+}
+
+// RETRIEVE MAINTENANCE WINDOWS
+/******************************************************************************/
+/// Every window, oldest first; with `after`, one-off windows that ended by
+/// then are left out
+pub fn get_maintenance_windows(
+    conn: &mut DbConnection,
+    after: Option<NaiveDateTime>,
+) -> Result<Vec<models::MaintenanceWindow>> {
+    use schema::maintenance_windows::dsl;
+
+    let mut query = dsl::maintenance_windows.into_boxed();
+    if let Some(after) = after {
+        query = query.filter(dsl::cron.is_not_null().or(dsl::ends_at.gt(after)));
+    }
+    Ok(query
+        .order(dsl::id.asc())
+        .select(models::MaintenanceWindow::as_select())
+        .load(conn)?)
+}
+
+// DELETE MAINTENANCE WINDOW
+/******************************************************************************/
+pub fn delete_maintenance_window(conn: &mut DbConnection, id: i64) -> Result<()> {
+    use schema::maintenance_windows::dsl;
+
+    let deleted = diesel::delete(dsl::maintenance_windows.find(id)).execute(conn)?;
+    if deleted == 0 {
+        return Err(SvcmonError::NotFound(format!("no maintenance window with id {}", id)));
+    }
+    Ok(())
+}
+
+/*-------------------------------REMEDIATION----------------------------------*/
+
+// INSERT REMEDIATION ATTEMPT
+/******************************************************************************/
+/// Record one remediation restart and return it
+pub fn insert_remediation_attempt(
+    conn: &mut DbConnection,
+    new_attempt: &models::NewRemediationAttempt,
+) -> Result<models::RemediationAttempt> {
+    use schema::remediation_attempts::dsl;
+
+    conn.transaction::<_, SvcmonError, _>(|conn| {
+        diesel::insert_into(dsl::remediation_attempts)
+            .values(new_attempt)
+            .execute(conn)?;
+
+        let id = last_insert_id(conn)?;
+        Ok(dsl::remediation_attempts
+            .find(id)
+            .select(models::RemediationAttempt::as_select())
+            .first(conn)?)
+    })
+}
+
+// RETRIEVE RECENT REMEDIATION ATTEMPTS
+/******************************************************************************/
+/// Attempts on `service` at or after `since`, oldest first
+pub fn get_recent_remediation_attempts(
+    conn: &mut DbConnection,
+    hostname: &str,
+    service: &str,
+    since: NaiveDateTime,
+) -> Result<Vec<models::RemediationAttempt>> {
+    use schema::remediation_attempts::dsl;
+
+    Ok(dsl::remediation_attempts
+        .filter(dsl::hostname.eq(hostname))
+        .filter(dsl::service.eq(service))
+        .filter(dsl::attempted_at.ge(since))
+        .order((dsl::attempted_at.asc(), dsl::id.asc()))
+        .select(models::RemediationAttempt::as_select())
+        .load(conn)?)
+}
+
+/// Narrows `get_remediation_attempts`; `None` matches everything
+#[derive(Debug, Default)]
+pub struct RemediationFilter<'a> {
+    pub hostname: Option<&'a str>,
+    pub service: Option<&'a str>,
+    pub outcome: Option<&'a str>,
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+    /// Only attempts on targets this scope allows
+    pub scope: Option<&'a Scope>,
+}
+
+fn remediation_query<'a>(
+    filter: &RemediationFilter<'a>,
+) -> schema::remediation_attempts::BoxedQuery<'a, DbBackend> {
+    use schema::remediation_attempts::dsl;
+
+    let mut query = dsl::remediation_attempts.into_boxed();
+    if let Some(hostname) = filter.hostname {
+        query = query.filter(dsl::hostname.eq(hostname));
+    }
+    if let Some(service) = filter.service {
+        query = query.filter(dsl::service.eq(service));
+    }
+    if let Some(outcome) = filter.outcome {
+        query = query.filter(dsl::outcome.eq(outcome));
+    }
+    if let Some(since) = filter.since {
+        query = query.filter(dsl::attempted_at.ge(since));
+    }
+    if let Some(until) = filter.until {
+        query = query.filter(dsl::attempted_at.le(until));
+    }
+    if let Some(Scope::Owned(owned)) = filter.scope {
+        query = query.filter(scope_predicate!(
+            schema::remediation_attempts::table,
+            owned,
+            dsl::hostname,
+            dsl::service
+        ));
+    }
+    query
+}
+
+// RETRIEVE REMEDIATION ATTEMPTS
+/******************************************************************************/
+/// One page of matching attempts, newest first, and the total number of
+/// matching attempts
+pub fn get_remediation_attempts(
+    conn: &mut DbConnection,
+    filter: &RemediationFilter,
+    offset: i64,
+    limit: i64,
+) -> Result<(Vec<models::RemediationAttempt>, i64)> {
+    use schema::remediation_attempts::dsl;
+
+    let total: i64 = remediation_query(filter).count().get_result(conn)?;
+    let results: Vec<models::RemediationAttempt> = remediation_query(filter)
+        .order((dsl::attempted_at.desc(), dsl::id.desc()))
+        .offset(offset)
+        .limit(limit)
+        .select(models::RemediationAttempt::as_select())
+        .load(conn)?;
+
+    Ok((results, total))
 }
 
 /*----------------------------------STATUS------------------------------------*/
@@ -197,15 +959,14 @@ pub fn add_group_to_groups(parent_id: Option<i64>, name: &str) -> QueryResult<us
 // INSERT STATUS
 /******************************************************************************/
 pub fn insert_status(
+    conn: &mut DbConnection,
     hostname: &str,
     name: &str,
     description: Option<&str>,
     enabled: Option<bool>,
     active_status: Option<&str>,
     last_check: Option<NaiveDateTime>,
-) -> QueryResult<usize> {
-    let conn = &mut establish_connection();
-
+) -> Result<usize> {
     // TODO: this should be DEFAULT CURRENT_TIMESTAMP in the SQL
     let last_check = match last_check {
         Some(datetime) => Some(datetime),
@@ -218,18 +979,108 @@ pub fn insert_status(
         description,
         enabled,
         active_status,
+        sub_state: None,
+        main_pid: None,
+        n_restarts: None,
+        memory_current: None,
         last_check,
     };
 
-    diesel::insert_into(schema::service_status::table)
-        .values(&new_status)
-        .execute(conn)
+    insert_snapshot(conn, &new_status)
+}
+
+// INSERT STATUS FROM `systemctl show`
+/******************************************************************************/
+pub fn insert_unit_status(
+    conn: &mut DbConnection,
+    hostname: &str,
+    name: &str,
+    props: &UnitProperties,
+    last_check: NaiveDateTime,
+) -> Result<usize> {
+    let new_status = models::NewStatus {
+        hostname,
+        name,
+        description: props.description.as_deref(),
+        enabled: Some(props.is_enabled()),
+        active_status: props.active_state.as_ref().map(|state| state.as_str()),
+        sub_state: props.sub_state.as_ref().map(|state| state.as_str()),
+        main_pid: props.main_pid.map(i64::from),
+        n_restarts: props.n_restarts.and_then(|n| i32::try_from(n).ok()),
+        memory_current: props.memory_current.and_then(|bytes| i64::try_from(bytes).ok()),
+        last_check: Some(last_check),
+    };
+
+    insert_snapshot(conn, &new_status)
+}
+
+/// Append `new_status` to `service_status` and make it the service's row in
+/// `service_current_status`, unless that row is already newer
+fn insert_snapshot(conn: &mut DbConnection, new_status: &models::NewStatus) -> Result<usize> {
+    use schema::service_status::dsl;
+
+    conn.transaction::<_, SvcmonError, _>(|conn| {
+        let inserted = diesel::insert_into(dsl::service_status)
+            .values(new_status)
+            .execute(conn)?;
+
+        // MySQL has no `RETURNING`
+        let status_id = last_insert_id(conn)?;
+
+        let current = models::NewCurrentStatus {
+            hostname: new_status.hostname,
+            name: new_status.name,
+            status_id,
+            description: new_status.description,
+            enabled: new_status.enabled,
+            active_status: new_status.active_status,
+            sub_state: new_status.sub_state,
+            main_pid: new_status.main_pid,
+            n_restarts: new_status.n_restarts,
+            memory_current: new_status.memory_current,
+            last_check: new_status.last_check,
+        };
+        upsert_current_status(conn, &current)?;
+        Ok(inserted)
+    })
+}
+
+/// Update the service's row unless the stored row is newer, or insert it
+/// The update is a single conditional statement, so a backdated check cannot
+/// overwrite a newer row; a plain insert is used rather than `INSERT IGNORE`,
+/// which would turn every other error into a warning on MySQL
+fn upsert_current_status(conn: &mut DbConnection, current: &models::NewCurrentStatus) -> Result<()> {
+    use diesel::result::{DatabaseErrorKind, Error};
+    use schema::service_current_status::dsl;
+
+    let update = |conn: &mut DbConnection| {
+        // A backdated status leaves the stored row alone
+        let target = dsl::service_current_status
+            .find((current.hostname, current.name))
+            .filter(dsl::last_check.is_null().or(dsl::last_check.le(current.last_check)));
+        diesel::update(target).set(current).execute(conn)
+    };
+    if update(conn)? > 0 {
+        return Ok(());
+    }
+
+    let inserted = diesel::insert_into(dsl::service_current_status)
+        .values(current)
+        .execute(conn);
+    match inserted {
+        Ok(_) => Ok(()),
+        // The row exists: it is newer, or a concurrent check just inserted it
+        Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            update(conn)?;
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
 }
 
 // RETRIEVE ALL STATUSS
 /******************************************************************************/
-pub fn get_all_statuses() -> Result<Vec<models::Status>, Box<dyn Error>> {
-    let conn = &mut establish_connection();
+pub fn get_all_statuses(conn: &mut DbConnection) -> Result<Vec<models::Status>> {
     let results: Vec<models::Status> = schema::service_status::table
         .filter(schema::service_status::last_check.is_not_null())
         .select(models::Status::as_select())
@@ -240,112 +1091,319 @@ pub fn get_all_statuses() -> Result<Vec<models::Status>, Box<dyn Error>> {
 
 // GET ALL LATEST STATUSES
 /******************************************************************************/
-/// Perform raw SQL as diesel does not implement true `GROUP BY`
-pub fn get_latest_statuses() -> Result<Vec<models::Status>, Box<dyn Error>> {
-    let conn = &mut establish_connection();
-
-    const SQL_LATEST_STATUSES: &str = "SELECT * FROM (SELECT * FROM service_status WHERE last_check IS NOT NULL ORDER BY last_check DESC) AS temp GROUP BY temp.hostname, temp.name;";
+/// Newest row per `(hostname, name)`, read from `service_current_status`
+pub fn get_latest_statuses(conn: &mut DbConnection) -> Result<Vec<models::Status>> {
+    let results: Vec<models::CurrentStatus> = schema::service_current_status::table
+        .order((
+            schema::service_current_status::hostname.asc(),
+            schema::service_current_status::name.asc(),
+        ))
+        .select(models::CurrentStatus::as_select())
+        .load(conn)?;
 
-    match sql_query(SQL_LATEST_STATUSES).load(conn) {
-        Ok(statuses) => Ok(statuses),
-        Err(err) => Err(Box::new(GenericError(format!("{:?}", err)))),
-    }
+    Ok(results.into_iter().map(models::Status::from).collect())
 }
 
-/// Get latest statuses from `service_status` table for hosts and services 
-/// within `host.json` file
-pub fn get_json_statuses() -> Result<Vec<models::Status>, Box<dyn Error>> {
-    let hosts = match Hosts::init_from_file() {
-        Ok(hosts) => hosts,
-        Err(_) => {
-            return Err(Box::new(GenericError("error".to_string())));
-        }
-    };
+/// Get latest statuses for hosts and services within `host.json` file, in
+/// one query against `service_current_status`
+pub fn get_json_statuses(conn: &mut DbConnection) -> Result<Vec<models::Status>> {
+    let hosts = Hosts::init_from_file()?.get_hosts();
 
-    enum SendStatus {
-        Status(models::Status),
-        Error(String),
-    }
-
-    let (tx, rx) = mpsc::channel();
-    let mut handles = vec![];
-    let mut hosts_from_db: Vec<models::Status> = vec![];
-    for host in hosts.get_hosts() {
-        let tx_cpy = mpsc::Sender::clone(&tx);
-        // TODO: Use an async library to better handle errors from 
-        // `get_status_from_db`
-        let handle = thread::spawn(move || {
-            for service in host.services {
-                let tmp = match get_status_from_db(&host.hostname, &service)  {
-                    Ok(status) => {
-                        SendStatus::Status(status)
-                    },
-                    Err(e) => {
-                        SendStatus::Error(format!("Error retrieving,{}, from {}: {}",
-                                                  &host.hostname, service, e))
-                    },
-                };
-                if tx_cpy.send(tmp).is_err() {
-                    println!("Breaking on {}, {}.", &host.hostname, &service);
-                    break;
-                }
-            }
-        });
-        handles.push(handle);
-    }
-    // Must manually `drop(tx)` since doesn't send anything
-    drop(tx);
+    let hostnames: Vec<&str> = hosts.iter().map(|host| host.hostname.as_str()).collect();
+    let monitored: HashSet<(&str, &str)> = hosts
+        .iter()
+        .flat_map(|host| {
+            host.services
+                .iter()
+                .map(move |service| (host.hostname.as_str(), service.as_str()))
+        })
+        .collect();
 
-    // Load statuses from threads
-    for status_res in rx {
-        match status_res {
-            SendStatus::Status(status) => {
-                hosts_from_db.push(status);
-            }
-            SendStatus::Error(e_str) => eprintln!("{}", e_str),
-        }
-    }
+    let results: Vec<models::CurrentStatus> = schema::service_current_status::table
+        .filter(schema::service_current_status::hostname.eq_any(hostnames))
+        .order((
+            schema::service_current_status::hostname.asc(),
+            schema::service_current_status::name.asc(),
+        ))
+        .select(models::CurrentStatus::as_select())
+        .load(conn)?;
 
-    Ok(hosts_from_db)
+    // Services never checked yet have no row and are left out
+    Ok(results
+        .into_iter()
+        .filter(|status| monitored.contains(&(status.hostname.as_str(), status.name.as_str())))
+        .map(models::Status::from)
+        .collect())
 }
 
 // RETRIEVE STATUS BY NAME
 /******************************************************************************/
+/// Newest status of one service
 pub fn get_status_from_db(
+    conn: &mut DbConnection,
     hostname: &str,
     servicename: &str,
-) -> Result<models::Status, Box<dyn Error>> {
-    let conn = &mut establish_connection();
-    let mut result: Vec<models::Status> = schema::service_status::table
-        .filter(schema::service_status::hostname.eq(hostname))
-        .filter(schema::service_status::name.eq(servicename))
-        .order(schema::service_status::last_check.desc())
-        .limit(1)
-        .select(models::Status::as_select())
-        .load(conn)?;
+) -> Result<models::Status> {
+    let result: Option<models::CurrentStatus> = schema::service_current_status::table
+        .find((hostname, servicename))
+        .select(models::CurrentStatus::as_select())
+        .first(conn)
+        .optional()?;
 
-    let result: models::Status = result.pop().ok_or(format!(
-        "Error retrieving service: '{}' on host: '{}', from table, `service_status`",
+    let result: models::CurrentStatus = result.ok_or_else(|| SvcmonError::NotFound(format!(
+        "Error retrieving service: '{}' on host: '{}', from table, `service_current_status`",
         servicename, hostname
-    ))?;
+    )))?;
 
-    Ok(result)
+    Ok(result.into())
 }
 
 // GET STATUS BY ID
 /******************************************************************************/
-pub fn get_status_by_id(id: i64) -> Result<models::Status, Box<dyn Error>> {
-    let conn = &mut establish_connection();
+pub fn get_status_by_id(conn: &mut DbConnection, id: i64) -> Result<models::Status> {
     let mut result: Vec<models::Status> = schema::service_status::table
         .filter(schema::service_status::id.eq(id))
         .limit(1)
         .select(models::Status::as_select())
         .load(conn)?;
 
-    let result: models::Status = result.pop().ok_or(format!(
+    let result: models::Status = result.pop().ok_or_else(|| SvcmonError::NotFound(format!(
         "Error retrieving `Status` by `id` = {} from  `service_status`",
         id
-    ))?;
+    )))?;
 
     Ok(result)
 }
+
+/*----------------------------------EVENTS------------------------------------*/
+
+// RECORD STATUS AND STATE CHANGE
+/******************************************************************************/
+/// Insert a snapshot from `systemctl show` and, when `ActiveState`,
+/// `UnitFileState` or `SubState` differ from the previous snapshot, a
+/// `service_events` row holding the old and new values
+pub fn record_unit_status(
+    conn: &mut DbConnection,
+    hostname: &str,
+    name: &str,
+    props: &UnitProperties,
+    last_check: NaiveDateTime,
+) -> Result<()> {
+    conn.transaction::<_, SvcmonError, _>(|conn| {
+        let previous = match get_status_from_db(conn, hostname, name) {
+            Ok(status) => Some(status),
+            Err(SvcmonError::NotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+        insert_unit_status(conn, hostname, name, props, last_check)?;
+
+        // First snapshot for this service; nothing to compare against
+        let previous = match previous {
+            Some(previous) => previous,
+            None => return Ok(()),
+        };
+
+        let new_active_status = props.active_state.as_ref().map(|state| state.as_str());
+        let new_enabled = Some(props.is_enabled());
+        let new_sub_state = props.sub_state.as_ref().map(|state| state.as_str());
+
+        // Rows written before `sub_state` existed have it unset; don't count
+        // that as a change
+        let sub_state_changed =
+            previous.sub_state.is_some() && previous.sub_state.as_deref() != new_sub_state;
+        if previous.active_status.as_deref() == new_active_status
+            && previous.enabled == new_enabled
+            && !sub_state_changed
+        {
+            return Ok(());
+        }
+
+        let new_event = models::NewServiceEvent {
+            hostname,
+            name,
+            old_active_status: previous.active_status.as_deref(),
+            new_active_status,
+            old_enabled: previous.enabled,
+            new_enabled,
+            old_sub_state: previous.sub_state.as_deref(),
+            new_sub_state,
+            changed_at: last_check,
+        };
+        diesel::insert_into(schema::service_events::table)
+            .values(&new_event)
+            .execute(conn)?;
+        Ok(())
+    })
+}
+
+// RETRIEVE EVENTS
+/******************************************************************************/
+/// Narrows `get_events`; `None` matches everything
+#[derive(Debug, Default)]
+pub struct EventFilter<'a> {
+    pub hostname: Option<&'a str>,
+    pub name: Option<&'a str>,
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+    /// Only events on services this scope allows
+    pub scope: Option<&'a Scope>,
+}
+
+fn event_query<'a>(filter: &EventFilter<'a>) -> schema::service_events::BoxedQuery<'a, DbBackend> {
+    use schema::service_events::dsl;
+
+    let mut query = dsl::service_events.into_boxed();
+    if let Some(hostname) = filter.hostname {
+        query = query.filter(dsl::hostname.eq(hostname));
+    }
+    if let Some(name) = filter.name {
+        query = query.filter(dsl::name.eq(name));
+    }
+    if let Some(since) = filter.since {
+        query = query.filter(dsl::changed_at.ge(since));
+    }
+    if let Some(until) = filter.until {
+        query = query.filter(dsl::changed_at.le(until));
+    }
+    if let Some(Scope::Owned(owned)) = filter.scope {
+        query = query.filter(scope_predicate!(
+            schema::service_events::table,
+            owned,
+            dsl::hostname,
+            dsl::name
+        ));
+    }
+    query
+}
+
+/// One page of matching state changes, newest first, and the total number of
+/// matching changes
+pub fn get_events(
+    conn: &mut DbConnection,
+    filter: &EventFilter,
+    offset: i64,
+    limit: i64,
+) -> Result<(Vec<models::ServiceEvent>, i64)> {
+    use schema::service_events::dsl;
+
+    let total: i64 = event_query(filter).count().get_result(conn)?;
+    let results: Vec<models::ServiceEvent> = event_query(filter)
+        .order((dsl::changed_at.desc(), dsl::id.desc()))
+        .offset(offset)
+        .limit(limit)
+        .select(models::ServiceEvent::as_select())
+        .load(conn)?;
+
+    Ok((results, total))
+}
+
+/*---------------------------------ROLLUPS------------------------------------*/
+
+// OLDEST STATUS CHECK
+/******************************************************************************/
+pub fn get_oldest_last_check(conn: &mut DbConnection) -> Result<Option<NaiveDateTime>> {
+    let oldest: Option<NaiveDateTime> = schema::service_status::table
+        .select(diesel::dsl::min(schema::service_status::last_check))
+        .first(conn)?;
+
+    Ok(oldest)
+}
+
+// RETRIEVE STATUSES IN RANGE
+/******************************************************************************/
+/// Statuses checked in `[from, to)`, ordered by host, service then time
+pub fn get_statuses_between(
+    conn: &mut DbConnection,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> Result<Vec<models::Status>> {
+    use schema::service_status::dsl;
+
+    let results: Vec<models::Status> = dsl::service_status
+        .filter(dsl::last_check.ge(from))
+        .filter(dsl::last_check.lt(to))
+        .order((dsl::hostname.asc(), dsl::name.asc(), dsl::last_check.asc()))
+        .select(models::Status::as_select())
+        .load(conn)?;
+
+    Ok(results)
+}
+
+// DELETE STATUSES IN RANGE
+/******************************************************************************/
+/// Delete statuses checked in `[from, to)`
+pub fn delete_statuses_between(
+    conn: &mut DbConnection,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> Result<usize> {
+    use schema::service_status::dsl;
+
+    let deleted = diesel::delete(
+        dsl::service_status
+            .filter(dsl::last_check.ge(from))
+            .filter(dsl::last_check.lt(to)),
+    )
+    .execute(conn)?;
+
+    Ok(deleted)
+}
+
+// ADD TO ROLLUP
+/******************************************************************************/
+/// Insert a rollup, or merge it into the existing row for the same host,
+/// service, period and start
+pub fn add_to_rollup(conn: &mut DbConnection, rollup: &models::NewStatusRollup) -> Result<()> {
+    use schema::service_status_rollups::dsl;
+
+    let mut existing: Vec<models::StatusRollup> = dsl::service_status_rollups
+        .filter(dsl::hostname.eq(rollup.hostname))
+        .filter(dsl::name.eq(rollup.name))
+        .filter(dsl::period.eq(rollup.period))
+        .filter(dsl::period_start.eq(rollup.period_start))
+        .limit(1)
+        .select(models::StatusRollup::as_select())
+        .load(conn)?;
+
+    match existing.pop() {
+        Some(existing) => {
+            let samples = existing.samples + rollup.samples;
+            let active_samples = existing.active_samples + rollup.active_samples;
+            diesel::update(dsl::service_status_rollups.find(existing.id))
+                .set((
+                    dsl::samples.eq(samples),
+                    dsl::active_samples.eq(active_samples),
+                    dsl::percent_active.eq(100.0 * active_samples as f64 / samples.max(1) as f64),
+                    dsl::flaps.eq(existing.flaps + rollup.flaps),
+                ))
+                .execute(conn)?;
+        }
+        None => {
+            diesel::insert_into(dsl::service_status_rollups)
+                .values(rollup)
+                .execute(conn)?;
+        }
+    }
+
+    Ok(())
+}
+
+// DELETE OLD ROLLUPS
+/******************************************************************************/
+/// Delete rollups of `period` (`"hour"` or `"day"`) that start before `before`
+pub fn delete_rollups_before(
+    conn: &mut DbConnection,
+    period: &str,
+    before: NaiveDateTime,
+) -> Result<usize> {
+    use schema::service_status_rollups::dsl;
+
+    let deleted = diesel::delete(
+        dsl::service_status_rollups
+            .filter(dsl::period.eq(period))
+            .filter(dsl::period_start.lt(before)),
+    )
+    .execute(conn)?;
+
+    Ok(deleted)
+}
@@ -5,10 +5,12 @@ use serde::Serialize;
 
 #[derive(Queryable, Selectable, Serialize, Debug)]
 #[diesel(table_name = crate::schema::users)]
-#[diesel(check_for_backend(diesel::mysql::Mysql))]
+#[diesel(check_for_backend(crate::database::DbBackend))]
 pub struct User{
     pub id: i64,
     pub name: String,
+    /// Argon2 hash; never sent to clients
+    #[serde(skip_serializing)]
     pub password: String,
     pub group_accts_id: Option<i64>,
     pub active: bool,
@@ -19,10 +21,10 @@ pub struct User{
 
 #[derive(Queryable, Selectable, Serialize, Debug)]
 #[diesel(table_name = crate::schema::group_accts)]
-#[diesel(check_for_backend(diesel::mysql::Mysql))]
+#[diesel(check_for_backend(crate::database::DbBackend))]
 pub struct Group{
     pub id: i64,
-    parent_id: Option<i64>,
+    pub parent_id: Option<i64>,
     pub name: String,
     pub created: NaiveDateTime,
     pub modified: NaiveDateTime,
@@ -30,7 +32,7 @@ pub struct Group{
 
 #[derive(Queryable, QueryableByName, Selectable, Serialize, Insertable, Debug)]
 #[diesel(table_name = crate::schema::service_status)]
-#[diesel(check_for_backend(diesel::mysql::Mysql))]
+#[diesel(check_for_backend(crate::database::DbBackend))]
 pub struct Status{
     pub id: i64,
     pub hostname: String,
@@ -38,9 +40,209 @@ pub struct Status{
     pub description: Option<String>,
     pub enabled: Option<bool>,
     pub active_status: Option<String>,
+    pub sub_state: Option<String>,
+    pub main_pid: Option<i64>,
+    pub n_restarts: Option<i32>,
+    pub memory_current: Option<i64>,
+    pub last_check: Option<NaiveDateTime>
+}
+
+/// A bearer token belonging to a user
+#[derive(Queryable, Selectable, Serialize, Debug)]
+#[diesel(table_name = crate::schema::api_tokens)]
+#[diesel(check_for_backend(crate::database::DbBackend))]
+pub struct ApiToken{
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    /// Public part of the token, used to find this row
+    pub prefix: String,
+    /// Hex SHA-256 of the secret part; never sent to clients
+    #[serde(skip_serializing)]
+    pub secret_hash: String,
+    /// Comma-separated permissions, e.g. `view,operate`
+    pub scopes: String,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used: Option<NaiveDateTime>,
+    pub revoked: bool,
+    pub created: NaiveDateTime,
+}
+
+/// Who did what, from `audit_log`
+#[derive(Queryable, Selectable, Serialize, Debug)]
+#[diesel(table_name = crate::schema::audit_log)]
+#[diesel(check_for_backend(crate::database::DbBackend))]
+pub struct AuditEntry{
+    pub id: i64,
+    pub actor: String,
+    pub user_id: Option<i64>,
+    pub token_id: Option<i64>,
+    pub action: String,
+    pub hostname: Option<String>,
+    pub service: Option<String>,
+    /// Request body or parameters, as JSON
+    pub payload: Option<String>,
+    pub output: Option<String>,
+    pub exit_code: Option<i32>,
+    pub success: bool,
+    pub created_at: NaiveDateTime,
+}
+
+/// One firing of an alert rule, from `alerts`
+#[derive(Queryable, Selectable, Serialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::alerts)]
+#[diesel(check_for_backend(crate::database::DbBackend))]
+pub struct Alert{
+    pub id: i64,
+    /// `name` of the rule in `[[config.alerts.rules]]`
+    pub rule: String,
+    pub hostname: String,
+    pub service: String,
+    pub severity: Option<String>,
+    /// `firing` or `resolved`
+    pub state: String,
+    /// `ActiveState` when the alert fired
+    pub active_state: Option<String>,
+    pub message: String,
+    pub fired_at: NaiveDateTime,
+    pub resolved_at: Option<NaiveDateTime>,
+}
+
+/// A host, or one service on it, owned by a group
+#[derive(Queryable, Selectable, Serialize, Debug)]
+#[diesel(table_name = crate::schema::group_hosts)]
+#[diesel(check_for_backend(crate::database::DbBackend))]
+pub struct GroupHost{
+    pub id: i64,
+    pub group_accts_id: i64,
+    pub hostname: String,
+    /// `None` owns every service on the host
+    pub service: Option<String>,
+}
+
+/// A permission granted to a group and, through `parent_id`, its subgroups
+#[derive(Queryable, Selectable, Serialize, Debug)]
+#[diesel(table_name = crate::schema::group_permissions)]
+#[diesel(check_for_backend(crate::database::DbBackend))]
+pub struct GroupPermission{
+    pub id: i64,
+    pub group_accts_id: i64,
+    pub permission: String,
+}
+
+/// A period during which alerts and remediation are suppressed
+/// Applies to services matching every one of `hostname`, `service` and
+/// `group_accts_id` that is set
+#[derive(Queryable, Selectable, Serialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::maintenance_windows)]
+#[diesel(check_for_backend(crate::database::DbBackend))]
+pub struct MaintenanceWindow{
+    pub id: i64,
+    pub reason: String,
+    /// Hostname or glob
+    pub hostname: Option<String>,
+    /// Service name or glob
+    pub service: Option<String>,
+    pub group_accts_id: Option<i64>,
+    /// One-off windows only
+    pub starts_at: Option<NaiveDateTime>,
+    pub ends_at: Option<NaiveDateTime>,
+    /// Recurring windows only: five-field cron expression, in UTC
+    pub cron: Option<String>,
+    /// Recurring windows only: how long each occurrence lasts
+    pub duration_secs: Option<i64>,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// One automatic restart made by a remediation policy
+#[derive(Queryable, Selectable, Serialize, Debug)]
+#[diesel(table_name = crate::schema::remediation_attempts)]
+#[diesel(check_for_backend(crate::database::DbBackend))]
+pub struct RemediationAttempt{
+    pub id: i64,
+    pub hostname: String,
+    pub service: String,
+    /// 1 for the first attempt in the policy's window
+    pub attempt: i32,
+    /// `success` or `failed`
+    pub outcome: String,
+    pub exit_code: Option<i32>,
+    /// `ActiveState` the unit settled in
+    pub final_state: Option<String>,
+    /// Why the attempt failed
+    pub message: Option<String>,
+    pub duration_ms: i64,
+    pub attempted_at: NaiveDateTime,
+}
+
+/// Newest `Status` per service, kept current by the collector
+#[derive(Queryable, Selectable, Serialize, Debug)]
+#[diesel(table_name = crate::schema::service_current_status)]
+#[diesel(check_for_backend(crate::database::DbBackend))]
+pub struct CurrentStatus{
+    pub hostname: String,
+    pub name: String,
+    pub status_id: i64,
+    pub description: Option<String>,
+    pub enabled: Option<bool>,
+    pub active_status: Option<String>,
+    pub sub_state: Option<String>,
+    pub main_pid: Option<i64>,
+    pub n_restarts: Option<i32>,
+    pub memory_current: Option<i64>,
     pub last_check: Option<NaiveDateTime>
 }
 
+impl From<CurrentStatus> for Status {
+    fn from(current: CurrentStatus) -> Self {
+        Status {
+            id: current.status_id,
+            hostname: current.hostname,
+            name: current.name,
+            description: current.description,
+            enabled: current.enabled,
+            active_status: current.active_status,
+            sub_state: current.sub_state,
+            main_pid: current.main_pid,
+            n_restarts: current.n_restarts,
+            memory_current: current.memory_current,
+            last_check: current.last_check,
+        }
+    }
+}
+
+#[derive(Queryable, Selectable, Serialize, Debug)]
+#[diesel(table_name = crate::schema::service_events)]
+#[diesel(check_for_backend(crate::database::DbBackend))]
+pub struct ServiceEvent{
+    pub id: i64,
+    pub hostname: String,
+    pub name: String,
+    pub old_active_status: Option<String>,
+    pub new_active_status: Option<String>,
+    pub old_enabled: Option<bool>,
+    pub new_enabled: Option<bool>,
+    pub old_sub_state: Option<String>,
+    pub new_sub_state: Option<String>,
+    pub changed_at: NaiveDateTime,
+}
+
+#[derive(Queryable, Selectable, Serialize, Debug)]
+#[diesel(table_name = crate::schema::service_status_rollups)]
+#[diesel(check_for_backend(crate::database::DbBackend))]
+pub struct StatusRollup{
+    pub id: i64,
+    pub hostname: String,
+    pub name: String,
+    pub period: String,
+    pub period_start: NaiveDateTime,
+    pub samples: i32,
+    pub active_samples: i32,
+    pub percent_active: f64,
+    pub flaps: i32,
+}
+
 
 // NEW RECORDS
 /******************************************************************************/
@@ -55,6 +257,18 @@ pub struct NewUser<'a> {
     pub active: Option<bool>,
 }
 
+/// Columns of `users` to change; `None` leaves a column as it is
+#[derive(AsChangeset, Debug, Default)]
+#[diesel(table_name = users)]
+pub struct UserChanges<'a> {
+    pub name: Option<&'a str>,
+    /// Argon2 hash, not the plain-text password
+    pub password: Option<&'a str>,
+    /// `Some(None)` removes the user from its group
+    pub group_accts_id: Option<Option<i64>>,
+    pub active: Option<bool>,
+}
+
 use crate::schema::group_accts;
 
 #[derive(Insertable, Debug, Deserialize)]
@@ -64,6 +278,113 @@ pub struct NewGroup<'a> {
     pub name: &'a str,
 }
 
+/// Columns of `group_accts` to change; `None` leaves a column as it is
+#[derive(AsChangeset, Debug, Default)]
+#[diesel(table_name = group_accts)]
+pub struct GroupChanges<'a> {
+    pub name: Option<&'a str>,
+    /// `Some(None)` makes the group a root
+    pub parent_id: Option<Option<i64>>,
+}
+
+use crate::schema::api_tokens;
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = api_tokens)]
+pub struct NewApiToken<'a> {
+    pub user_id: i64,
+    pub name: &'a str,
+    pub prefix: &'a str,
+    pub secret_hash: &'a str,
+    pub scopes: &'a str,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+use crate::schema::audit_log;
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = audit_log)]
+pub struct NewAuditEntry<'a> {
+    pub actor: &'a str,
+    pub user_id: Option<i64>,
+    pub token_id: Option<i64>,
+    pub action: &'a str,
+    pub hostname: Option<&'a str>,
+    pub service: Option<&'a str>,
+    pub payload: Option<&'a str>,
+    pub output: Option<&'a str>,
+    pub exit_code: Option<i32>,
+    pub success: bool,
+    pub created_at: NaiveDateTime,
+}
+
+use crate::schema::alerts;
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = alerts)]
+pub struct NewAlert<'a> {
+    pub rule: &'a str,
+    pub hostname: &'a str,
+    pub service: &'a str,
+    pub severity: Option<&'a str>,
+    pub state: &'a str,
+    pub active_state: Option<&'a str>,
+    pub message: &'a str,
+    pub fired_at: NaiveDateTime,
+}
+
+use crate::schema::maintenance_windows;
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = maintenance_windows)]
+pub struct NewMaintenanceWindow<'a> {
+    pub reason: &'a str,
+    pub hostname: Option<&'a str>,
+    pub service: Option<&'a str>,
+    pub group_accts_id: Option<i64>,
+    pub starts_at: Option<NaiveDateTime>,
+    pub ends_at: Option<NaiveDateTime>,
+    pub cron: Option<&'a str>,
+    pub duration_secs: Option<i64>,
+    pub created_by: &'a str,
+    pub created_at: NaiveDateTime,
+}
+
+use crate::schema::remediation_attempts;
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = remediation_attempts)]
+pub struct NewRemediationAttempt<'a> {
+    pub hostname: &'a str,
+    pub service: &'a str,
+    pub attempt: i32,
+    pub outcome: &'a str,
+    pub exit_code: Option<i32>,
+    pub final_state: Option<&'a str>,
+    pub message: Option<&'a str>,
+    pub duration_ms: i64,
+    pub attempted_at: NaiveDateTime,
+}
+
+use crate::schema::group_hosts;
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = group_hosts)]
+pub struct NewGroupHost<'a> {
+    pub group_accts_id: i64,
+    pub hostname: &'a str,
+    pub service: Option<&'a str>,
+}
+
+use crate::schema::group_permissions;
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = group_permissions)]
+pub struct NewGroupPermission<'a> {
+    pub group_accts_id: i64,
+    pub permission: &'a str,
+}
+
 use crate::schema::service_status;
 
 #[derive(Insertable, Debug, Deserialize)]
@@ -74,6 +395,62 @@ pub struct NewStatus<'a> {
     pub description: Option<&'a str>,
     pub enabled: Option<bool>,
     pub active_status: Option<&'a str>,
+    pub sub_state: Option<&'a str>,
+    pub main_pid: Option<i64>,
+    pub n_restarts: Option<i32>,
+    pub memory_current: Option<i64>,
+    pub last_check: Option<NaiveDateTime>,
+}
+
+use crate::schema::service_current_status;
+
+/// `None` fields overwrite the stored value, so a snapshot never keeps stale
+/// columns from the one before it
+#[derive(Insertable, AsChangeset, Debug)]
+#[diesel(table_name = service_current_status)]
+#[diesel(primary_key(hostname, name))]
+#[diesel(treat_none_as_null = true)]
+pub struct NewCurrentStatus<'a> {
+    pub hostname: &'a str,
+    pub name: &'a str,
+    pub status_id: i64,
+    pub description: Option<&'a str>,
+    pub enabled: Option<bool>,
+    pub active_status: Option<&'a str>,
+    pub sub_state: Option<&'a str>,
+    pub main_pid: Option<i64>,
+    pub n_restarts: Option<i32>,
+    pub memory_current: Option<i64>,
     pub last_check: Option<NaiveDateTime>,
 }
 
+use crate::schema::service_events;
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = service_events)]
+pub struct NewServiceEvent<'a> {
+    pub hostname: &'a str,
+    pub name: &'a str,
+    pub old_active_status: Option<&'a str>,
+    pub new_active_status: Option<&'a str>,
+    pub old_enabled: Option<bool>,
+    pub new_enabled: Option<bool>,
+    pub old_sub_state: Option<&'a str>,
+    pub new_sub_state: Option<&'a str>,
+    pub changed_at: NaiveDateTime,
+}
+
+use crate::schema::service_status_rollups;
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = service_status_rollups)]
+pub struct NewStatusRollup<'a> {
+    pub hostname: &'a str,
+    pub name: &'a str,
+    pub period: &'a str,
+    pub period_start: NaiveDateTime,
+    pub samples: i32,
+    pub active_samples: i32,
+    pub percent_active: f64,
+    pub flaps: i32,
+}
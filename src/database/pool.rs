@@ -0,0 +1,102 @@
+use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
+use dotenvy::dotenv;
+use std::env;
+use std::time::Duration;
+
+use super::DbConnection;
+use crate::config::read_config_file;
+use crate::error::{Result, SvcmonError};
+
+/// Connection pool managed by Rocket and shared with the scheduler
+pub type DbPool = Pool<ConnectionManager<DbConnection>>;
+
+/// Connection checked out of `DbPool`
+pub type DbConn = PooledConnection<ConnectionManager<DbConnection>>;
+
+// DATABASE URL
+/******************************************************************************/
+/// `DATABASE_URL` if set, otherwise `url` from `[config.database]`
+/// The URL must match the backend svcmon was built with
+pub fn database_url() -> Result<String> {
+    dotenv().ok();
+
+    let url = match env::var("DATABASE_URL") {
+        Ok(url) => url,
+        Err(_) => read_config_file().database.url.clone().ok_or_else(|| {
+            SvcmonError::Config(
+                "set DATABASE_URL or `url` under [config.database] in 'svcmon.toml'".to_string(),
+            )
+        })?,
+    };
+    check_url(url)
+}
+
+#[cfg(feature = "mysql")]
+fn check_url(url: String) -> Result<String> {
+    if url.starts_with("mysql://") {
+        Ok(url)
+    } else {
+        Err(SvcmonError::Config(format!(
+            "svcmon was built for MySQL, but the database URL is '{}'",
+            url
+        )))
+    }
+}
+
+/// SQLite takes a file path; accept and strip a `sqlite://` prefix
+#[cfg(feature = "sqlite")]
+fn check_url(url: String) -> Result<String> {
+    if url.starts_with("mysql://") {
+        return Err(SvcmonError::Config(format!(
+            "svcmon was built for SQLite, but the database URL is '{}'",
+            url
+        )));
+    }
+    Ok(url.trim_start_matches("sqlite://").to_string())
+}
+
+// BUILD POOL
+/******************************************************************************/
+/// Build the pool from the database URL and `[config.database]`
+/// Connections are opened lazily so the server still starts while the
+/// database is down
+pub fn init_pool() -> Result<DbPool> {
+    let config = &read_config_file().database;
+
+    let manager = ConnectionManager::<DbConnection>::new(database_url()?);
+    let builder = Pool::builder()
+        .max_size(config.pool_size)
+        .min_idle(Some(0))
+        .connection_timeout(Duration::from_secs(config.connection_timeout_secs))
+        .idle_timeout(config.idle_timeout_secs.map(Duration::from_secs))
+        .test_on_check_out(config.test_on_check_out);
+
+    #[cfg(feature = "sqlite")]
+    let builder = builder.connection_customizer(Box::new(SqlitePragmas));
+
+    Ok(builder.build_unchecked(manager))
+}
+
+/// Per-connection SQLite settings: wait on locks instead of failing with
+/// "database is locked", and enforce the `REFERENCES` clauses
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+struct SqlitePragmas;
+
+#[cfg(feature = "sqlite")]
+impl diesel::r2d2::CustomizeConnection<DbConnection, diesel::r2d2::Error> for SqlitePragmas {
+    fn on_acquire(&self, conn: &mut DbConnection) -> std::result::Result<(), diesel::r2d2::Error> {
+        use diesel::connection::SimpleConnection;
+        conn.batch_execute("PRAGMA busy_timeout = 5000; PRAGMA foreign_keys = ON;")
+            .map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+// CHECK OUT CONNECTION
+/******************************************************************************/
+/// Check out a connection, failing with `SvcmonError::Unavailable` (503) when
+/// the database cannot be reached
+pub fn get_conn(pool: &DbPool) -> Result<DbConn> {
+    pool.get()
+        .map_err(|e| SvcmonError::Unavailable(format!("database unreachable: {}", e)))
+}
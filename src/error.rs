@@ -0,0 +1,133 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use rocket::http::Status;
+use rocket::request::Request;
+use rocket::response::{self, status::Custom, Responder};
+use rocket::serde::json::Json;
+
+use crate::response::GenericResponse;
+
+// SVCMON ERROR
+/******************************************************************************/
+/// Crate-wide error type
+/// Each variant maps to one HTTP status when returned from a route
+#[derive(Debug)]
+pub enum SvcmonError {
+    /// Could not run `systemctl` on the host
+    Transport(String),
+    /// The host did not answer within its timeout
+    Timeout(String),
+    /// Output from a host that could not be understood
+    Parse(String),
+    /// Unreadable or invalid `svcmon.toml` or `hosts.json`
+    Config(String),
+    /// A database query failed
+    Database(String),
+    /// A backing service, such as the database, cannot be reached
+    Unavailable(String),
+    /// The requested host, service or record does not exist
+    NotFound(String),
+    /// The request itself is invalid
+    Validation(String),
+    /// Missing, expired or wrong credentials
+    Unauthorized(String),
+    /// The caller is logged in but lacks the permission
+    Forbidden(String),
+    /// The request clashes with one already in progress or completed
+    Conflict(String),
+    /// A unit ended up `failed` after `start`, `stop` or `restart`
+    UnitFailed(String),
+    /// `systemctl` ran but exited with an unexpected code
+    CommandFailed(String),
+    /// A notification could not be delivered
+    Notify(String),
+}
+
+/// Shorthand used throughout the crate
+pub type Result<T> = std::result::Result<T, SvcmonError>;
+
+impl SvcmonError {
+    /// HTTP status returned for this error
+    pub fn status(&self) -> Status {
+        match self {
+            SvcmonError::Transport(_) => Status::BadGateway,
+            SvcmonError::Timeout(_) => Status::GatewayTimeout,
+            SvcmonError::Parse(_) => Status::BadGateway,
+            SvcmonError::Config(_) => Status::InternalServerError,
+            SvcmonError::Database(_) => Status::InternalServerError,
+            SvcmonError::Unavailable(_) => Status::ServiceUnavailable,
+            SvcmonError::NotFound(_) => Status::NotFound,
+            SvcmonError::Validation(_) => Status::BadRequest,
+            SvcmonError::Unauthorized(_) => Status::Unauthorized,
+            SvcmonError::Forbidden(_) => Status::Forbidden,
+            SvcmonError::Conflict(_) => Status::Conflict,
+            SvcmonError::UnitFailed(_) => Status::BadGateway,
+            SvcmonError::CommandFailed(_) => Status::BadGateway,
+            SvcmonError::Notify(_) => Status::BadGateway,
+        }
+    }
+}
+
+impl fmt::Display for SvcmonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SvcmonError::Transport(msg) => write!(f, "Transport error: {}", msg),
+            SvcmonError::Timeout(msg) => write!(f, "Timed out: {}", msg),
+            SvcmonError::Parse(msg) => write!(f, "Parse error: {}", msg),
+            SvcmonError::Config(msg) => write!(f, "Config error: {}", msg),
+            SvcmonError::Database(msg) => write!(f, "Database error: {}", msg),
+            SvcmonError::Unavailable(msg) => write!(f, "Unavailable: {}", msg),
+            SvcmonError::NotFound(msg) => write!(f, "Not found: {}", msg),
+            SvcmonError::Validation(msg) => write!(f, "Invalid request: {}", msg),
+            SvcmonError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
+            SvcmonError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
+            SvcmonError::Conflict(msg) => write!(f, "Conflict: {}", msg),
+            SvcmonError::UnitFailed(msg) => write!(f, "Unit failed: {}", msg),
+            SvcmonError::CommandFailed(msg) => write!(f, "Command failed: {}", msg),
+            SvcmonError::Notify(msg) => write!(f, "Notification failed: {}", msg),
+        }
+    }
+}
+
+impl Error for SvcmonError {}
+
+// CONVERSIONS
+/******************************************************************************/
+impl From<io::Error> for SvcmonError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::TimedOut => SvcmonError::Timeout(err.to_string()),
+            _ => SvcmonError::Transport(err.to_string()),
+        }
+    }
+}
+
+impl From<diesel::result::Error> for SvcmonError {
+    fn from(err: diesel::result::Error) -> Self {
+        match err {
+            diesel::result::Error::NotFound => SvcmonError::NotFound(err.to_string()),
+            _ => SvcmonError::Database(err.to_string()),
+        }
+    }
+}
+
+impl From<diesel::ConnectionError> for SvcmonError {
+    fn from(err: diesel::ConnectionError) -> Self {
+        SvcmonError::Database(err.to_string())
+    }
+}
+
+// RESPONDER
+/******************************************************************************/
+/// Errors are returned as a `GenericResponse` with `status == "fail"`
+impl<'r> Responder<'r, 'static> for SvcmonError {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let body = GenericResponse {
+            status: String::from("fail"),
+            message: self.to_string(),
+        };
+        Custom(self.status(), Json(body)).respond_to(req)
+    }
+}
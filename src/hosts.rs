@@ -1,25 +1,12 @@
 use crate::config::read_config_file;
+use crate::error::{Result, SvcmonError};
+use crate::remediation::RemediationPolicy;
+use crate::transport::TransportKind;
 
 use serde::{Deserialize, Serialize};
-use core::fmt;
-use std::error::Error;
+use std::collections::HashMap;
 use std::fs::File;
 
-
-// GENERIC ERROR
-/******************************************************************************/
-/// Generic error to satisfy Box<dyn Err>
-#[derive(Debug)]
-struct HostError (String);
-
-impl fmt::Display for HostError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Host error: {}", self.0)
-    }
-}
-
-impl Error for HostError {}
-
 // HOSTS STRUCTS
 /******************************************************************************/
 /// Host object representing each host to be monitored from `hosts.json`
@@ -27,11 +14,74 @@ impl Error for HostError {}
 pub struct Host {
     pub hostname: String,
     pub services: Vec<String>,
+    /// How to reach the host; defaults to `systemctl -H`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transport: Option<TransportKind>,
+    /// Remote user for `ssh` and `systemctl -H`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// SSH port, `ssh` transport only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// Private key passed to `ssh -i`, `ssh` transport only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity_file: Option<String>,
+    /// Seconds allowed for read-only `systemctl` calls on this host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// Seconds allowed for `start`, `stop` and `restart` on this host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub control_timeout_secs: Option<u64>,
+    /// Free-form labels, e.g. `web` or `eu-west`, for bulk actions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Automatic restart policy, by service name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub remediation: HashMap<String, RemediationPolicy>,
 }
 
 // IMPL `Host`
 //------------------------------------------------------------------------------
 impl Host {
+    /// `Host` with no services and default transport settings
+    pub fn new(hostname: &str) -> Host {
+        Host {
+            hostname: hostname.to_string(),
+            services: vec![],
+            transport: None,
+            user: None,
+            port: None,
+            identity_file: None,
+            timeout_secs: None,
+            control_timeout_secs: None,
+            tags: vec![],
+            remediation: HashMap::new(),
+        }
+    }
+
+    /// `SvcmonError::Validation` unless `hostname` and `user` are safe to put
+    /// on an `ssh` or `systemctl -H` command line: not empty, not starting
+    /// with `-` and free of whitespace
+    pub fn validate(&self) -> Result<()> {
+        let fields = [("hostname", Some(&self.hostname)), ("user", self.user.as_ref())];
+        for (field, value) in fields {
+            let value = match value {
+                Some(value) => value,
+                None => continue,
+            };
+            if value.is_empty()
+                || value.starts_with('-')
+                || value.chars().any(|c| c.is_whitespace() || c.is_control())
+            {
+                return Err(SvcmonError::Validation(format!(
+                    "bad {} '{}'; it must not be empty, start with '-' or contain whitespace",
+                    field, value
+                )));
+            }
+        }
+        Ok(())
+    }
+
     /// Add a service to `services` Only called by `Hosts` object
     pub fn add_service(&mut self, service: &str) {
         self.services.push(service.to_string());
@@ -58,12 +108,21 @@ pub struct Hosts {
 impl Hosts {
     /// Initialize `Hosts` from file
     /// Reads hosts from 'hosts.json'
-    pub fn init_from_file() -> Result<Hosts, Box<dyn Error>> {
+    pub fn init_from_file() -> Result<Hosts> {
         let config_file = &read_config_file().hostsfile;
 
-        let file = File::open(&config_file)?;
+        let file = File::open(config_file).map_err(|e| {
+            SvcmonError::Config(format!("Could not open '{}': {}", config_file, e))
+        })?;
 
-        let hosts: Hosts = serde_json::from_reader(file)?;
+        let hosts: Hosts = serde_json::from_reader(file).map_err(|e| {
+            SvcmonError::Config(format!("Could not parse '{}': {}", config_file, e))
+        })?;
+        for host in &hosts.hosts {
+            host.validate().map_err(|e| {
+                SvcmonError::Config(format!("Could not load '{}': {}", config_file, e))
+            })?;
+        }
 
         Ok(hosts)
     }
@@ -72,6 +131,15 @@ impl Hosts {
         self.hosts
     }
 
+    /// Keep only the hosts for which `keep` returns `true`; `keep` may also
+    /// trim a host's services
+    pub fn retain_hosts<F>(&mut self, keep: F)
+    where
+        F: FnMut(&mut Host) -> bool,
+    {
+        self.hosts.retain_mut(keep);
+    }
+
     /// Add a `Host` to `Hosts`
     pub fn add_host(&mut self, host: Host) {
         self.hosts.push(host);
@@ -85,6 +153,13 @@ impl Hosts {
         }
     }
 
+    /// `service` is listed under `hostname`
+    pub fn has_service(&self, hostname: &str, service: &str) -> bool {
+        self.hosts
+            .iter()
+            .any(|host| host.hostname == hostname && host.services.iter().any(|svc| svc == service))
+    }
+
     /// Return a `&mut Host` by hostname
     pub fn get_host_by_hostname(&mut self, hostname: &str) -> Option<&mut Host> {
         match self.hosts.iter_mut()
@@ -98,22 +173,20 @@ impl Hosts {
     pub fn remove_host_by_hostname(&mut self, hostname: &str) -> Option<Host>{
         match self.hosts.iter_mut()
             .position(|host| host.hostname == hostname) {
-                Some(pos) => {
-                    return Some(self.hosts.remove(pos));
-                }
+                Some(pos) => Some(self.hosts.remove(pos)),
                 None => None,
             }
     }
 
     /// Add service to a `Host`
-    pub fn add_service(&mut self, hostname: &str, service: &str) -> Result<(), Box<dyn Error>> {
+    pub fn add_service(&mut self, hostname: &str, service: &str) -> Result<()> {
         match self.get_host_by_hostname(hostname) {
             Some(host) => {
                 host.services.push(service.to_string());
             }
             None => { 
                 let err_msg = format!("Could not find host by hostname, '{}'.", hostname);
-                return Err(Box::new(HostError(err_msg)));
+                return Err(SvcmonError::NotFound(err_msg));
             }
         };
 
@@ -121,7 +194,7 @@ impl Hosts {
     }
 
     /// Remove service from `Host`
-    pub fn remove_service(&mut self, hostname: &str, service: &str) -> Result<(), Box<dyn Error>> {
+    pub fn remove_service(&mut self, hostname: &str, service: &str) -> Result<()> {
         match self.get_host_by_hostname(hostname) {
             Some(host) => {
                 match host.services.iter_mut().position(|svc| svc == service) {
@@ -130,45 +203,96 @@ impl Hosts {
                     },
                     None => {
                         let err_str = format!("Service, '{}', does not exists for host, '{}'.", service, hostname);
-                        return Err(Box::new(HostError(err_str)));
+                        return Err(SvcmonError::NotFound(err_str));
                     },
                 };
             },
             None => {
                 let err_msg = format!("Could not find host by hostname, '{}'.", hostname);
-                return Err(Box::new(HostError(err_msg)));
+                return Err(SvcmonError::NotFound(err_msg));
             }
         };
         Ok(())
     }
 
-    pub fn write_to_file(self) -> Result<(), Box<dyn Error>> {
+    pub fn write_to_file(self) -> Result<()> {
         let config_file = &read_config_file().hostsfile;
-        let file = File::create(&config_file)?;
+        let file = File::create(config_file).map_err(|e| {
+            SvcmonError::Config(format!("Could not write '{}': {}", config_file, e))
+        })?;
 
-        serde_json::to_writer_pretty(file, &self)?;
+        serde_json::to_writer_pretty(file, &self).map_err(|e| {
+            SvcmonError::Config(format!("Could not write '{}': {}", config_file, e))
+        })?;
 
         Ok(())
     }
 }
 
+/// Look up a `Host` in `hosts.json` by hostname
+/// Hosts that are not listed get default transport settings
+pub fn lookup_host(hostname: &str) -> Result<Host> {
+    let mut hosts = Hosts::init_from_file()?;
+    match hosts.get_host_by_hostname(hostname) {
+        Some(host) => Ok(host.clone()),
+        None => Ok(Host::new(hostname)),
+    }
+}
+
 /// Add `Host` to `hosts.json`
-pub fn add_host_to_hosts_file(host: Host) -> Result<(), Box<dyn Error>> {
+pub fn add_host_to_hosts_file(host: Host) -> Result<()> {
+    host.validate()?;
+    let mut hosts = Hosts::init_from_file()?;
+    if hosts.get_host_by_hostname(&host.hostname).is_some() {
+        return Err(SvcmonError::Conflict(format!(
+            "Host, '{}', already exists; use /hosts/update_host to change it",
+            host.hostname
+        )));
+    }
+    hosts.add_host(host);
+    hosts.write_to_file()?;
+
+    Ok(())
+}
+
+/// Replace an existing `Host` in `hosts.json`
+pub fn update_host_in_hosts_file(host: Host) -> Result<()> {
+    host.validate()?;
     let mut hosts = Hosts::init_from_file()?;
-    hosts.remove_host(&host);
+    if hosts.remove_host_by_hostname(&host.hostname).is_none() {
+        return Err(SvcmonError::NotFound(format!(
+            "Host, '{}', does not exist",
+            host.hostname
+        )));
+    }
     hosts.add_host(host);
     hosts.write_to_file()?;
 
     Ok(())
 }
 
+/// Remove a `Host` from `hosts.json`
+pub fn rem_host_from_hosts_file(hostname: &str) -> Result<()> {
+    let mut hosts = Hosts::init_from_file()?;
+    if hosts.remove_host_by_hostname(hostname).is_none() {
+        return Err(SvcmonError::NotFound(format!(
+            "Host, '{}', does not exist",
+            hostname
+        )));
+    }
+    hosts.write_to_file()?;
+
+    Ok(())
+}
+
 /// Add a service to a `Host` and add to the `hosts.json` file
-pub fn add_svc_to_hosts_file(hostname: &str, service: &str) -> Result<(), Box<dyn Error>> {
+pub fn add_svc_to_hosts_file(hostname: &str, service: &str) -> Result<()> {
     let mut hosts = Hosts::init_from_file()?;
     let host: &mut Host = match hosts.get_host_by_hostname(hostname) {
         Some(host) => host,
         None => {
-            return Err(Box::new(HostError("No host found by that hostname".into())));
+            let err_msg = format!("Could not find host by hostname, '{}'.", hostname);
+            return Err(SvcmonError::NotFound(err_msg));
         }
     };
 
@@ -180,9 +304,25 @@ pub fn add_svc_to_hosts_file(hostname: &str, service: &str) -> Result<(), Box<dy
 }
 
 /// Remove a service from a `Host` and write to 'hosts.json' file
-pub fn rem_svc_from_hosts_file(hostname: &str, service: &str) -> Result<(), Box<dyn Error>> {
+pub fn rem_svc_from_hosts_file(hostname: &str, service: &str) -> Result<()> {
     let mut hosts = Hosts::init_from_file()?;
     hosts.remove_service(hostname, service)?;
     hosts.write_to_file()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_rejects_hostnames_ssh_would_read_as_options() {
+        assert!(Host::new("web01.example.com").validate().is_ok());
+        for hostname in ["", "-oProxyCommand=touch /tmp/x", "web 01", "web01\n"] {
+            assert!(Host::new(hostname).validate().is_err(), "{:?}", hostname);
+        }
+        let mut host = Host::new("web01");
+        host.user = Some(String::from("-oProxyCommand=id"));
+        assert!(host.validate().is_err());
+    }
+}
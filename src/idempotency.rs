@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::config::read_config_file;
+use crate::error::{Result, SvcmonError};
+use crate::response::ControlResponse;
+
+// IDEMPOTENCY CACHE
+/******************************************************************************/
+/// Responses to `start`, `stop` and `restart` kept by `(user id, idempotency
+/// key)`, so a retried request is answered without running the command again
+/// - Kept in memory; keys do not survive a restart of `runserver`
+/// - Entries expire after `idempotency_ttl_secs` under `[config.control]`
+pub struct IdempotencyCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<(i64, String), Entry>>,
+}
+
+struct Entry {
+    /// `action hostname service` the key was first used for
+    fingerprint: String,
+    state: EntryState,
+    created: Instant,
+}
+
+enum EntryState {
+    InFlight,
+    Done(Box<ControlResponse>),
+}
+
+/// Result of `IdempotencyCache::claim`
+pub enum Claim {
+    /// First use of the key; run the command, then `complete` or `release`
+    New,
+    /// The key already finished; return this instead
+    Replay(Box<ControlResponse>),
+}
+
+impl Default for IdempotencyCache {
+    fn default() -> Self {
+        IdempotencyCache::new(Duration::from_secs(
+            read_config_file().control.idempotency_ttl_secs,
+        ))
+    }
+}
+
+impl IdempotencyCache {
+    pub fn new(ttl: Duration) -> Self {
+        IdempotencyCache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Reserve `key` for `fingerprint`
+    /// Reusing a key for a different request, or while the first one is still
+    /// running, is a `Conflict`
+    pub fn claim(&self, user_id: i64, key: &str, fingerprint: &str) -> Result<Claim> {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        entries.retain(|_, entry| now.duration_since(entry.created) < self.ttl);
+
+        let slot = (user_id, key.to_string());
+        if let Some(entry) = entries.get(&slot) {
+            if entry.fingerprint != fingerprint {
+                return Err(SvcmonError::Conflict(format!(
+                    "idempotency key '{}' was already used for '{}'",
+                    key, entry.fingerprint
+                )));
+            }
+            return match &entry.state {
+                EntryState::InFlight => Err(SvcmonError::Conflict(format!(
+                    "a request with idempotency key '{}' is still running",
+                    key
+                ))),
+                EntryState::Done(response) => Ok(Claim::Replay(response.clone())),
+            };
+        }
+
+        entries.insert(
+            slot,
+            Entry {
+                fingerprint: fingerprint.to_string(),
+                state: EntryState::InFlight,
+                created: now,
+            },
+        );
+        Ok(Claim::New)
+    }
+
+    /// Store the response for a claimed key
+    pub fn complete(&self, user_id: i64, key: &str, response: &ControlResponse) {
+        if let Some(entry) = self
+            .entries
+            .lock()
+            .unwrap()
+            .get_mut(&(user_id, key.to_string()))
+        {
+            entry.state = EntryState::Done(Box::new(response.clone()));
+        }
+    }
+
+    /// Forget a claimed key after a failure, so the request can be retried
+    pub fn release(&self, user_id: i64, key: &str) {
+        self.entries
+            .lock()
+            .unwrap()
+            .remove(&(user_id, key.to_string()));
+    }
+}
@@ -7,21 +7,18 @@ pub mod api;
 pub mod database;
 pub mod schema;
 pub mod collector;
-
-
-/// Generic Error to satisfy Box<dyn Error>
-use std::error::Error;
-use std::fmt;
-
-// Generic Error to satisfy Box<dyn Error>
-#[derive(Debug)]
-struct GenericError(String);
-
-impl fmt::Display for GenericError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.0)
-    }
-}
-
-impl Error for GenericError {}
-
+pub mod scheduler;
+pub mod transport;
+pub mod unit;
+pub mod error;
+pub mod retention;
+pub mod auth;
+pub mod rbac;
+pub mod audit;
+pub mod idempotency;
+pub mod bulk;
+pub mod alerts;
+pub mod notify;
+pub mod remediation;
+pub mod maintenance;
+pub mod metrics;
@@ -0,0 +1,322 @@
+use std::str::FromStr;
+
+use chrono::{Datelike, Duration, NaiveDateTime, Timelike};
+
+use crate::bulk::glob_match;
+use crate::database::crudops;
+use crate::database::models::MaintenanceWindow;
+use crate::database::DbConnection;
+use crate::error::{Result, SvcmonError};
+use crate::rbac::{owned_by, Scope};
+
+/// Longest occurrence of a recurring window
+pub const MAX_RECURRING_SECS: i64 = 7 * 86400;
+
+// CRON
+/******************************************************************************/
+/// Five-field cron expression: minute, hour, day of month, month, day of week
+/// - Each field takes `*`, numbers, ranges `a-b`, lists `a,b` and steps `/n`
+/// - Day of week runs from 0 (Sunday) to 7 (Sunday again)
+/// - As in cron, when both day fields are restricted either one may match
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Day of month or day of week was `*`
+    any_day: bool,
+}
+
+impl Cron {
+    /// `at` falls in a minute this expression matches
+    pub fn matches(&self, at: NaiveDateTime) -> bool {
+        let bit = |set: u64, value: u32| set & (1 << value) != 0;
+        let day = bit(self.days, at.day());
+        let weekday = bit(self.weekdays, at.weekday().num_days_from_sunday());
+        let day_matches = if self.any_day { day && weekday } else { day || weekday };
+
+        bit(self.minutes, at.minute())
+            && bit(self.hours, at.hour())
+            && bit(self.months, at.month())
+            && day_matches
+    }
+
+    /// Some occurrence lasting `duration` that started at or before `at` is
+    /// still running
+    pub fn covers(&self, at: NaiveDateTime, duration: Duration) -> bool {
+        let mut start = at.with_second(0).and_then(|at| at.with_nanosecond(0)).unwrap_or(at);
+        while at - start < duration {
+            if self.matches(start) {
+                return true;
+            }
+            start -= Duration::minutes(1);
+        }
+        false
+    }
+}
+
+impl FromStr for Cron {
+    type Err = SvcmonError;
+
+    fn from_str(expr: &str) -> Result<Cron> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(SvcmonError::Validation(format!(
+                "cron expression '{}' must have 5 fields: minute hour day month weekday",
+                expr
+            )));
+        }
+        let mut weekdays = parse_field(fields[4], 0, 7, "weekday")?;
+        // 7 is Sunday too
+        if weekdays & (1 << 7) != 0 {
+            weekdays |= 1;
+        }
+        Ok(Cron {
+            minutes: parse_field(fields[0], 0, 59, "minute")?,
+            hours: parse_field(fields[1], 0, 23, "hour")?,
+            days: parse_field(fields[2], 1, 31, "day")?,
+            months: parse_field(fields[3], 1, 12, "month")?,
+            weekdays,
+            any_day: fields[2] == "*" || fields[4] == "*",
+        })
+    }
+}
+
+/// Bit set of the values `field` allows between `min` and `max`
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<u64> {
+    let invalid = || {
+        SvcmonError::Validation(format!(
+            "bad cron {} '{}'; expected values from {} to {}",
+            name, field, min, max
+        ))
+    };
+    let number = |value: &str| -> Result<u32> {
+        match value.parse::<u32>() {
+            Ok(value) if (min..=max).contains(&value) => Ok(value),
+            _ => Err(invalid()),
+        }
+    };
+
+    let mut set = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(invalid()),
+            },
+            None => (part, 1),
+        };
+        let (first, last) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((first, last)) => (number(first)?, number(last)?),
+                // `5/15` runs from 5 to the end of the field
+                None if step > 1 => (number(range)?, max),
+                None => (number(range)?, number(range)?),
+            },
+        };
+        if first > last {
+            return Err(invalid());
+        }
+        for value in (first..=last).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+// WINDOWS
+/******************************************************************************/
+/// `window` is running at `now`
+/// Recurring windows with a cron expression that no longer parses never are
+pub fn is_active(window: &MaintenanceWindow, now: NaiveDateTime) -> bool {
+    match (&window.cron, window.duration_secs) {
+        (Some(cron), Some(duration_secs)) => cron
+            .parse::<Cron>()
+            .is_ok_and(|cron| {
+                cron.covers(now, Duration::seconds(duration_secs.min(MAX_RECURRING_SECS)))
+            }),
+        _ => match (window.starts_at, window.ends_at) {
+            (Some(starts_at), Some(ends_at)) => starts_at <= now && now < ends_at,
+            _ => false,
+        },
+    }
+}
+
+/// The windows running at one moment, for checking many services at once
+pub struct Maintenance {
+    /// Each window with what its `group_accts_id` owns
+    active: Vec<(MaintenanceWindow, Option<Scope>)>,
+}
+
+impl Maintenance {
+    /// Windows running at `now`
+    pub fn load(conn: &mut DbConnection, now: NaiveDateTime) -> Result<Maintenance> {
+        let mut active = vec![];
+        for window in crudops::get_maintenance_windows(conn, Some(now))? {
+            if !is_active(&window, now) {
+                continue;
+            }
+            let group = window
+                .group_accts_id
+                .map(|id| owned_by(conn, id))
+                .transpose()?;
+            active.push((window, group));
+        }
+        Ok(Maintenance { active })
+    }
+
+    /// Some running window applies to `service` on `hostname`
+    pub fn covers(&self, hostname: &str, service: &str) -> bool {
+        self.active.iter().any(|(window, group)| {
+            window
+                .hostname
+                .as_deref()
+                .is_none_or(|pattern| glob_match(pattern, hostname))
+                && window
+                    .service
+                    .as_deref()
+                    .is_none_or(|pattern| glob_match(pattern, service))
+                && group
+                    .as_ref()
+                    .is_none_or(|group| group.allows_service(hostname, service))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use std::collections::{HashMap, HashSet};
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn cron(expr: &str) -> Cron {
+        expr.parse().unwrap()
+    }
+
+    fn window(hostname: Option<&str>, service: Option<&str>) -> MaintenanceWindow {
+        MaintenanceWindow {
+            id: 1,
+            reason: String::from("patching"),
+            hostname: hostname.map(String::from),
+            service: service.map(String::from),
+            group_accts_id: None,
+            starts_at: Some(at(13, 0, 0)),
+            ends_at: Some(at(13, 2, 0)),
+            cron: None,
+            duration_secs: None,
+            created_by: String::from("admin"),
+            created_at: at(1, 0, 0),
+        }
+    }
+
+    #[test]
+    fn restricted_day_fields_match_either() {
+        // The 13th, or any Friday
+        let cron = cron("0 0 13 * 5");
+        assert!(cron.matches(at(13, 0, 0)), "Tuesday the 13th");
+        assert!(cron.matches(at(16, 0, 0)), "Friday the 16th");
+        assert!(!cron.matches(at(14, 0, 0)), "Wednesday the 14th");
+    }
+
+    #[test]
+    fn wildcard_day_field_defers_to_the_other() {
+        assert!(cron("0 0 * * 5").matches(at(16, 0, 0)));
+        assert!(!cron("0 0 * * 5").matches(at(13, 0, 0)));
+        assert!(cron("0 0 13 * *").matches(at(13, 0, 0)));
+        assert!(!cron("0 0 13 * *").matches(at(16, 0, 0)));
+    }
+
+    #[test]
+    fn seven_is_sunday() {
+        assert!(cron("0 0 * * 7").matches(at(18, 0, 0)));
+        assert!(cron("0 0 * * 0").matches(at(18, 0, 0)));
+        assert!(!cron("0 0 * * 7").matches(at(17, 0, 0)));
+        assert!(cron("0 0 * * 5-7").matches(at(18, 0, 0)));
+    }
+
+    #[test]
+    fn step_from_a_start_value() {
+        let cron = cron("5/15 * * * *");
+        for minute in [5, 20, 35, 50] {
+            assert!(cron.matches(at(13, 1, minute)), "minute {}", minute);
+        }
+        for minute in [0, 6, 15, 55] {
+            assert!(!cron.matches(at(13, 1, minute)), "minute {}", minute);
+        }
+    }
+
+    #[test]
+    fn lists_ranges_and_wildcard_steps() {
+        let cron = cron("*/30 1-3,22 * * *");
+        assert!(cron.matches(at(13, 2, 30)));
+        assert!(cron.matches(at(13, 22, 0)));
+        assert!(!cron.matches(at(13, 4, 0)));
+        assert!(!cron.matches(at(13, 2, 15)));
+    }
+
+    #[test]
+    fn rejects_bad_expressions() {
+        for expr in [
+            "* * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "a * * * *",
+        ] {
+            assert!(expr.parse::<Cron>().is_err(), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn covers_until_the_duration_ends() {
+        let cron = cron("0 2 * * *");
+        let hour = Duration::hours(1);
+        assert!(cron.covers(at(13, 2, 0), hour));
+        assert!(cron.covers(at(13, 2, 59), hour));
+        assert!(!cron.covers(at(13, 3, 0), hour));
+        assert!(!cron.covers(at(13, 1, 59), hour));
+        // An occurrence that started the day before is still running
+        assert!(cron.covers(at(14, 1, 0), Duration::hours(24)));
+    }
+
+    #[test]
+    fn one_off_windows_run_from_start_to_end() {
+        let window = window(None, None);
+        assert!(is_active(&window, at(13, 0, 0)));
+        assert!(is_active(&window, at(13, 1, 59)));
+        assert!(!is_active(&window, at(13, 2, 0)));
+    }
+
+    #[test]
+    fn maintenance_matches_globs_and_groups() {
+        let owned: HashMap<String, Option<HashSet<String>>> = HashMap::from([(
+            String::from("db1"),
+            Some(HashSet::from([String::from("postgresql")])),
+        )]);
+        let maintenance = Maintenance {
+            active: vec![
+                (window(Some("web*"), Some("nginx")), None),
+                (window(None, None), Some(Scope::Owned(owned))),
+            ],
+        };
+        assert!(maintenance.covers("web1", "nginx"));
+        assert!(!maintenance.covers("web1", "sshd"));
+        assert!(maintenance.covers("db1", "postgresql"));
+        assert!(!maintenance.covers("db1", "sshd"));
+        assert!(!maintenance.covers("db2", "postgresql"));
+    }
+}
@@ -0,0 +1,358 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+
+use crate::database::models::Status;
+use crate::rbac::Scope;
+use crate::unit::ActiveState;
+
+/// Upper bounds, in seconds, of the `svcmon_check_duration_seconds` buckets
+const DURATION_BUCKETS: [f64; 9] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// Collector metrics shared between the scheduler and the `/metrics` handler
+pub type SharedMetrics = Arc<CollectorMetrics>;
+
+// COLLECTOR METRICS
+/******************************************************************************/
+/// What the poller has done since `runserver` started
+#[derive(Default)]
+pub struct CollectorMetrics {
+    hosts: Mutex<BTreeMap<String, HostMetrics>>,
+    cycles: Mutex<CycleMetrics>,
+}
+
+#[derive(Default, Clone)]
+struct HostMetrics {
+    checks: u64,
+    failures: u64,
+    duration_sum: f64,
+    /// Checks at or under each of `DURATION_BUCKETS`
+    buckets: [u64; DURATION_BUCKETS.len()],
+    last_success: Option<NaiveDateTime>,
+}
+
+#[derive(Default, Clone)]
+struct CycleMetrics {
+    count: u64,
+    last_duration: f64,
+    last_finished: Option<NaiveDateTime>,
+}
+
+impl CollectorMetrics {
+    /// Count one check of a service on `hostname`
+    pub fn record_check(&self, hostname: &str, elapsed: Duration, success: bool) {
+        let secs = elapsed.as_secs_f64();
+        let mut hosts = self.hosts.lock().unwrap();
+        let host = hosts.entry(hostname.to_string()).or_default();
+        host.checks += 1;
+        host.duration_sum += secs;
+        for (bucket, bound) in host.buckets.iter_mut().zip(DURATION_BUCKETS) {
+            if secs <= bound {
+                *bucket += 1;
+            }
+        }
+        if success {
+            host.last_success = Some(Utc::now().naive_utc());
+        } else {
+            host.failures += 1;
+        }
+    }
+
+    /// Count one finished polling cycle
+    pub fn record_cycle(&self, elapsed: Duration) {
+        let mut cycles = self.cycles.lock().unwrap();
+        cycles.count += 1;
+        cycles.last_duration = elapsed.as_secs_f64();
+        cycles.last_finished = Some(Utc::now().naive_utc());
+    }
+}
+
+// EXPOSITION
+/******************************************************************************/
+/// Prometheus text exposition format, version 0.0.4
+#[derive(Default)]
+struct Exposition(String);
+
+impl Exposition {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.0, "# HELP {} {}", name, help);
+        let _ = writeln!(self.0, "# TYPE {} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.0.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(label, value)| format!("{}=\"{}\"", label, escape(value)))
+                .collect();
+            let _ = write!(self.0, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.0, " {}", value);
+    }
+}
+
+/// Label values escape `\`, `"` and newlines
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// `host` and `service` labels of a unit series
+fn unit_labels(status: &Status) -> [(&str, &str); 2] {
+    [("host", &status.hostname), ("service", &status.name)]
+}
+
+fn unix_secs(at: NaiveDateTime) -> f64 {
+    at.and_utc().timestamp_millis() as f64 / 1000.0
+}
+
+/// Unit gauges for `statuses`, then collector metrics for the hosts `scope`
+/// allows
+pub fn render(statuses: &[Status], collector: &CollectorMetrics, scope: &Scope) -> String {
+    let mut out = Exposition::default();
+
+    out.family(
+        "svcmon_unit_active",
+        "gauge",
+        "1 for the unit's current ActiveState, 0 for the others.",
+    );
+    for status in statuses {
+        let current = match &status.active_status {
+            Some(state) => state.as_str(),
+            None => continue,
+        };
+        let (host, service) = (status.hostname.as_str(), status.name.as_str());
+        // Values systemd may add later get a series of their own
+        let unknown = (!ActiveState::VALUES.contains(&current)).then_some(current);
+        for state in ActiveState::VALUES.iter().copied().chain(unknown) {
+            let labels = [("host", host), ("service", service), ("state", state)];
+            out.sample("svcmon_unit_active", &labels, f64::from(u8::from(state == current)));
+        }
+    }
+
+    out.family("svcmon_unit_enabled", "gauge", "1 if the unit is enabled.");
+    for status in statuses {
+        if let Some(enabled) = status.enabled {
+            let labels = unit_labels(status);
+            out.sample("svcmon_unit_enabled", &labels, f64::from(u8::from(enabled)));
+        }
+    }
+
+    out.family("svcmon_unit_restarts_total", "counter", "NRestarts reported by systemd.");
+    for status in statuses {
+        if let Some(restarts) = status.n_restarts {
+            let labels = unit_labels(status);
+            out.sample("svcmon_unit_restarts_total", &labels, f64::from(restarts));
+        }
+    }
+
+    out.family("svcmon_unit_memory_bytes", "gauge", "MemoryCurrent reported by systemd.");
+    for status in statuses {
+        if let Some(bytes) = status.memory_current {
+            let labels = unit_labels(status);
+            out.sample("svcmon_unit_memory_bytes", &labels, bytes as f64);
+        }
+    }
+
+    out.family(
+        "svcmon_unit_last_check_timestamp_seconds",
+        "gauge",
+        "When the unit was last checked.",
+    );
+    for status in statuses {
+        if let Some(last_check) = status.last_check {
+            let labels = unit_labels(status);
+            out.sample("svcmon_unit_last_check_timestamp_seconds", &labels, unix_secs(last_check));
+        }
+    }
+
+    let hosts: Vec<(String, HostMetrics)> = collector
+        .hosts
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(hostname, _)| scope.allows_host(hostname))
+        .map(|(hostname, host)| (hostname.clone(), host.clone()))
+        .collect();
+
+    out.family(
+        "svcmon_check_duration_seconds",
+        "histogram",
+        "Time taken by each poller check, by host.",
+    );
+    for (hostname, host) in &hosts {
+        for (count, bound) in host.buckets.iter().zip(DURATION_BUCKETS) {
+            let bound = bound.to_string();
+            let labels = [("host", hostname.as_str()), ("le", bound.as_str())];
+            out.sample("svcmon_check_duration_seconds_bucket", &labels, *count as f64);
+        }
+        let labels = [("host", hostname.as_str()), ("le", "+Inf")];
+        out.sample("svcmon_check_duration_seconds_bucket", &labels, host.checks as f64);
+        let labels = [("host", hostname.as_str())];
+        out.sample("svcmon_check_duration_seconds_sum", &labels, host.duration_sum);
+        out.sample("svcmon_check_duration_seconds_count", &labels, host.checks as f64);
+    }
+
+    out.family("svcmon_checks_total", "counter", "Poller checks, by host.");
+    for (hostname, host) in &hosts {
+        let labels = [("host", hostname.as_str())];
+        out.sample("svcmon_checks_total", &labels, host.checks as f64);
+    }
+
+    out.family("svcmon_check_failures_total", "counter", "Poller checks that failed, by host.");
+    for (hostname, host) in &hosts {
+        let labels = [("host", hostname.as_str())];
+        out.sample("svcmon_check_failures_total", &labels, host.failures as f64);
+    }
+
+    out.family(
+        "svcmon_last_successful_check_timestamp_seconds",
+        "gauge",
+        "When a check last succeeded, by host.",
+    );
+    for (hostname, host) in &hosts {
+        if let Some(last_success) = host.last_success {
+            out.sample(
+                "svcmon_last_successful_check_timestamp_seconds",
+                &[("host", hostname.as_str())],
+                unix_secs(last_success),
+            );
+        }
+    }
+
+    let cycles = collector.cycles.lock().unwrap().clone();
+    out.family("svcmon_poll_cycles_total", "counter", "Polling cycles completed.");
+    out.sample("svcmon_poll_cycles_total", &[], cycles.count as f64);
+    if let Some(last_finished) = cycles.last_finished {
+        out.family(
+            "svcmon_poll_cycle_duration_seconds",
+            "gauge",
+            "Duration of the last polling cycle.",
+        );
+        out.sample("svcmon_poll_cycle_duration_seconds", &[], cycles.last_duration);
+        out.family(
+            "svcmon_last_poll_timestamp_seconds",
+            "gauge",
+            "When the last polling cycle finished.",
+        );
+        out.sample("svcmon_last_poll_timestamp_seconds", &[], unix_secs(last_finished));
+    }
+
+    out.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use std::collections::HashMap;
+
+    fn status(hostname: &str, name: &str, active_status: &str) -> Status {
+        Status {
+            id: 1,
+            hostname: hostname.to_string(),
+            name: name.to_string(),
+            description: None,
+            enabled: Some(true),
+            active_status: Some(active_status.to_string()),
+            sub_state: None,
+            main_pid: None,
+            n_restarts: Some(2),
+            memory_current: None,
+            last_check: NaiveDate::from_ymd_opt(2026, 10, 13)
+                .unwrap()
+                .and_hms_opt(0, 0, 1),
+        }
+    }
+
+    fn lines(out: &str, prefix: &str) -> Vec<String> {
+        out.lines()
+            .filter(|line| line.starts_with(prefix))
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn escape_label_values() {
+        assert_eq!(escape(r#"a\b"c"#), r#"a\\b\"c"#);
+        assert_eq!(escape("two\nlines"), r"two\nlines");
+    }
+
+    #[test]
+    fn exposition_lines() {
+        let mut out = Exposition::default();
+        out.family("svcmon_x", "counter", "An x.");
+        out.sample("svcmon_x", &[], 3.0);
+        out.sample("svcmon_x", &[("host", "web\"01"), ("le", "0.5")], 1.5);
+        assert_eq!(
+            out.0,
+            "# HELP svcmon_x An x.\n# TYPE svcmon_x counter\nsvcmon_x 3\nsvcmon_x{host=\"web\\\"01\",le=\"0.5\"} 1.5\n"
+        );
+    }
+
+    #[test]
+    fn render_unit_series() {
+        let statuses = [status("web01", "nginx", "failed")];
+        let out = render(&statuses, &CollectorMetrics::default(), &Scope::All);
+
+        let active = lines(&out, "svcmon_unit_active{");
+        assert_eq!(active.len(), ActiveState::VALUES.len());
+        assert!(active.contains(&String::from(
+            "svcmon_unit_active{host=\"web01\",service=\"nginx\",state=\"failed\"} 1"
+        )));
+        assert!(active.contains(&String::from(
+            "svcmon_unit_active{host=\"web01\",service=\"nginx\",state=\"active\"} 0"
+        )));
+        assert_eq!(
+            lines(&out, "svcmon_unit_restarts_total{"),
+            ["svcmon_unit_restarts_total{host=\"web01\",service=\"nginx\"} 2"]
+        );
+        assert_eq!(
+            lines(&out, "svcmon_unit_last_check_timestamp_seconds{"),
+            ["svcmon_unit_last_check_timestamp_seconds{host=\"web01\",service=\"nginx\"} 1791849601"]
+        );
+        assert!(out.contains("# TYPE svcmon_unit_memory_bytes gauge\n"));
+        assert!(lines(&out, "svcmon_unit_memory_bytes{").is_empty());
+    }
+
+    #[test]
+    fn render_unknown_active_state_gets_its_own_series() {
+        let statuses = [status("web01", "nginx", "refreshing")];
+        let out = render(&statuses, &CollectorMetrics::default(), &Scope::All);
+        let active = lines(&out, "svcmon_unit_active{");
+        assert_eq!(active.len(), ActiveState::VALUES.len() + 1);
+        assert!(active.contains(&String::from(
+            "svcmon_unit_active{host=\"web01\",service=\"nginx\",state=\"refreshing\"} 1"
+        )));
+    }
+
+    #[test]
+    fn render_histogram_is_cumulative_and_scoped() {
+        let collector = CollectorMetrics::default();
+        collector.record_check("web01", Duration::from_millis(200), true);
+        collector.record_check("web01", Duration::from_secs(3), false);
+        collector.record_check("db01", Duration::from_secs(1), true);
+        let scope = Scope::Owned(HashMap::from([(String::from("web01"), None)]));
+        let out = render(&[], &collector, &scope);
+
+        let buckets = lines(&out, "svcmon_check_duration_seconds_bucket{");
+        assert_eq!(buckets.len(), DURATION_BUCKETS.len() + 1);
+        assert_eq!(buckets[0], "svcmon_check_duration_seconds_bucket{host=\"web01\",le=\"0.1\"} 0");
+        assert_eq!(buckets[1], "svcmon_check_duration_seconds_bucket{host=\"web01\",le=\"0.25\"} 1");
+        assert_eq!(buckets[5], "svcmon_check_duration_seconds_bucket{host=\"web01\",le=\"5\"} 2");
+        assert_eq!(buckets[9], "svcmon_check_duration_seconds_bucket{host=\"web01\",le=\"+Inf\"} 2");
+        assert_eq!(
+            lines(&out, "svcmon_check_failures_total{"),
+            ["svcmon_check_failures_total{host=\"web01\"} 1"]
+        );
+        assert!(!out.contains("db01"));
+        assert!(out.contains("\nsvcmon_poll_cycles_total 0\n"));
+        assert!(!out.contains("svcmon_poll_cycle_duration_seconds"));
+    }
+}
@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde_json::{json, Value};
+
+use crate::config::{read_config_file, ChannelConfig, ChannelKind, NotifyConfig, SmtpSecurity};
+use crate::database::models::Alert;
+use crate::error::{Result, SvcmonError};
+
+/// Longest wait between two delivery attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Notifier shared between Rocket handlers and the scheduler
+pub type SharedNotifier = Arc<Notifier>;
+
+/// Somewhere alerts can be sent
+pub trait Channel: Send + Sync {
+    /// Deliver `alert` once; `Notifier` takes care of retries
+    fn send(&self, alert: &Alert) -> Result<()>;
+}
+
+/// One-line summary used by the chat and email channels
+fn summary(alert: &Alert) -> String {
+    let severity = alert
+        .severity
+        .as_deref()
+        .map_or(String::new(), |severity| format!(" ({})", severity));
+    format!(
+        "[{}] {}{}: {}",
+        alert.state.to_uppercase(),
+        alert.rule,
+        severity,
+        alert.message
+    )
+}
+
+// WEBHOOK
+/******************************************************************************/
+/// `POST`s JSON to any URL
+pub struct WebhookChannel {
+    url: String,
+    body: Option<String>,
+    headers: HashMap<String, String>,
+    timeout: Duration,
+}
+
+impl WebhookChannel {
+    /// `body` with each `{{field}}` replaced by that field of `alert`,
+    /// JSON-escaped so it can sit inside a string literal
+    /// Placeholders that name no field are left as they are
+    fn render(template: &str, alert: &Alert) -> String {
+        let fields = match serde_json::to_value(alert) {
+            Ok(Value::Object(fields)) => fields,
+            _ => return template.to_string(),
+        };
+        let mut body = template.to_string();
+        for (name, value) in fields {
+            let text = match value {
+                Value::String(text) => text,
+                Value::Null => String::new(),
+                other => other.to_string(),
+            };
+            let escaped = serde_json::to_string(&text).unwrap_or_default();
+            let escaped = &escaped[1..escaped.len() - 1];
+            body = body.replace(&format!("{{{{{}}}}}", name), escaped);
+        }
+        body
+    }
+}
+
+impl Channel for WebhookChannel {
+    fn send(&self, alert: &Alert) -> Result<()> {
+        let body = match &self.body {
+            Some(template) => WebhookChannel::render(template, alert),
+            None => serde_json::to_string(alert).unwrap_or_default(),
+        };
+        let mut request = ureq::post(&self.url)
+            .timeout(self.timeout)
+            .set("Content-Type", "application/json");
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+        request
+            .send_string(&body)
+            .map_err(|e| SvcmonError::Notify(format!("webhook {}: {}", self.url, e)))?;
+        Ok(())
+    }
+}
+
+// SLACK
+/******************************************************************************/
+/// Slack or Mattermost incoming webhook
+pub struct SlackChannel {
+    url: String,
+    channel: Option<String>,
+    username: Option<String>,
+    timeout: Duration,
+}
+
+impl SlackChannel {
+    fn payload(&self, alert: &Alert) -> Value {
+        let mut payload = json!({ "text": summary(alert) });
+        if let Some(channel) = &self.channel {
+            payload["channel"] = json!(channel);
+        }
+        if let Some(username) = &self.username {
+            payload["username"] = json!(username);
+        }
+        payload
+    }
+}
+
+impl Channel for SlackChannel {
+    fn send(&self, alert: &Alert) -> Result<()> {
+        ureq::post(&self.url)
+            .timeout(self.timeout)
+            .set("Content-Type", "application/json")
+            .send_string(&self.payload(alert).to_string())
+            .map_err(|e| SvcmonError::Notify(format!("slack webhook: {}", e)))?;
+        Ok(())
+    }
+}
+
+// EMAIL
+/******************************************************************************/
+/// Plain-text email over SMTP
+pub struct EmailChannel {
+    mailer: SmtpTransport,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl EmailChannel {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        smtp_host: &str,
+        smtp_port: u16,
+        security: SmtpSecurity,
+        username: Option<&str>,
+        password: Option<&str>,
+        from: &str,
+        to: &[String],
+        timeout: Duration,
+    ) -> Result<EmailChannel> {
+        let smtp_error = |e: lettre::transport::smtp::Error| {
+            SvcmonError::Config(format!("SMTP server '{}': {}", smtp_host, e))
+        };
+        let builder = match security {
+            SmtpSecurity::Plain => SmtpTransport::builder_dangerous(smtp_host),
+            SmtpSecurity::Starttls => SmtpTransport::starttls_relay(smtp_host).map_err(smtp_error)?,
+            SmtpSecurity::Tls => SmtpTransport::relay(smtp_host).map_err(smtp_error)?,
+        };
+        let mut builder = builder.port(smtp_port).timeout(Some(timeout));
+        if let (Some(username), Some(password)) = (username, password) {
+            builder = builder.credentials(Credentials::new(username.to_string(), password.to_string()));
+        }
+
+        let parse = |address: &str| {
+            address
+                .parse::<Mailbox>()
+                .map_err(|e| SvcmonError::Config(format!("bad email address '{}': {}", address, e)))
+        };
+        if to.is_empty() {
+            return Err(SvcmonError::Config(String::from("email channel has no `to`")));
+        }
+        Ok(EmailChannel {
+            mailer: builder.build(),
+            from: parse(from)?,
+            to: to.iter().map(|address| parse(address)).collect::<Result<_>>()?,
+        })
+    }
+
+    fn body(alert: &Alert) -> String {
+        let resolved = alert
+            .resolved_at
+            .map_or(String::new(), |resolved_at| format!("Resolved: {}\n", resolved_at));
+        format!(
+            "{}\n\nHost: {}\nService: {}\nRule: {}\nState: {}\nFired: {}\n{}",
+            alert.message,
+            alert.hostname,
+            alert.service,
+            alert.rule,
+            alert.state,
+            alert.fired_at,
+            resolved
+        )
+    }
+}
+
+impl Channel for EmailChannel {
+    fn send(&self, alert: &Alert) -> Result<()> {
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .subject(format!("[svcmon] {}", summary(alert)));
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        let email = message
+            .body(EmailChannel::body(alert))
+            .map_err(|e| SvcmonError::Notify(format!("could not build email: {}", e)))?;
+        self.mailer
+            .send(&email)
+            .map_err(|e| SvcmonError::Notify(format!("SMTP: {}", e)))?;
+        Ok(())
+    }
+}
+
+// NOTIFIER
+/******************************************************************************/
+/// Every channel in `[[config.notify.channels]]`, with retries
+pub struct Notifier {
+    channels: Vec<(ChannelConfig, Box<dyn Channel>)>,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl Default for Notifier {
+    /// Channels that cannot be set up are reported and left out
+    fn default() -> Self {
+        Notifier::new(&read_config_file().notify)
+    }
+}
+
+impl Notifier {
+    pub fn new(config: &NotifyConfig) -> Self {
+        let timeout = Duration::from_secs(config.timeout_secs.max(1));
+        let mut channels = vec![];
+        for channel in &config.channels {
+            match build_channel(&channel.kind, timeout) {
+                Ok(built) => channels.push((channel.clone(), built)),
+                Err(e) => eprintln!(
+                    "WARNING: notification channel '{}' disabled: {}",
+                    channel.name, e
+                ),
+            }
+        }
+        Notifier {
+            channels,
+            retries: config.retries,
+            backoff: Duration::from_millis(config.backoff_ms),
+        }
+    }
+
+    /// Names of the configured channels
+    pub fn channel_names(&self) -> Vec<&str> {
+        self.channels
+            .iter()
+            .map(|(config, _)| config.name.as_str())
+            .collect()
+    }
+
+    /// Send `alert` to every channel that takes its rule, on a background
+    /// thread so slow channels do not hold up the poller
+    pub fn dispatch(self: &Arc<Self>, alert: Alert) {
+        if !self.channels.iter().any(|(config, _)| takes(config, &alert)) {
+            return;
+        }
+        let notifier = Arc::clone(self);
+        thread::spawn(move || {
+            for (config, channel) in &notifier.channels {
+                if !takes(config, &alert) {
+                    continue;
+                }
+                if let Err(e) = notifier.deliver(channel.as_ref(), &alert) {
+                    eprintln!(
+                        "WARNING: alert {} not sent to '{}': {}",
+                        alert.id, config.name, e
+                    );
+                }
+            }
+        });
+    }
+
+    /// Send `alert` to the channel named `name` right away, once, whatever
+    /// its `rules`
+    pub fn send_to(&self, name: &str, alert: &Alert) -> Result<()> {
+        match self.channels.iter().find(|(config, _)| config.name == name) {
+            Some((_, channel)) => channel.send(alert),
+            None => Err(SvcmonError::NotFound(format!(
+                "no notification channel named '{}'",
+                name
+            ))),
+        }
+    }
+
+    /// `send`, retried `retries` times with exponential backoff
+    fn deliver(&self, channel: &dyn Channel, alert: &Alert) -> Result<()> {
+        let mut wait = self.backoff;
+        let mut attempt = 0;
+        loop {
+            match channel.send(alert) {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt >= self.retries => return Err(e),
+                Err(_) => {
+                    thread::sleep(wait);
+                    wait = (wait * 2).min(MAX_BACKOFF);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+/// `config` takes alerts of this rule
+fn takes(config: &ChannelConfig, alert: &Alert) -> bool {
+    config.rules.is_empty() || config.rules.contains(&alert.rule)
+}
+
+fn build_channel(kind: &ChannelKind, timeout: Duration) -> Result<Box<dyn Channel>> {
+    Ok(match kind {
+        ChannelKind::Webhook { url, body, headers } => Box::new(WebhookChannel {
+            url: url.clone(),
+            body: body.clone(),
+            headers: headers.clone(),
+            timeout,
+        }),
+        ChannelKind::Slack {
+            url,
+            channel,
+            username,
+        } => Box::new(SlackChannel {
+            url: url.clone(),
+            channel: channel.clone(),
+            username: username.clone(),
+            timeout,
+        }),
+        ChannelKind::Email {
+            smtp_host,
+            smtp_port,
+            security,
+            username,
+            password,
+            from,
+            to,
+        } => Box::new(EmailChannel::new(
+            smtp_host,
+            *smtp_port,
+            *security,
+            username.as_deref(),
+            password.as_deref(),
+            from,
+            to,
+            timeout,
+        )?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn alert() -> Alert {
+        let fired_at = NaiveDate::from_ymd_opt(2026, 10, 13)
+            .unwrap()
+            .and_hms_opt(9, 30, 0)
+            .unwrap();
+        Alert {
+            id: 7,
+            rule: String::from("nginx-down"),
+            hostname: String::from("web01"),
+            service: String::from("nginx"),
+            severity: Some(String::from("critical")),
+            state: String::from("firing"),
+            active_state: Some(String::from("failed")),
+            message: String::from("nginx on web01 has been 'failed' for 3 check(s)"),
+            fired_at,
+            resolved_at: None,
+        }
+    }
+
+    #[test]
+    fn render_substitutes_fields() {
+        let body = WebhookChannel::render(
+            r#"{"text": "{{rule}} on {{hostname}}/{{service}} is {{state}}", "id": {{id}}}"#,
+            &alert(),
+        );
+        assert_eq!(body, r#"{"text": "nginx-down on web01/nginx is firing", "id": 7}"#);
+    }
+
+    #[test]
+    fn render_escapes_values_for_json_strings() {
+        let mut alert = alert();
+        alert.message = String::from("said \"no\"\\\nthen quit");
+        let body = WebhookChannel::render(r#"{"text": "{{message}}"}"#, &alert);
+        assert_eq!(body, r#"{"text": "said \"no\"\\\nthen quit"}"#);
+        let parsed: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(parsed["text"], json!(alert.message));
+    }
+
+    #[test]
+    fn render_leaves_unknown_fields_and_blanks_missing_values() {
+        let body = WebhookChannel::render("{{nope}} [{{resolved_at}}] {{severity}}", &alert());
+        assert_eq!(body, "{{nope}} [] critical");
+    }
+
+    #[test]
+    fn summary_names_state_rule_and_severity() {
+        let mut alert = alert();
+        assert_eq!(
+            summary(&alert),
+            "[FIRING] nginx-down (critical): nginx on web01 has been 'failed' for 3 check(s)"
+        );
+        alert.severity = None;
+        alert.state = String::from("resolved");
+        assert!(summary(&alert).starts_with("[RESOLVED] nginx-down: "));
+    }
+
+    #[test]
+    fn slack_payload_carries_optional_channel_and_username() {
+        let mut slack = SlackChannel {
+            url: String::from("http://localhost/hook"),
+            channel: None,
+            username: None,
+            timeout: Duration::from_secs(1),
+        };
+        assert_eq!(slack.payload(&alert()), json!({ "text": summary(&alert()) }));
+
+        slack.channel = Some(String::from("#ops"));
+        slack.username = Some(String::from("svcmon"));
+        assert_eq!(
+            slack.payload(&alert()),
+            json!({ "text": summary(&alert()), "channel": "#ops", "username": "svcmon" })
+        );
+    }
+
+    #[test]
+    fn email_body_lists_resolution_only_once_resolved() {
+        let mut alert = alert();
+        let body = EmailChannel::body(&alert);
+        assert!(body.starts_with("nginx on web01 has been 'failed' for 3 check(s)\n\nHost: web01\n"));
+        assert!(!body.contains("Resolved:"));
+
+        alert.resolved_at = Some(alert.fired_at + chrono::Duration::minutes(5));
+        assert!(EmailChannel::body(&alert).ends_with("Resolved: 2026-10-13 09:35:00\n"));
+    }
+}
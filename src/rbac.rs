@@ -0,0 +1,331 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::database::crudops;
+use crate::database::DbConnection;
+use crate::error::{Result, SvcmonError};
+use crate::hosts::Hosts;
+
+// PERMISSION
+/******************************************************************************/
+/// What a group may do, stored in `group_permissions.permission`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    /// Read statuses, events, hosts and `systemctl status`/`show`
+    View,
+    /// `start`, `stop` and `restart`
+    Operate,
+    /// Change `hosts.json`
+    EditHosts,
+    /// Everything, including users and groups
+    Admin,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 4] = [
+        Permission::View,
+        Permission::Operate,
+        Permission::EditHosts,
+        Permission::Admin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::View => "view",
+            Permission::Operate => "operate",
+            Permission::EditHosts => "edit_hosts",
+            Permission::Admin => "admin",
+        }
+    }
+
+    /// Holding `self` also grants `other`
+    /// `admin` grants everything; `operate` and `edit_hosts` include `view`
+    pub fn implies(&self, other: Permission) -> bool {
+        match self {
+            Permission::Admin => true,
+            Permission::Operate | Permission::EditHosts => {
+                *self == other || other == Permission::View
+            }
+            Permission::View => other == Permission::View,
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = SvcmonError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Permission::ALL
+            .into_iter()
+            .find(|permission| permission.as_str() == s)
+            .ok_or_else(|| {
+                SvcmonError::Validation(format!(
+                    "unknown permission '{}'; expected view, operate, edit_hosts or admin",
+                    s
+                ))
+            })
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// EFFECTIVE PERMISSIONS
+/******************************************************************************/
+/// Everything granted to a group and its ancestors
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Permissions(HashSet<Permission>);
+
+impl Permissions {
+    pub fn allows(&self, permission: Permission) -> bool {
+        self.0.iter().any(|granted| granted.implies(permission))
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.allows(Permission::Admin)
+    }
+
+    /// Only those of `scopes` that these permissions allow; used for API
+    /// tokens, which can never do more than their owner
+    pub fn restrict(&self, scopes: &[Permission]) -> Permissions {
+        Permissions(
+            scopes
+                .iter()
+                .copied()
+                .filter(|scope| self.allows(*scope))
+                .collect(),
+        )
+    }
+}
+
+/// Parse `api_tokens.scopes`, skipping unknown names
+pub fn parse_scopes(scopes: &str) -> Vec<Permission> {
+    scopes
+        .split(',')
+        .filter_map(|scope| scope.trim().parse().ok())
+        .collect()
+}
+
+/// Inverse of `parse_scopes`
+pub fn join_scopes(scopes: &[Permission]) -> String {
+    scopes
+        .iter()
+        .map(|scope| scope.as_str())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Permissions granted to `group_accts_id` or any group above it in the
+/// `parent_id` chain. A user without a group has none
+pub fn effective_permissions(
+    conn: &mut DbConnection,
+    group_accts_id: Option<i64>,
+) -> Result<Permissions> {
+    let mut permissions = Permissions::default();
+    let group_accts_id = match group_accts_id {
+        Some(id) => id,
+        None => return Ok(permissions),
+    };
+
+    for group_id in crudops::get_group_ancestry(conn, group_accts_id)? {
+        for granted in crudops::get_group_permissions(conn, group_id)? {
+            // Rows with unknown names are ignored rather than failing every request
+            if let Ok(permission) = granted.permission.parse() {
+                permissions.0.insert(permission);
+            }
+        }
+    }
+
+    Ok(permissions)
+}
+
+// SCOPE
+/******************************************************************************/
+/// Hosts and services a user may see and act on
+#[derive(Debug, Clone)]
+pub enum Scope {
+    /// Admins: everything
+    All,
+    /// Hostname to the services owned on it; `None` owns the whole host
+    Owned(HashMap<String, Option<HashSet<String>>>),
+}
+
+impl Scope {
+    /// Owns the host itself or at least one service on it
+    pub fn allows_host(&self, hostname: &str) -> bool {
+        match self {
+            Scope::All => true,
+            Scope::Owned(hosts) => hosts.contains_key(hostname),
+        }
+    }
+
+    /// Owns the whole host, not just some of its services
+    pub fn owns_host(&self, hostname: &str) -> bool {
+        match self {
+            Scope::All => true,
+            Scope::Owned(hosts) => matches!(hosts.get(hostname), Some(None)),
+        }
+    }
+
+    pub fn allows_service(&self, hostname: &str, service: &str) -> bool {
+        match self {
+            Scope::All => true,
+            Scope::Owned(hosts) => match hosts.get(hostname) {
+                Some(None) => true,
+                Some(Some(services)) => services.contains(service),
+                None => false,
+            },
+        }
+    }
+
+    /// Drop hosts outside the scope, and services outside it from the rest
+    pub fn filter_hosts(&self, hosts: &mut Hosts) {
+        hosts.retain_hosts(|host| {
+            if !self.allows_host(&host.hostname) {
+                return false;
+            }
+            host.services
+                .retain(|service| self.allows_service(&host.hostname, service));
+            true
+        });
+    }
+}
+
+/// Admins get `Scope::All`; everyone else gets what `group_hosts` assigns to
+/// their group and every group below it
+pub fn scope_for(
+    conn: &mut DbConnection,
+    group_accts_id: Option<i64>,
+    permissions: &Permissions,
+) -> Result<Scope> {
+    if permissions.is_admin() {
+        return Ok(Scope::All);
+    }
+
+    match group_accts_id {
+        Some(id) => owned_by(conn, id),
+        None => Ok(Scope::Owned(HashMap::new())),
+    }
+}
+
+/// What `group_hosts` assigns to `group_accts_id` and every group below it
+pub fn owned_by(conn: &mut DbConnection, group_accts_id: i64) -> Result<Scope> {
+    let mut owned: HashMap<String, Option<HashSet<String>>> = HashMap::new();
+    let subtree = crudops::get_group_subtree(conn, group_accts_id)?;
+    for assignment in crudops::get_group_hosts(conn, &subtree)? {
+        let services = owned
+            .entry(assignment.hostname)
+            .or_insert_with(|| Some(HashSet::new()));
+        match assignment.service {
+            // The whole host is owned, whatever else is assigned on it
+            None => *services = None,
+            Some(service) => {
+                if let Some(services) = services {
+                    services.insert(service);
+                }
+            }
+        }
+    }
+
+    Ok(Scope::Owned(owned))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn permissions(granted: &[Permission]) -> Permissions {
+        Permissions(granted.iter().copied().collect())
+    }
+
+    fn owned(hosts: &[(&str, Option<&[&str]>)]) -> Scope {
+        Scope::Owned(
+            hosts
+                .iter()
+                .map(|(hostname, services)| {
+                    let services = services.map(|services| {
+                        services.iter().map(|service| service.to_string()).collect()
+                    });
+                    (hostname.to_string(), services)
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn admin_implies_everything() {
+        for permission in Permission::ALL {
+            assert!(Permission::Admin.implies(permission), "{}", permission);
+        }
+    }
+
+    #[test]
+    fn operate_and_edit_hosts_include_view_only() {
+        for granted in [Permission::Operate, Permission::EditHosts] {
+            assert!(granted.implies(granted));
+            assert!(granted.implies(Permission::View));
+            assert!(!granted.implies(Permission::Admin));
+        }
+        assert!(!Permission::Operate.implies(Permission::EditHosts));
+        assert!(!Permission::EditHosts.implies(Permission::Operate));
+        assert!(Permission::View.implies(Permission::View));
+        assert!(!Permission::View.implies(Permission::Operate));
+    }
+
+    #[test]
+    fn parse_scopes_trims_and_skips_unknown_names() {
+        assert_eq!(
+            parse_scopes("view, operate,root,,edit_hosts"),
+            vec![Permission::View, Permission::Operate, Permission::EditHosts]
+        );
+        assert!(parse_scopes("").is_empty());
+        let scopes = [Permission::View, Permission::Admin];
+        assert_eq!(parse_scopes(&join_scopes(&scopes)), scopes);
+    }
+
+    #[test]
+    fn restrict_never_grants_more_than_the_owner() {
+        let owner = permissions(&[Permission::Operate]);
+        let token = owner.restrict(&[Permission::View, Permission::Admin]);
+        assert!(token.allows(Permission::View));
+        assert!(!token.allows(Permission::Operate));
+        assert!(!token.is_admin());
+
+        let token = permissions(&[Permission::Admin]).restrict(&[Permission::EditHosts]);
+        assert!(token.allows(Permission::EditHosts));
+        assert!(token.allows(Permission::View));
+        assert!(!token.allows(Permission::Operate));
+    }
+
+    #[test]
+    fn scope_of_whole_hosts_and_single_services() {
+        let scope = owned(&[("web1", None), ("db1", Some(&["postgresql"]))]);
+
+        assert!(scope.allows_host("web1"));
+        assert!(scope.owns_host("web1"));
+        assert!(scope.allows_service("web1", "anything"));
+
+        assert!(scope.allows_host("db1"));
+        assert!(!scope.owns_host("db1"));
+        assert!(scope.allows_service("db1", "postgresql"));
+        assert!(!scope.allows_service("db1", "sshd"));
+
+        assert!(!scope.allows_host("mail1"));
+        assert!(!scope.owns_host("mail1"));
+        assert!(!scope.allows_service("mail1", "postfix"));
+    }
+
+    #[test]
+    fn scope_all_allows_everything() {
+        assert!(Scope::All.allows_host("web1"));
+        assert!(Scope::All.owns_host("web1"));
+        assert!(Scope::All.allows_service("web1", "nginx"));
+    }
+}
@@ -0,0 +1,182 @@
+use chrono::{Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::alerts::AlertEngine;
+use crate::config::AlertCondition;
+use crate::database::crudops;
+use crate::database::models::{Alert, NewRemediationAttempt, RemediationAttempt};
+use crate::database::pool::{get_conn, DbPool};
+use crate::error::Result;
+use crate::hosts::Host;
+use crate::service::{restart_service, WaitFor};
+use crate::transport::HostTransport;
+use crate::unit::ActiveState;
+
+// POLICY
+/******************************************************************************/
+/// Automatic restarts for one service, set per service under a host's
+/// `remediation` in `hosts.json`
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct RemediationPolicy {
+    /// When to restart; `failed` unless set
+    #[serde(default = "default_condition")]
+    pub condition: AlertCondition,
+    /// Restarts allowed within `window_secs`
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+    /// Wait after the first restart before trying again; doubles with each
+    /// further attempt in the window
+    #[serde(default = "default_backoff_secs")]
+    pub backoff_secs: u64,
+    /// Upper bound of the doubled wait
+    #[serde(default = "default_max_backoff_secs")]
+    pub max_backoff_secs: u64,
+    /// Severity of the alert raised once `max_attempts` is used up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<String>,
+}
+
+fn default_condition() -> AlertCondition {
+    AlertCondition::Failed
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+fn default_window_secs() -> u64 {
+    3600
+}
+
+fn default_backoff_secs() -> u64 {
+    30
+}
+
+fn default_max_backoff_secs() -> u64 {
+    600
+}
+
+impl RemediationPolicy {
+    /// Wait required after the `attempts`-th attempt in the window
+    fn backoff(&self, attempts: usize) -> Duration {
+        let doublings = attempts.saturating_sub(1).min(32) as u32;
+        let secs = self
+            .backoff_secs
+            .saturating_mul(2u64.saturating_pow(doublings))
+            .min(self.max_backoff_secs.max(self.backoff_secs));
+        seconds(secs)
+    }
+}
+
+/// `secs` as a `chrono::Duration`, capped at ten years so date arithmetic
+/// cannot overflow
+fn seconds(secs: u64) -> Duration {
+    Duration::seconds(secs.min(10 * 365 * 86400) as i64)
+}
+
+// REMEDIATE
+/******************************************************************************/
+/// What `remediate` did on one check
+#[derive(Debug)]
+pub enum Remediation {
+    /// The condition does not hold; carries the `remediation` alert it
+    /// resolved, if any
+    Healthy(Option<Alert>),
+    /// Still backing off after the last attempt
+    Waiting,
+    /// Restarted the service
+    Attempted(RemediationAttempt),
+    /// `max_attempts` used up in the window; carries the `remediation` alert
+    /// if this check raised it
+    Exhausted(Option<Alert>),
+}
+
+/// Apply `policy` to `service` on `host`, last seen in `active_state`
+/// - Restarts through `service::restart_service`, waiting for the unit to settle, and
+///   records each attempt in `remediation_attempts`
+/// - Once the window's attempts are used up, raises a `remediation` alert
+///   through `alerts`; it resolves when the condition no longer holds
+#[allow(clippy::too_many_arguments)]
+pub fn remediate(
+    transport: &dyn HostTransport,
+    pool: &DbPool,
+    host: &Host,
+    service: &str,
+    policy: &RemediationPolicy,
+    active_state: Option<&ActiveState>,
+    alerts: Option<&AlertEngine>,
+    now: NaiveDateTime,
+) -> Result<Remediation> {
+    let recent = {
+        let conn = &mut get_conn(pool)?;
+        if !policy.condition.holds(active_state) {
+            let resolved = match alerts {
+                Some(alerts) => alerts.clear_escalation(conn, &host.hostname, service, now)?,
+                None => None,
+            };
+            return Ok(Remediation::Healthy(resolved));
+        }
+
+        let since = now - seconds(policy.window_secs);
+        let recent =
+            crudops::get_recent_remediation_attempts(conn, &host.hostname, service, since)?;
+
+        if recent.len() >= policy.max_attempts as usize {
+            let message = format!(
+                "{} on {} is still '{}' after {} restart(s) in {}s",
+                service,
+                host.hostname,
+                active_state.map_or("unknown", |state| state.as_str()),
+                recent.len(),
+                policy.window_secs
+            );
+            let raised = match alerts {
+                Some(alerts) => alerts.escalate(
+                    conn,
+                    &host.hostname,
+                    service,
+                    policy.severity.as_deref(),
+                    active_state,
+                    &message,
+                    now,
+                )?,
+                None => None,
+            };
+            return Ok(Remediation::Exhausted(raised));
+        }
+        if let Some(last) = recent.last() {
+            if now < last.attempted_at + policy.backoff(recent.len()) {
+                return Ok(Remediation::Waiting);
+            }
+        }
+        recent
+    };
+
+    // No connection is held while the restart runs
+    let outcome = restart_service(transport, host, service, WaitFor::from_config(None));
+    let (final_state, message) = match &outcome.result {
+        Ok((_, settled)) => (
+            settled.as_ref().map(|settled| settled.final_state.to_string()),
+            None,
+        ),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    let conn = &mut get_conn(pool)?;
+    let attempt = crudops::insert_remediation_attempt(
+        conn,
+        &NewRemediationAttempt {
+            hostname: &host.hostname,
+            service,
+            attempt: i32::try_from(recent.len() + 1).unwrap_or(i32::MAX),
+            outcome: if outcome.result.is_ok() { "success" } else { "failed" },
+            exit_code: outcome.exit_code,
+            final_state: final_state.as_deref(),
+            message: message.as_deref(),
+            duration_ms: i64::try_from(outcome.elapsed_ms).unwrap_or(i64::MAX),
+            attempted_at: now,
+        },
+    )?;
+    Ok(Remediation::Attempted(attempt))
+}
@@ -1,7 +1,11 @@
-use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 use crate::hosts::{Host, Hosts};
-use crate::database::models::{User, Group, Status};
+use crate::unit::UnitProperties;
+use crate::database::models::{Alert, ApiToken, AuditEntry, User, Group, Status, ServiceEvent,
+    MaintenanceWindow, RemediationAttempt};
+use crate::rbac::Permission;
+use crate::bulk::{BulkMode, TargetStatus};
+use crate::service::{CommandOutput, ControlAction};
 
 
 /// Generic Response for basic HTTP reponses
@@ -15,16 +19,92 @@ pub struct GenericResponse {
 #[derive(Serialize, Debug)]
 pub struct SystemctlResponse {
     pub status: String,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
     pub count: usize,
+    /// `stdout` lines
     pub data: Vec<String>,
+    pub stderr: Vec<String>,
+}
+
+impl From<CommandOutput> for SystemctlResponse {
+    fn from(output: CommandOutput) -> Self {
+        SystemctlResponse {
+            status: String::from("success"),
+            exit_code: output.exit_code,
+            duration_ms: output.duration_ms,
+            count: output.stdout.len(),
+            data: output.stdout,
+            stderr: output.stderr,
+        }
+    }
+}
+
+/// Response for `start`, `stop` and `restart`
+#[derive(Serialize, Debug, Clone)]
+pub struct ControlResponse {
+    pub status: String,
+    pub action: ControlAction,
+    pub hostname: String,
+    pub service: String,
+    /// Nothing was run; see `command` and `current_state`
+    pub dry_run: bool,
+    /// Stored response for a repeated idempotency key; nothing was run again
+    pub replayed: bool,
+    pub command: String,
+    /// `ActiveState` before the command, reported by dry runs
+    pub current_state: Option<String>,
+    /// `ActiveState` the unit settled in, reported with `wait`
+    pub final_state: Option<String>,
+    /// Milliseconds from sending the command until it returned or, with
+    /// `wait`, until the unit settled
+    pub elapsed_ms: Option<u64>,
+    pub exit_code: Option<i32>,
+    pub count: usize,
+    /// `stdout` lines
+    pub data: Vec<String>,
+    pub stderr: Vec<String>,
+}
+
+/// Response for `POST /api/systemctl/bulk`
+#[derive(Serialize, Debug)]
+pub struct BulkResponse {
+    /// `fail` if any target failed
+    pub status: String,
+    pub action: ControlAction,
+    pub mode: BulkMode,
+    pub dry_run: bool,
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub data: Vec<BulkTargetResult>,
+}
+
+/// One target of a bulk action
+#[derive(Serialize, Debug)]
+pub struct BulkTargetResult {
+    pub hostname: String,
+    pub service: String,
+    pub status: TargetStatus,
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub final_state: Option<String>,
+    pub elapsed_ms: Option<u64>,
+    /// Error message of a failed target
+    pub message: Option<String>,
+    /// `stdout` lines
+    pub data: Vec<String>,
+    pub stderr: Vec<String>,
 }
 
 /// Response for `systemctl show` outputs
 #[derive(Serialize, Debug)]
 pub struct SystemctlShowResponse {
     pub status: String,
+    /// Properties in `data` that are set
     pub count: usize,
-    pub data: HashMap<String, String>,
+    pub data: UnitProperties,
 }
 
 
@@ -87,7 +167,119 @@ pub struct StatusResponse {
 #[derive(Serialize, Debug)]
 pub struct StatusesResponse {
     pub status: String,
-    pub data: Vec<Status>,
+    pub data: Vec<LatestStatus>,
+}
+
+/// `Status` with whether a maintenance window covers the service right now
+#[derive(Serialize, Debug)]
+pub struct LatestStatus {
+    #[serde(flatten)]
+    pub status: Status,
+    pub in_maintenance: bool,
+}
+
+/// Response for service state changes
+/// `Vec<ServiceEvent>` object
+#[derive(Serialize, Debug)]
+pub struct EventsResponse {
+    pub status: String,
+    pub count: usize,
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+    pub data: Vec<ServiceEvent>,
+}
+
+/// Response for a newly minted API token
+/// `token` is shown here once and never again
+#[derive(Serialize, Debug)]
+pub struct TokenResponse {
+    pub status: String,
+    pub token: String,
+    pub data: ApiToken,
+}
+
+/// Response for API tokens
+/// `Vec<ApiToken>` object, without secrets
+#[derive(Serialize, Debug)]
+pub struct TokensResponse {
+    pub status: String,
+    pub count: usize,
+    pub data: Vec<ApiToken>,
+}
+
+/// Response for one page of the audit log
+/// `total` counts every matching entry, not just this page
+#[derive(Serialize, Debug)]
+pub struct AuditResponse {
+    pub status: String,
+    pub count: usize,
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+    pub data: Vec<AuditEntry>,
+}
+
+/// Response for `GET /api/alerts`
+#[derive(Serialize, Debug)]
+pub struct AlertsResponse {
+    pub status: String,
+    pub count: usize,
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+    pub data: Vec<Alert>,
+}
+
+/// Response for `GET /api/remediation/attempts`
+#[derive(Serialize, Debug)]
+pub struct RemediationAttemptsResponse {
+    pub status: String,
+    pub count: usize,
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+    pub data: Vec<RemediationAttempt>,
+}
+
+/// A maintenance window and whether it is running now
+#[derive(Serialize, Debug)]
+pub struct MaintenanceEntry {
+    #[serde(flatten)]
+    pub window: MaintenanceWindow,
+    pub active: bool,
+}
+
+/// Response for `GET /api/maintenance`
+#[derive(Serialize, Debug)]
+pub struct MaintenanceWindowsResponse {
+    pub status: String,
+    pub count: usize,
+    pub data: Vec<MaintenanceEntry>,
+}
+
+/// Response for a created maintenance window
+#[derive(Serialize, Debug)]
+pub struct MaintenanceWindowResponse {
+    pub status: String,
+    pub data: MaintenanceEntry,
+}
+
+/// Response for `POST /api/notify/test`
+#[derive(Serialize, Debug)]
+pub struct NotifyTestResponse {
+    /// `fail` if any channel failed
+    pub status: String,
+    pub data: Vec<ChannelResult>,
+}
+
+/// Outcome of one test notification
+#[derive(Serialize, Debug)]
+pub struct ChannelResult {
+    pub channel: String,
+    pub success: bool,
+    /// Why delivery failed
+    pub message: Option<String>,
 }
 
 
@@ -106,6 +298,23 @@ pub struct UserRequest {
     pub active: Option<bool>,
 }
 
+/// Body of `PATCH /api/users/<id>`; absent fields are left as they are
+#[derive(Debug, Deserialize)]
+pub struct UserUpdateRequest {
+    pub name: Option<String>,
+    pub password: Option<String>,
+    /// `null` removes the user from its group
+    #[serde(default, deserialize_with = "present")]
+    pub group_accts_id: Option<Option<i64>>,
+    pub active: Option<bool>,
+}
+
+/// Body of `PATCH /api/users/<id>/move`
+#[derive(Debug, Deserialize)]
+pub struct MoveUserRequest {
+    pub group_accts_id: Option<i64>,
+}
+
 use crate::schema::group_accts;
 
 #[derive(Insertable, Debug, Deserialize)]
@@ -115,9 +324,27 @@ pub struct GroupRequest {
     pub name: String,
 }
 
+/// Body of `PATCH /api/groups/<id>`; absent fields are left as they are
+#[derive(Debug, Deserialize)]
+pub struct GroupUpdateRequest {
+    pub name: Option<String>,
+    /// `null` makes the group a root
+    #[serde(default, deserialize_with = "present")]
+    pub parent_id: Option<Option<i64>>,
+}
+
+/// Tell an explicit `null` (`Some(None)`) apart from an absent field (`None`)
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
 use crate::schema::service_status;
 
-#[derive(Insertable, Debug, Deserialize)]
+#[derive(Insertable, Debug, Deserialize, Serialize)]
 #[diesel(table_name = service_status)]
 pub struct StatusRequest {
     pub hostname: String,
@@ -128,3 +355,19 @@ pub struct StatusRequest {
     pub last_check: Option<NaiveDateTime>,
 }
 
+
+/// Body of `POST /api/auth/login`
+#[derive(Debug, Deserialize)]
+pub struct LoginRequest {
+    pub name: String,
+    pub password: String,
+}
+
+/// Body of `POST /api/tokens/mint`
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    pub name: String,
+    pub scopes: Vec<Permission>,
+    /// Days until the token expires; never when absent
+    pub expires_in_days: Option<u32>,
+}
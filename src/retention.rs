@@ -0,0 +1,257 @@
+use chrono::{Duration, NaiveDateTime, Timelike};
+use diesel::Connection;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::config::RetentionConfig;
+use crate::database::crudops;
+use crate::database::models::{NewStatusRollup, Status};
+use crate::database::DbConnection;
+use crate::error::{Result, SvcmonError};
+
+/// `period` value of hourly rollups
+pub const HOURLY: &str = "hour";
+/// `period` value of daily rollups
+pub const DAILY: &str = "day";
+
+// RETENTION REPORT
+/******************************************************************************/
+/// What one retention run did
+#[derive(Debug, Default)]
+pub struct RetentionReport {
+    pub raw_deleted: usize,
+    pub hourly_rollups: usize,
+    pub daily_rollups: usize,
+    pub hourly_deleted: usize,
+}
+
+impl fmt::Display for RetentionReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "rolled up {} raw statuses into {} hourly and {} daily rollups; deleted {} expired hourly rollups",
+            self.raw_deleted, self.hourly_rollups, self.daily_rollups, self.hourly_deleted
+        )
+    }
+}
+
+// RETENTION JOB
+/******************************************************************************/
+/// Apply `[config.retention]`
+/// - Raw statuses older than `raw_days` are rolled up into hourly and daily
+///   summaries, one day per transaction, then deleted
+/// - Hourly rollups older than `hourly_days` are deleted
+pub fn run_retention(
+    conn: &mut DbConnection,
+    config: &RetentionConfig,
+    now: NaiveDateTime,
+) -> Result<RetentionReport> {
+    let mut report = RetentionReport::default();
+    let mut last_states = LastStates::new();
+    let cutoff = start_of_day(now - Duration::days(i64::from(config.raw_days)));
+
+    // Each pass deletes the oldest day, so this always makes progress
+    while let Some(oldest) = crudops::get_oldest_last_check(conn)? {
+        if oldest >= cutoff {
+            break;
+        }
+        let day = start_of_day(oldest);
+        let next_day = day + Duration::days(1);
+
+        conn.transaction::<_, SvcmonError, _>(|conn| {
+            let statuses = crudops::get_statuses_between(conn, day, next_day)?;
+            for rollup in rollups(&statuses, HOURLY, start_of_hour, &last_states) {
+                crudops::add_to_rollup(conn, &rollup)?;
+                report.hourly_rollups += 1;
+            }
+            for rollup in rollups(&statuses, DAILY, start_of_day, &last_states) {
+                crudops::add_to_rollup(conn, &rollup)?;
+                report.daily_rollups += 1;
+            }
+            remember_last_states(&statuses, &mut last_states);
+            report.raw_deleted += crudops::delete_statuses_between(conn, day, next_day)?;
+            Ok(())
+        })?;
+    }
+
+    if config.hourly_days > 0 {
+        let before = start_of_day(now - Duration::days(i64::from(config.hourly_days)));
+        report.hourly_deleted = crudops::delete_rollups_before(conn, HOURLY, before)?;
+    }
+
+    Ok(report)
+}
+
+// ROLLUPS
+/******************************************************************************/
+/// `active_status` of the last sample rolled up, by host and service
+type LastStates = HashMap<(String, String), Option<String>>;
+
+/// Running totals for one host, service and period
+#[derive(Default)]
+struct Bucket {
+    samples: i32,
+    active_samples: i32,
+    flaps: i32,
+}
+
+/// Summarise `statuses` (ordered by host, service, then time) into one rollup
+/// per host, service and period start
+/// A flap is any change of `active_status` between consecutive samples; one
+/// that straddles two periods counts in the later, and the first sample of a
+/// service is compared with its entry in `carried`, if any
+fn rollups<'a>(
+    statuses: &'a [Status],
+    period: &'static str,
+    period_start: fn(NaiveDateTime) -> NaiveDateTime,
+    carried: &LastStates,
+) -> Vec<NewStatusRollup<'a>> {
+    let mut buckets: BTreeMap<(&str, &str, NaiveDateTime), Bucket> = BTreeMap::new();
+    let mut previous: Option<&Status> = None;
+    for status in statuses {
+        let last_check = match status.last_check {
+            Some(last_check) => last_check,
+            None => continue,
+        };
+        let last_status = match previous {
+            Some(previous)
+                if previous.hostname == status.hostname && previous.name == status.name =>
+            {
+                Some(&previous.active_status)
+            }
+            _ => carried.get(&(status.hostname.clone(), status.name.clone())),
+        };
+        previous = Some(status);
+
+        let key = (status.hostname.as_str(), status.name.as_str(), period_start(last_check));
+        let bucket = buckets.entry(key).or_default();
+        if last_status.is_some_and(|last_status| *last_status != status.active_status) {
+            bucket.flaps += 1;
+        }
+        bucket.samples += 1;
+        if status.active_status.as_deref() == Some("active") {
+            bucket.active_samples += 1;
+        }
+    }
+
+    buckets
+        .into_iter()
+        .map(|((hostname, name, period_start), bucket)| NewStatusRollup {
+            hostname,
+            name,
+            period,
+            period_start,
+            samples: bucket.samples,
+            active_samples: bucket.active_samples,
+            percent_active: 100.0 * f64::from(bucket.active_samples) / f64::from(bucket.samples),
+            flaps: bucket.flaps,
+        })
+        .collect()
+}
+
+/// Record the last `active_status` of each service in `statuses`
+fn remember_last_states(statuses: &[Status], last_states: &mut LastStates) {
+    for status in statuses.iter().filter(|status| status.last_check.is_some()) {
+        last_states.insert(
+            (status.hostname.clone(), status.name.clone()),
+            status.active_status.clone(),
+        );
+    }
+}
+
+fn start_of_day(datetime: NaiveDateTime) -> NaiveDateTime {
+    datetime.date().and_hms_opt(0, 0, 0).unwrap_or(datetime)
+}
+
+fn start_of_hour(datetime: NaiveDateTime) -> NaiveDateTime {
+    datetime
+        .date()
+        .and_hms_opt(datetime.hour(), 0, 0)
+        .unwrap_or(datetime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn status(name: &str, hour: u32, minute: u32, active_status: &str) -> Status {
+        Status {
+            id: 0,
+            hostname: String::from("web1"),
+            name: String::from(name),
+            description: None,
+            enabled: Some(true),
+            active_status: Some(String::from(active_status)),
+            sub_state: None,
+            main_pid: None,
+            n_restarts: None,
+            memory_current: None,
+            last_check: NaiveDate::from_ymd_opt(2026, 10, 13)
+                .unwrap()
+                .and_hms_opt(hour, minute, 0),
+        }
+    }
+
+    #[test]
+    fn flaps_across_hours_count_in_the_later_hour() {
+        let statuses = [
+            status("nginx", 1, 50, "active"),
+            status("nginx", 2, 10, "failed"),
+            status("nginx", 2, 20, "failed"),
+        ];
+        let hourly = rollups(&statuses, HOURLY, start_of_hour, &LastStates::new());
+        assert_eq!(hourly.len(), 2);
+        assert_eq!(hourly[0].flaps, 0);
+        assert_eq!(hourly[1].flaps, 1);
+        let daily = rollups(&statuses, DAILY, start_of_day, &LastStates::new());
+        assert_eq!(daily[0].flaps, 1);
+    }
+
+    #[test]
+    fn first_sample_is_compared_with_the_carried_state() {
+        let statuses = [status("nginx", 0, 5, "failed"), status("sshd", 0, 5, "active")];
+        let mut carried = LastStates::new();
+        remember_last_states(&[status("nginx", 23, 55, "active")], &mut carried);
+        let hourly = rollups(&statuses, HOURLY, start_of_hour, &carried);
+        assert_eq!(hourly[0].name, "nginx");
+        assert_eq!(hourly[0].flaps, 1);
+        assert_eq!(hourly[1].name, "sshd");
+        assert_eq!(hourly[1].flaps, 0);
+    }
+
+    #[test]
+    fn rollups_per_service_and_period() {
+        let statuses = [
+            status("nginx", 1, 0, "active"),
+            status("nginx", 1, 30, "failed"),
+            status("nginx", 1, 45, "active"),
+            status("nginx", 2, 0, "active"),
+            status("sshd", 1, 10, "active"),
+        ];
+        let hourly = rollups(&statuses, HOURLY, start_of_hour, &LastStates::new());
+        assert_eq!(hourly.len(), 3);
+        let first = &hourly[0];
+        assert_eq!((first.name, first.period), ("nginx", HOURLY));
+        assert_eq!(first.period_start, start_of_hour(statuses[0].last_check.unwrap()));
+        assert_eq!((first.samples, first.active_samples, first.flaps), (3, 2, 2));
+        assert!((first.percent_active - 200.0 / 3.0).abs() < 1e-9);
+        assert_eq!((hourly[1].samples, hourly[1].flaps), (1, 0));
+        assert_eq!((hourly[2].name, hourly[2].samples), ("sshd", 1));
+
+        let daily = rollups(&statuses, DAILY, start_of_day, &LastStates::new());
+        assert_eq!(daily.len(), 2);
+        assert_eq!((daily[0].samples, daily[0].active_samples, daily[0].flaps), (4, 3, 2));
+        assert_eq!(daily[0].percent_active, 75.0);
+    }
+
+    #[test]
+    fn statuses_without_a_check_time_are_skipped() {
+        let mut unchecked = status("nginx", 0, 0, "failed");
+        unchecked.last_check = None;
+        let statuses = [unchecked, status("nginx", 1, 0, "active")];
+        let hourly = rollups(&statuses, HOURLY, start_of_hour, &LastStates::new());
+        assert_eq!(hourly.len(), 1);
+        assert_eq!((hourly[0].samples, hourly[0].flaps), (1, 0));
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use chrono::Utc;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::tokio::sync::{watch, Semaphore};
+use rocket::tokio::task::{self, JoinHandle};
+use rocket::tokio::time::{self, MissedTickBehavior};
+use rocket::tokio;
+use rocket::{Orbit, Rocket};
+
+use crate::alerts::AlertEngine;
+use crate::collector::update_service_status;
+use crate::config::{read_config_file, CollectorConfig, RetentionConfig};
+use crate::error::SvcmonError;
+use crate::database::pool::{get_conn, DbPool};
+use crate::hosts::Hosts;
+use crate::maintenance::Maintenance;
+use crate::metrics::SharedMetrics;
+use crate::notify::SharedNotifier;
+use crate::retention::run_retention;
+use crate::transport::SharedTransport;
+
+// SCHEDULER FAIRING
+/******************************************************************************/
+/// Background jobs run inside `runserver`
+/// - Poller: refreshes `service_status` for every host and service in
+///   `hosts.json` on the interval set in `[config.collector]`
+/// - Alerts: the poller runs every check through `[config.alerts]` rules
+/// - Remediation: the poller applies each service's policy from `hosts.json`
+/// - Metrics: the poller counts its checks and cycles for `/metrics`
+/// - Retention: rolls up and prunes history per `[config.retention]`
+/// - Started on liftoff, stopped when Rocket shuts down
+#[derive(Default)]
+pub struct Scheduler {
+    stop_tx: Mutex<Option<watch::Sender<bool>>>,
+    handles: Mutex<Vec<JoinHandle<()>>>,
+}
+
+#[rocket::async_trait]
+impl Fairing for Scheduler {
+    fn info(&self) -> Info {
+        Info {
+            name: "Service status scheduler",
+            kind: Kind::Liftoff | Kind::Shutdown,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let config = read_config_file();
+        let transport = match rocket.state::<SharedTransport>() {
+            Some(transport) => Arc::clone(transport),
+            None => {
+                eprintln!("WARNING: scheduler not started, no `SharedTransport` managed");
+                return;
+            }
+        };
+        let pool = match rocket.state::<DbPool>() {
+            Some(pool) => pool.clone(),
+            None => {
+                eprintln!("WARNING: scheduler not started, no `DbPool` managed");
+                return;
+            }
+        };
+
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let mut handles = vec![];
+        if config.collector.enabled {
+            let collector = config.collector.clone();
+            let alerts = if config.alerts.enabled {
+                let mut engine = AlertEngine::new(config.alerts.rules.clone());
+                match rocket.state::<SharedNotifier>() {
+                    Some(notifier) => engine = engine.with_notifier(Arc::clone(notifier)),
+                    None => {
+                        eprintln!("WARNING: alerts will not be sent, no `SharedNotifier` managed")
+                    }
+                }
+                Some(Arc::new(engine))
+            } else {
+                println!("Alerting disabled in `svcmon.toml`");
+                None
+            };
+            let metrics = rocket.state::<SharedMetrics>().map(Arc::clone);
+            if metrics.is_none() {
+                eprintln!("WARNING: collector metrics off, no `SharedMetrics` managed");
+            }
+            handles.push(tokio::spawn(run(
+                collector,
+                transport,
+                pool.clone(),
+                alerts,
+                metrics,
+                stop_rx.clone(),
+            )));
+        } else {
+            println!("Poller disabled in `svcmon.toml`");
+        }
+        if config.retention.enabled {
+            let retention = config.retention.clone();
+            handles.push(tokio::spawn(run_retention_loop(retention, pool, stop_rx)));
+        } else {
+            println!("Retention disabled in `svcmon.toml`");
+        }
+
+        *self.stop_tx.lock().unwrap() = Some(stop_tx);
+        *self.handles.lock().unwrap() = handles;
+    }
+
+    async fn on_shutdown(&self, _rocket: &Rocket<Orbit>) {
+        if let Some(stop_tx) = self.stop_tx.lock().unwrap().take() {
+            let _ = stop_tx.send(true);
+        }
+        // Take the handles out first so the lock is not held across `.await`
+        let handles: Vec<JoinHandle<()>> = self.handles.lock().unwrap().drain(..).collect();
+        for handle in handles {
+            if let Err(e) = handle.await {
+                eprintln!("WARNING: scheduler did not stop cleanly: {}", e);
+            }
+        }
+    }
+}
+
+// POLLING LOOP
+/******************************************************************************/
+/// Poll every host every `interval_secs`, counted from the start of each
+/// cycle, until told to stop
+/// A cycle that overruns the interval is followed straight away by the next
+async fn run(
+    config: CollectorConfig,
+    transport: SharedTransport,
+    pool: DbPool,
+    alerts: Option<Arc<AlertEngine>>,
+    metrics: Option<SharedMetrics>,
+    mut stop_rx: watch::Receiver<bool>,
+) {
+    let mut ticks = time::interval(Duration::from_secs(config.interval_secs.max(1)));
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => {}
+            _ = stop_rx.changed() => break,
+        }
+
+        let started = Instant::now();
+        poll_hosts(&config, &transport, &pool, &alerts, &metrics, &stop_rx).await;
+        if let Some(metrics) = &metrics {
+            metrics.record_cycle(started.elapsed());
+        }
+    }
+}
+
+/// Run one polling cycle over `hosts.json`
+/// Each host gets its own semaphore so a slow host cannot starve the others
+async fn poll_hosts(
+    config: &CollectorConfig,
+    transport: &SharedTransport,
+    pool: &DbPool,
+    alerts: &Option<Arc<AlertEngine>>,
+    metrics: &Option<SharedMetrics>,
+    stop_rx: &watch::Receiver<bool>,
+) {
+    let hosts = match Hosts::init_from_file() {
+        Ok(hosts) => hosts,
+        Err(e) => {
+            eprintln!("WARNING: scheduler could not read hosts file: {}", e);
+            return;
+        }
+    };
+
+    // One read of the windows serves every check in the cycle
+    let loaded = {
+        let pool = pool.clone();
+        task::spawn_blocking(move || {
+            let conn = &mut get_conn(&pool)?;
+            Maintenance::load(conn, Utc::now().naive_utc())
+        })
+        .await
+    };
+    let maintenance = match loaded {
+        Ok(Ok(maintenance)) => Some(Arc::new(maintenance)),
+        Ok(Err(e)) => {
+            eprintln!("WARNING: maintenance windows not checked this cycle: {}", e);
+            None
+        }
+        Err(e) => {
+            eprintln!("WARNING: loading maintenance windows panicked: {}", e);
+            None
+        }
+    };
+
+    // Services dropped from the hosts file are never checked again, so
+    // their alerts are resolved here
+    if let Some(alerts) = alerts {
+        let alerts = Arc::clone(alerts);
+        let hosts = hosts.clone();
+        let pool = pool.clone();
+        let resolved = task::spawn_blocking(move || {
+            let conn = &mut get_conn(&pool)?;
+            let resolved = alerts.resolve_removed(conn, &hosts, Utc::now().naive_utc())?;
+            for alert in resolved {
+                alerts.notify(alert);
+            }
+            Ok::<_, SvcmonError>(())
+        })
+        .await;
+        match resolved {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => eprintln!("WARNING: alerts on removed services not resolved: {}", e),
+            Err(e) => eprintln!("WARNING: resolving alerts on removed services panicked: {}", e),
+        }
+    }
+
+    let jitter_max = Duration::from_millis(config.jitter_ms);
+    let mut checks = vec![];
+    for host in hosts.get_hosts() {
+        let permits = Arc::new(Semaphore::new(config.max_concurrent_per_host.max(1)));
+        let host = Arc::new(host);
+        for service in host.services.clone() {
+            let host = Arc::clone(&host);
+            let transport = Arc::clone(transport);
+            let pool = pool.clone();
+            let alerts = alerts.clone();
+            let maintenance = maintenance.clone();
+            let metrics = metrics.clone();
+            let permits = Arc::clone(&permits);
+            let mut stop_rx = stop_rx.clone();
+            checks.push(tokio::spawn(async move {
+                tokio::select! {
+                    _ = time::sleep(jitter(jitter_max)) => {}
+                    _ = stop_rx.changed() => return,
+                }
+                let _permit = match permits.acquire_owned().await {
+                    Ok(permit) => permit,
+                    Err(_) => return,
+                };
+                let result = task::spawn_blocking(move || {
+                    let alerts = alerts.as_deref();
+                    let maintenance = maintenance.as_deref();
+                    let transport = transport.as_ref();
+                    let started = Instant::now();
+                    let result = update_service_status(
+                        transport,
+                        &pool,
+                        &host,
+                        &service,
+                        alerts,
+                        maintenance,
+                        true,
+                    );
+                    if let Some(metrics) = &metrics {
+                        metrics.record_check(&host.hostname, started.elapsed(), result.is_ok());
+                    }
+                    if let Err(e) = result {
+                        eprintln!(
+                            "WARNING: did not update {} on {}: {}",
+                            service, host.hostname, e
+                        );
+                    }
+                })
+                .await;
+                if let Err(e) = result {
+                    eprintln!("WARNING: status check panicked: {}", e);
+                }
+            }));
+        }
+    }
+
+    for check in checks {
+        let _ = check.await;
+    }
+}
+
+// RETENTION LOOP
+/******************************************************************************/
+/// Apply the retention policy, then sleep until the next run or until told to
+/// stop
+async fn run_retention_loop(
+    config: RetentionConfig,
+    pool: DbPool,
+    mut stop_rx: watch::Receiver<bool>,
+) {
+    let interval = Duration::from_secs(config.interval_secs.max(60));
+    loop {
+        let pool = pool.clone();
+        let retention = config.clone();
+        let result = task::spawn_blocking(move || {
+            let conn = &mut get_conn(&pool)?;
+            run_retention(conn, &retention, Utc::now().naive_utc())
+        })
+        .await;
+        match result {
+            Ok(Ok(report)) => println!("Retention: {}", report),
+            Ok(Err(e)) => eprintln!("WARNING: retention failed: {}", e),
+            Err(e) => eprintln!("WARNING: retention panicked: {}", e),
+        }
+
+        tokio::select! {
+            _ = time::sleep(interval) => {}
+            _ = stop_rx.changed() => break,
+        }
+    }
+}
+
+/// Random delay in `[0, max)` used to spread checks across the interval
+fn jitter(max: Duration) -> Duration {
+    let max_ms = max.as_millis() as u64;
+    if max_ms == 0 {
+        return Duration::ZERO;
+    }
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(nanos);
+    Duration::from_millis(hasher.finish() % max_ms)
+}
@@ -1,12 +1,138 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    alerts (id) {
+        id -> Bigint,
+        rule -> Text,
+        hostname -> Text,
+        service -> Text,
+        severity -> Nullable<Text>,
+        state -> Text,
+        active_state -> Nullable<Text>,
+        message -> Text,
+        fired_at -> Timestamp,
+        resolved_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    api_tokens (id) {
+        id -> Bigint,
+        user_id -> Bigint,
+        name -> Text,
+        prefix -> Text,
+        secret_hash -> Text,
+        scopes -> Text,
+        expires_at -> Nullable<Timestamp>,
+        last_used -> Nullable<Timestamp>,
+        revoked -> Bool,
+        created -> Timestamp,
+    }
+}
+
+diesel::table! {
+    audit_log (id) {
+        id -> Bigint,
+        actor -> Text,
+        user_id -> Nullable<Bigint>,
+        token_id -> Nullable<Bigint>,
+        action -> Text,
+        hostname -> Nullable<Text>,
+        service -> Nullable<Text>,
+        payload -> Nullable<Text>,
+        output -> Nullable<Text>,
+        exit_code -> Nullable<Integer>,
+        success -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     group_accts (id) {
         id -> Bigint,
         parent_id -> Nullable<Bigint>,
         name -> Text,
-        created -> Datetime,
-        modified -> Datetime,
+        created -> Timestamp,
+        modified -> Timestamp,
+    }
+}
+
+diesel::table! {
+    group_hosts (id) {
+        id -> Bigint,
+        group_accts_id -> Bigint,
+        hostname -> Text,
+        service -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    group_permissions (id) {
+        id -> Bigint,
+        group_accts_id -> Bigint,
+        permission -> Text,
+    }
+}
+
+diesel::table! {
+    maintenance_windows (id) {
+        id -> Bigint,
+        reason -> Text,
+        hostname -> Nullable<Text>,
+        service -> Nullable<Text>,
+        group_accts_id -> Nullable<Bigint>,
+        starts_at -> Nullable<Timestamp>,
+        ends_at -> Nullable<Timestamp>,
+        cron -> Nullable<Text>,
+        duration_secs -> Nullable<Bigint>,
+        created_by -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    remediation_attempts (id) {
+        id -> Bigint,
+        hostname -> Text,
+        service -> Text,
+        attempt -> Integer,
+        outcome -> Text,
+        exit_code -> Nullable<Integer>,
+        final_state -> Nullable<Text>,
+        message -> Nullable<Text>,
+        duration_ms -> Bigint,
+        attempted_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    service_current_status (hostname, name) {
+        hostname -> Text,
+        name -> Text,
+        status_id -> Bigint,
+        description -> Nullable<Text>,
+        enabled -> Nullable<Bool>,
+        active_status -> Nullable<Text>,
+        sub_state -> Nullable<Text>,
+        main_pid -> Nullable<Bigint>,
+        n_restarts -> Nullable<Integer>,
+        memory_current -> Nullable<Bigint>,
+        last_check -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    service_events (id) {
+        id -> Bigint,
+        hostname -> Text,
+        name -> Text,
+        old_active_status -> Nullable<Text>,
+        new_active_status -> Nullable<Text>,
+        old_enabled -> Nullable<Bool>,
+        new_enabled -> Nullable<Bool>,
+        old_sub_state -> Nullable<Text>,
+        new_sub_state -> Nullable<Text>,
+        changed_at -> Timestamp,
     }
 }
 
@@ -18,7 +144,25 @@ diesel::table! {
         description -> Nullable<Text>,
         enabled -> Nullable<Bool>,
         active_status -> Nullable<Text>,
-        last_check -> Nullable<Datetime>,
+        sub_state -> Nullable<Text>,
+        main_pid -> Nullable<Bigint>,
+        n_restarts -> Nullable<Integer>,
+        memory_current -> Nullable<Bigint>,
+        last_check -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    service_status_rollups (id) {
+        id -> Bigint,
+        hostname -> Text,
+        name -> Text,
+        period -> Text,
+        period_start -> Timestamp,
+        samples -> Integer,
+        active_samples -> Integer,
+        percent_active -> Double,
+        flaps -> Integer,
     }
 }
 
@@ -29,15 +173,29 @@ diesel::table! {
         password -> Text,
         group_accts_id -> Nullable<Bigint>,
         active -> Bool,
-        created -> Datetime,
-        modified -> Datetime,
+        created -> Timestamp,
+        modified -> Timestamp,
     }
 }
 
+diesel::joinable!(api_tokens -> users (user_id));
+diesel::joinable!(group_hosts -> group_accts (group_accts_id));
+diesel::joinable!(group_permissions -> group_accts (group_accts_id));
+diesel::joinable!(maintenance_windows -> group_accts (group_accts_id));
 diesel::joinable!(users -> group_accts (group_accts_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    alerts,
+    api_tokens,
+    audit_log,
     group_accts,
+    group_hosts,
+    group_permissions,
+    maintenance_windows,
+    remediation_attempts,
+    service_current_status,
+    service_events,
     service_status,
+    service_status_rollups,
     users,
 );
@@ -1,182 +1,409 @@
+use rocket::tokio::task;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::error::Error;
-use std::{io, process};
-
-
-/// Convert Vec<u8> to Vec<String>
-/// Since the return of `output()` is io::Result<Vec<u8>>, we convert this to
-/// a vector of strings for nearly (if not every) `systemctl` call
-/// TODO: 1. out_str = String::from_utf8(out); 2. out_vec = out_str.split("\n");
-/// 3. keep all values according to specs below
-fn vec_u8_to_vec_string(out: std::process::Output) -> Vec<String> {
-    let mut lines: Vec<String> = Vec::new();
-    lines.push(String::new());
-    for elem in out.stdout.iter() {
-        if *elem == 10 {
-            lines.push(String::new());
-            continue;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::read_config_file;
+use crate::error::{Result, SvcmonError};
+use crate::hosts::Host;
+use crate::transport::{HostTransport, SharedTransport, TransportKind};
+use crate::unit::{ActiveState, UnitProperties};
+
+
+/// Everything `systemctl` reported for one call
+#[derive(Debug, Clone)]
+pub struct CommandOutput {
+    /// e.g. `` `systemctl restart nginx` on web01 ``, for error messages
+    pub command: String,
+    /// `None` when `systemctl` was killed by a signal
+    pub exit_code: Option<i32>,
+    pub stdout: Vec<String>,
+    pub stderr: Vec<String>,
+    pub duration_ms: u64,
+}
+
+impl CommandOutput {
+    /// `SvcmonError::CommandFailed` unless the exit code is one of `ok`
+    /// The error message carries `stderr`, or `stdout` if that is empty
+    pub fn expect_exit(self, ok: &[i32]) -> Result<Self> {
+        match self.exit_code {
+            Some(code) if ok.contains(&code) => Ok(self),
+            code => {
+                let status = code.map_or(String::from("was killed by a signal"), |code| {
+                    format!("exited with {}", code)
+                });
+                let detail = if self.stderr.is_empty() {
+                    &self.stdout
+                } else {
+                    &self.stderr
+                };
+                Err(SvcmonError::CommandFailed(format!(
+                    "{} {}: {}",
+                    self.command,
+                    status,
+                    detail.join(" ")
+                )))
+            }
         }
-        // Skip non-ASCII characters
-        else if *elem > 127 {
-            continue;
+    }
+
+    /// First line of `stdout`, for `is-active` and `is-enabled`, whose exit
+    /// code only repeats the answer
+    /// A call that printed nothing fails as `expect_exit` would
+    pub fn answer(self) -> Result<String> {
+        match self.stdout.first() {
+            Some(answer) => Ok(answer.clone()),
+            None => self.expect_exit(&[]).map(|_| String::new()),
         }
-        let line = lines
-            .last_mut()
-            .expect("Error getting last string in vector");
-        line.push(char::from(*elem));
     }
-    // Remove white spaces
-    let mut lines: Vec<String> = lines.iter().map(|s| s.trim().to_string()).collect();
-    lines.retain(|line| !line.is_empty());
-    lines
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct SystemCtlStatus {
-    status: String,
-    stdout: String,
-    stderr: String,
+/// Split output into trimmed, non-empty lines
+/// Invalid UTF-8 is replaced with U+FFFD rather than dropped
+fn output_lines(bytes: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Run `systemctl <args>` on `host` and keep its exit code, output and timing
+fn run(
+    transport: &dyn HostTransport,
+    host: &Host,
+    args: &[&str],
+    timeout: Duration,
+) -> Result<CommandOutput> {
+    let started = Instant::now();
+    let out = transport.systemctl(host, args, timeout)?;
+
+    Ok(CommandOutput {
+        command: format!("`systemctl {}` on {}", args.join(" "), host.hostname),
+        exit_code: out.status.code(),
+        stdout: output_lines(&out.stdout),
+        stderr: output_lines(&out.stderr),
+        duration_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Timeout for read-only calls on `host`
+fn query_timeout(host: &Host) -> Duration {
+    let secs = host
+        .timeout_secs
+        .unwrap_or(read_config_file().transport.timeout_secs);
+    Duration::from_secs(secs)
+}
+
+/// Timeout for `start`, `stop` and `restart` on `host`
+fn control_timeout(host: &Host) -> Duration {
+    let secs = host
+        .control_timeout_secs
+        .unwrap_or(read_config_file().transport.control_timeout_secs);
+    Duration::from_secs(secs)
 }
 
 /// List unit files for given host
 /// To retrieve only enabled unit-files, pass `enabled_only == true`
-pub fn list_unit_files(host: &str, enabled_only: Option<bool>) -> io::Result<Vec<String>> {
-    let out = process::Command::new("systemctl")
-        .arg("-H")
-        .arg(&host)
-        .arg("list-unit-files")
-        .output()?;
-
-    let mut lines = vec_u8_to_vec_string(out);
+pub fn list_unit_files(
+    transport: &dyn HostTransport,
+    host: &Host,
+    enabled_only: Option<bool>,
+) -> Result<CommandOutput> {
+    let mut out = run(transport, host, &["list-unit-files"], query_timeout(host))?;
 
     let enabled_only = enabled_only.unwrap_or(true);
     if enabled_only {
-        lines = lines
-            .into_iter()
-            .filter(|line| line.contains("enabled"))
-            .collect();
+        out.stdout.retain(|line| line.contains("enabled"));
     }
 
-    Ok(lines)
+    Ok(out)
 }
 
 /// Get status of a given service on a given host
-pub fn get_status<'a, 'b>(host: &'b str, service: &'a str) -> io::Result<Vec<String>> {
-    let out = process::Command::new("systemctl")
-        .arg("-H")
-        .arg(&host)
-        .arg("status")
-        .arg(&service)
-        .arg("-l")
-        .output()?;
-
-    let mut lines = vec_u8_to_vec_string(out);
-    if !lines.is_empty() {
-        lines[0] = format!("Description: {}", lines[0]);
+/// NOTE: `systemctl status` exits with 3 for stopped units; see
+/// `expect_exit`
+pub fn get_status(
+    transport: &dyn HostTransport,
+    host: &Host,
+    service: &str,
+) -> Result<CommandOutput> {
+    let mut out = run(transport, host, &["status", service, "-l"], query_timeout(host))?;
+
+    if let Some(first) = out.stdout.first_mut() {
+        *first = format!("Description: {}", first);
     }
 
-    Ok(lines)
+    Ok(out)
 }
 
 /// See if service is active
-pub fn active_status<'a, 'b>(host: &'a str, service: &'b str) -> Result<String, Box<dyn Error>> {
-    let out = process::Command::new("systemctl")
-        .arg("-H")
-        .arg(&host)
-        .arg("is-active")
-        .arg(&service)
-        .output()?;
-
-    let mut stdout_str = String::new();
-    for elem in out.stdout {
-        stdout_str.push(char::from(elem));
-    }
-    stdout_str = stdout_str.trim().to_string();
-
-    Ok(stdout_str)
+/// NOTE: `systemctl is-active` exits non-zero for every state but `active`;
+/// see `CommandOutput::answer`
+pub fn active_status(
+    transport: &dyn HostTransport,
+    host: &Host,
+    service: &str,
+) -> Result<CommandOutput> {
+    run(transport, host, &["is-active", service], query_timeout(host))
 }
 
 /// See if service is enabled
-pub fn enabled_status<'a, 'b>(host: &'a str, service: &'b str) -> Result<String, Box<dyn Error>> {
-    let out = process::Command::new("systemctl")
-        .arg("-H")
-        .arg(&host)
-        .arg("is-enabled")
-        .arg(&service)
-        .output()?;
-
-    let mut stdout_str = String::new();
-    for elem in out.stdout {
-        stdout_str.push(char::from(elem));
-    }
-    stdout_str = stdout_str.trim().to_string();
-
-    Ok(stdout_str)
+/// NOTE: `systemctl is-enabled` exits non-zero unless the unit is enabled;
+/// see `CommandOutput::answer`
+pub fn enabled_status(
+    transport: &dyn HostTransport,
+    host: &Host,
+    service: &str,
+) -> Result<CommandOutput> {
+    run(transport, host, &["is-enabled", service], query_timeout(host))
 }
 
 /// Get full details of a service
-pub fn show_service<'a, 'b>(
-    host: &str,
+pub fn show_service(
+    transport: &dyn HostTransport,
+    host: &Host,
     service: &str,
-) -> Result<HashMap<String, String>, Box<dyn Error>> {
-    let out = process::Command::new("systemctl")
-        .arg("-H")
-        .arg(&host)
-        .arg("show")
-        .arg(&service)
-        .output()?;
-
-    let out = vec_u8_to_vec_string(out);
-
-    let mut service_status: HashMap<String, String> = HashMap::new();
-    for line in out {
+) -> Result<UnitProperties> {
+    let out = run(transport, host, &["show", service], query_timeout(host))?.expect_exit(&[0])?;
+
+    let mut pairs: Vec<(String, String)> = Vec::new();
+    for line in out.stdout {
         let key_val = line.split_once('=');
-        if !key_val.is_some() {
+        if key_val.is_none() {
             eprintln!(
                 "WARNING: no '=' found in `systemctl -H {} show {}`: line == {}",
-                host, service, line
+                host.hostname, service, line
             );
             continue;
         }
         let key_val = key_val.unwrap();
 
-        service_status.insert(key_val.0.to_string(), key_val.1.to_string());
+        pairs.push((key_val.0.to_string(), key_val.1.to_string()));
+    }
+    Ok(UnitProperties::from_pairs(pairs))
+}
+
+/// A state-changing `systemctl` command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ControlAction {
+    Start,
+    Stop,
+    Restart,
+}
+
+impl ControlAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ControlAction::Start => "start",
+            ControlAction::Stop => "stop",
+            ControlAction::Restart => "restart",
+        }
+    }
+
+    /// `ActiveState` the unit should settle in once the action worked
+    pub fn expected_state(&self) -> ActiveState {
+        match self {
+            ControlAction::Start | ControlAction::Restart => ActiveState::Active,
+            ControlAction::Stop => ActiveState::Inactive,
+        }
     }
-    Ok(service_status)
+}
+
+impl std::fmt::Display for ControlAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What `control_service` would run, for dry runs
+pub fn describe_command(host: &Host, service: &str, action: ControlAction) -> String {
+    let transport = host.transport.unwrap_or(TransportKind::Systemctl);
+    format!(
+        "systemctl {} {} on {} via {}",
+        action,
+        service,
+        host.hostname,
+        transport.as_str()
+    )
+}
+
+/// Run `action` on `service`
+pub fn control_service(
+    transport: &dyn HostTransport,
+    host: &Host,
+    service: &str,
+    action: ControlAction,
+) -> Result<CommandOutput> {
+    send_command(transport, host, service, action.as_str())
 }
 
 /// Send command (`start`, `stop`, `restart`) to `systemctl`
-/// NOTE: this function checks whether the command valid first
-fn send_command<'a, 'b>(
-    host: &'a str,
-    service: &'b str,
-    command: &'b str,
-) -> io::Result<Vec<String>> {
-    let out = process::Command::new("systemctl")
-        .arg("-H")
-        .arg(&host)
-        .arg(&command)
-        .arg(&service)
-        .output()?;
-
-    Ok(vec_u8_to_vec_string(out))
-}
-
-/// Restarts a service
-/// Calls helper function, `send_command`
-pub fn restart_service<'a, 'b>(host: &'a str, service: &'b str) -> io::Result<Vec<String>> {
-    send_command(host, service, "restart")
-}
-
-/// Starts a service
-/// Calls helper function, `send_command`
-pub fn stop_service<'a, 'b>(host: &'a str, service: &'b str) -> io::Result<Vec<String>> {
-    send_command(host, service, "stop")
-}
-
-/// Restarts a service
-/// Calls helper function, `send_command`
-pub fn start_service<'a, 'b>(host: &'a str, service: &'b str) -> io::Result<Vec<String>> {
-    send_command(host, service, "start")
+fn send_command(
+    transport: &dyn HostTransport,
+    host: &Host,
+    service: &str,
+    command: &str,
+) -> Result<CommandOutput> {
+    run(transport, host, &[command, service], control_timeout(host))
+}
+
+/// Where `service` settled after `wait_for_state`
+#[derive(Debug, Clone, Serialize)]
+pub struct Settled {
+    pub final_state: ActiveState,
+    /// Time spent polling
+    pub elapsed_ms: u64,
+}
+
+/// Poll `systemctl show` every `poll` until `service` reaches the
+/// `ActiveState` expected after `action`
+/// - `SvcmonError::UnitFailed` as soon as the unit is `failed`
+/// - `SvcmonError::Timeout` if it has not settled within `timeout`
+pub fn wait_for_state(
+    transport: &dyn HostTransport,
+    host: &Host,
+    service: &str,
+    action: ControlAction,
+    timeout: Duration,
+    poll: Duration,
+) -> Result<Settled> {
+    let expected = action.expected_state();
+    let started = Instant::now();
+    loop {
+        let state = show_service(transport, host, service)?.active_state;
+        let elapsed = started.elapsed();
+        match state {
+            Some(state) if state == expected => {
+                return Ok(Settled {
+                    final_state: state,
+                    elapsed_ms: elapsed.as_millis() as u64,
+                })
+            }
+            Some(ActiveState::Failed) => {
+                return Err(SvcmonError::UnitFailed(format!(
+                    "{} on {} is 'failed' after {} ({} ms)",
+                    service,
+                    host.hostname,
+                    action,
+                    elapsed.as_millis()
+                )))
+            }
+            state if elapsed >= timeout => {
+                let state = state.as_ref().map_or("unknown", |state| state.as_str());
+                return Err(SvcmonError::Timeout(format!(
+                    "{} on {} is still '{}' {} s after {}, expected '{}'",
+                    service,
+                    host.hostname,
+                    state,
+                    timeout.as_secs(),
+                    action,
+                    expected
+                )));
+            }
+            _ => thread::sleep(poll.min(timeout - elapsed)),
+        }
+    }
+}
+
+/// How long `run_action` waits for the unit to settle
+#[derive(Debug, Clone, Copy)]
+pub struct WaitFor {
+    pub timeout: Duration,
+    pub poll: Duration,
+}
+
+impl WaitFor {
+    /// `timeout_secs`, or `verify_timeout_secs` under `[config.control]`,
+    /// capped at `max_verify_timeout_secs`
+    pub fn from_config(timeout_secs: Option<u64>) -> WaitFor {
+        let config = &read_config_file().control;
+        let timeout_secs = timeout_secs
+            .unwrap_or(config.verify_timeout_secs)
+            .min(config.max_verify_timeout_secs);
+        WaitFor {
+            timeout: Duration::from_secs(timeout_secs),
+            poll: Duration::from_millis(config.verify_poll_ms.max(100)),
+        }
+    }
+}
+
+/// Result of `run_action`
+#[derive(Debug)]
+pub struct ActionOutcome {
+    /// Kept even when the action failed, for the audit log
+    pub exit_code: Option<i32>,
+    /// From sending the command until it returned or the unit settled
+    pub elapsed_ms: u64,
+    pub result: Result<(CommandOutput, Option<Settled>)>,
+}
+
+/// Run `action` on `service`, fail on a non-zero exit and, with `wait`, poll
+/// until the unit settles
+pub fn run_action(
+    transport: &dyn HostTransport,
+    host: &Host,
+    service: &str,
+    action: ControlAction,
+    wait: Option<WaitFor>,
+) -> ActionOutcome {
+    let started = Instant::now();
+    let output = control_service(transport, host, service, action);
+    let exit_code = output.as_ref().ok().and_then(|output| output.exit_code);
+    let result = output
+        .and_then(|output| output.expect_exit(&[0]))
+        .and_then(|output| match wait {
+            Some(wait) => {
+                wait_for_state(transport, host, service, action, wait.timeout, wait.poll)
+                    .map(|settled| (output, Some(settled)))
+            }
+            None => Ok((output, None)),
+        });
+
+    ActionOutcome {
+        exit_code,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        result,
+    }
+}
+
+/// Restart `service` on `host` and wait for it to settle
+pub fn restart_service(
+    transport: &dyn HostTransport,
+    host: &Host,
+    service: &str,
+    wait: WaitFor,
+) -> ActionOutcome {
+    run_action(transport, host, service, ControlAction::Restart, Some(wait))
+}
+
+/// `call` on Rocket's blocking thread pool, so a slow host does not hold up an
+/// async worker for the length of its timeout
+pub async fn spawn_call<T, F>(transport: SharedTransport, call: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&dyn HostTransport) -> Result<T> + Send + 'static,
+{
+    task::spawn_blocking(move || call(transport.as_ref()))
+        .await
+        .unwrap_or_else(|e| Err(SvcmonError::Unavailable(format!("systemctl panicked: {}", e))))
+}
+
+/// `run_action` on Rocket's blocking thread pool, so `wait` does not hold up
+/// an async worker
+pub async fn spawn_action(
+    transport: SharedTransport,
+    host: Host,
+    service: String,
+    action: ControlAction,
+    wait: Option<WaitFor>,
+) -> ActionOutcome {
+    task::spawn_blocking(move || run_action(transport.as_ref(), &host, &service, action, wait))
+        .await
+        .unwrap_or_else(|e| ActionOutcome {
+            exit_code: None,
+            elapsed_ms: 0,
+            result: Err(SvcmonError::Unavailable(format!("{} panicked: {}", action, e))),
+        })
 }
@@ -0,0 +1,368 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Read};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{self, Child, Command, Output, Stdio};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::hosts::Host;
+
+// TRANSPORT TRAIT
+/******************************************************************************/
+/// How `systemctl` reaches a host
+/// Every function in `service.rs` runs through one of these
+pub trait HostTransport: Send + Sync {
+    /// Run `systemctl <args>` against `host`, with `TZ=UTC` so timestamps
+    /// come back in UTC
+    /// Fails with `io::ErrorKind::TimedOut` if it runs longer than `timeout`
+    fn systemctl(&self, host: &Host, args: &[&str], timeout: Duration) -> io::Result<Output>;
+}
+
+/// Transport shared between Rocket handlers and the scheduler
+pub type SharedTransport = Arc<dyn HostTransport>;
+
+/// Transport selectable per host with the `transport` key in `hosts.json`
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    /// Run `systemctl` on the server itself
+    Local,
+    /// `systemctl -H [user@]host`
+    Systemctl,
+    /// `ssh [-l user] [-p port] [-i identity_file] host systemctl`
+    Ssh,
+}
+
+impl TransportKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransportKind::Local => "local",
+            TransportKind::Systemctl => "systemctl",
+            TransportKind::Ssh => "ssh",
+        }
+    }
+}
+
+// LOCAL
+/******************************************************************************/
+/// Runs `systemctl` on the machine svcmon is running on
+#[derive(Debug, Default)]
+pub struct LocalTransport;
+
+impl HostTransport for LocalTransport {
+    fn systemctl(&self, host: &Host, args: &[&str], timeout: Duration) -> io::Result<Output> {
+        let mut cmd = Command::new("systemctl");
+        cmd.env("TZ", "UTC").args(args);
+        output_with_timeout(cmd, &host.hostname, timeout)
+    }
+}
+
+// SYSTEMCTL -H
+/******************************************************************************/
+/// Uses systemd's built-in SSH transport, `systemctl -H [user@]host`
+/// NOTE: `port` and `identity_file` cannot be passed through `-H`
+#[derive(Debug, Default)]
+pub struct SystemctlTransport;
+
+impl HostTransport for SystemctlTransport {
+    fn systemctl(&self, host: &Host, args: &[&str], timeout: Duration) -> io::Result<Output> {
+        let target = match &host.user {
+            Some(user) => format!("{}@{}", user, host.hostname),
+            None => host.hostname.clone(),
+        };
+        // The local client formats the timestamps it gets over D-Bus
+        let mut cmd = Command::new("systemctl");
+        cmd.env("TZ", "UTC").arg("-H").arg(&target).args(args);
+        output_with_timeout(cmd, &host.hostname, timeout)
+    }
+}
+
+// SSH
+/******************************************************************************/
+/// Runs `systemctl` on the host over plain `ssh`, honouring the `user`, `port`
+/// and `identity_file` set for the host in `hosts.json`
+#[derive(Debug, Default)]
+pub struct SshTransport;
+
+impl SshTransport {
+    fn command(host: &Host, args: &[&str]) -> Command {
+        let mut cmd = Command::new("ssh");
+        // Never prompt for a password; fail instead
+        cmd.arg("-o").arg("BatchMode=yes");
+        if let Some(user) = &host.user {
+            cmd.arg("-l").arg(user);
+        }
+        if let Some(port) = host.port {
+            cmd.arg("-p").arg(port.to_string());
+        }
+        if let Some(identity_file) = &host.identity_file {
+            cmd.arg("-i").arg(identity_file);
+        }
+        // The remote side re-parses the command line with its shell
+        let remote: Vec<String> = args.iter().map(|arg| shell_quote(arg)).collect();
+        // `--` first, so a hostname can never be read as an option
+        cmd.arg("--")
+            .arg(&host.hostname)
+            .arg("env TZ=UTC systemctl")
+            .arg(remote.join(" "));
+        cmd
+    }
+}
+
+impl HostTransport for SshTransport {
+    fn systemctl(&self, host: &Host, args: &[&str], timeout: Duration) -> io::Result<Output> {
+        output_with_timeout(SshTransport::command(host, args), &host.hostname, timeout)
+    }
+}
+
+/// Single-quote an argument for the remote shell
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+// PER-HOST DISPATCH
+/******************************************************************************/
+/// Default transport: picks `Local`, `Systemctl` or `Ssh` from each host's
+/// `transport` key, falling back to `systemctl -H` when it is not set
+#[derive(Debug, Default)]
+pub struct ConfiguredTransport {
+    local: LocalTransport,
+    systemctl: SystemctlTransport,
+    ssh: SshTransport,
+}
+
+impl HostTransport for ConfiguredTransport {
+    fn systemctl(&self, host: &Host, args: &[&str], timeout: Duration) -> io::Result<Output> {
+        match host.transport.unwrap_or(TransportKind::Systemctl) {
+            TransportKind::Local => self.local.systemctl(host, args, timeout),
+            TransportKind::Systemctl => self.systemctl.systemctl(host, args, timeout),
+            TransportKind::Ssh => self.ssh.systemctl(host, args, timeout),
+        }
+    }
+}
+
+// TIMEOUTS
+/******************************************************************************/
+/// How often a running child is checked for exit
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Run `cmd` like `Command::output()`, but kill it if it outlives `timeout`
+/// The child gets its own process group so an `ssh` spawned by
+/// `systemctl -H` is killed along with it
+/// Its output must also be closed by the deadline: a grandchild that keeps
+/// the pipes open, such as an `ssh` control master, would otherwise hang the
+/// call after the child itself has exited
+pub fn output_with_timeout(
+    mut cmd: Command,
+    hostname: &str,
+    timeout: Duration,
+) -> io::Result<Output> {
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0);
+    let mut child = cmd.spawn()?;
+
+    // Drain the pipes on their own threads so a chatty child cannot block
+    let (sender, receiver) = mpsc::channel();
+    read_pipe(child.stdout.take(), STDOUT, sender.clone());
+    read_pipe(child.stderr.take(), STDERR, sender);
+
+    let timed_out = || {
+        io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!(
+                "`systemctl` on '{}' timed out after {}s",
+                hostname,
+                timeout.as_secs()
+            ),
+        )
+    };
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            kill_process_group(&mut child);
+            return Err(timed_out());
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let mut output = [Vec::new(), Vec::new()];
+    for _ in 0..output.len() {
+        match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok((pipe, buf)) => output[pipe] = buf,
+            Err(_) => {
+                // Whatever is left of the group still holds a pipe open
+                kill_process_group(&mut child);
+                return Err(timed_out());
+            }
+        }
+    }
+    let [stdout, stderr] = output;
+    Ok(Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+/// Index of each pipe in the output `read_pipe` sends
+const STDOUT: usize = 0;
+const STDERR: usize = 1;
+
+/// Read `pipe` to the end on its own thread and send `(index, output)`
+/// The thread outlives `output_with_timeout` if the pipe is never closed
+fn read_pipe<R: Read + Send + 'static>(
+    pipe: Option<R>,
+    index: usize,
+    sender: mpsc::Sender<(usize, Vec<u8>)>,
+) {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        // The receiver is gone once the call has timed out
+        let _ = sender.send((index, buf));
+    });
+}
+
+/// SIGKILL the child's whole process group and reap the child
+fn kill_process_group(child: &mut Child) {
+    let pgid = child.id() as libc::pid_t;
+    // SAFETY: `kill` has no memory-safety requirements; the group was created
+    // by `process_group(0)` above, and its id is not reused while any member
+    // is alive, so this cannot hit an unrelated group
+    unsafe {
+        libc::kill(-pgid, libc::SIGKILL);
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+// FAKE
+/******************************************************************************/
+/// Canned result returned by `FakeTransport`
+#[derive(Debug, Clone, Default)]
+pub struct FakeResponse {
+    pub exit_code: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// In-memory transport for tests and demos
+/// Responses are keyed by hostname and the space-joined `systemctl` arguments;
+/// unknown commands return exit code 1 with empty output
+#[derive(Debug, Default)]
+pub struct FakeTransport {
+    responses: Mutex<HashMap<(String, String), FakeResponse>>,
+    calls: Mutex<Vec<(String, String)>>,
+}
+
+impl FakeTransport {
+    /// Set the response for `systemctl <args>` on `hostname`
+    pub fn respond(&self, hostname: &str, args: &[&str], response: FakeResponse) {
+        self.responses
+            .lock()
+            .unwrap()
+            .insert((hostname.to_string(), args.join(" ")), response);
+    }
+
+    /// Every `(hostname, args)` pair run so far, oldest first
+    pub fn calls(&self) -> Vec<(String, String)> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+impl HostTransport for FakeTransport {
+    fn systemctl(&self, host: &Host, args: &[&str], _timeout: Duration) -> io::Result<Output> {
+        let key = (host.hostname.clone(), args.join(" "));
+        self.calls.lock().unwrap().push(key.clone());
+
+        let response = self
+            .responses
+            .lock()
+            .unwrap()
+            .get(&key)
+            .cloned()
+            .unwrap_or(FakeResponse {
+                exit_code: 1,
+                ..Default::default()
+            });
+
+        Ok(Output {
+            // Wait status layout: exit code lives in the second byte
+            status: process::ExitStatus::from_raw(response.exit_code << 8),
+            stdout: response.stdout.into_bytes(),
+            stderr: response.stderr.into_bytes(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_quote_wraps_and_escapes_single_quotes() {
+        assert_eq!(shell_quote("nginx"), "'nginx'");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("a b; rm -rf /"), "'a b; rm -rf /'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote("$(id)`id`"), "'$(id)`id`'");
+    }
+
+    #[test]
+    fn output_keeps_exit_code_stdout_and_stderr() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo out; echo err >&2; exit 3"]);
+        let output = output_with_timeout(cmd, "localhost", Duration::from_secs(5)).unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+    }
+
+    #[test]
+    fn output_held_open_by_a_grandchild_times_out() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "sleep 30 & echo started"]);
+        let started = Instant::now();
+        let err = output_with_timeout(cmd, "localhost", Duration::from_millis(300)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn ssh_command_quotes_every_remote_argument() {
+        let host: Host = serde_json::from_value(serde_json::json!({
+            "hostname": "web1",
+            "services": ["nginx"],
+            "user": "svcmon",
+            "port": 2222,
+        }))
+        .unwrap();
+        let cmd = SshTransport::command(&host, &["show", "x'; reboot"]);
+        let args: Vec<&str> = cmd.get_args().map(|arg| arg.to_str().unwrap()).collect();
+        assert_eq!(cmd.get_program(), "ssh");
+        assert_eq!(
+            args,
+            [
+                "-o",
+                "BatchMode=yes",
+                "-l",
+                "svcmon",
+                "-p",
+                "2222",
+                "--",
+                "web1",
+                "env TZ=UTC systemctl",
+                r"'show' 'x'\''; reboot'",
+            ]
+        );
+    }
+}
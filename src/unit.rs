@@ -0,0 +1,350 @@
+use chrono::{Duration, FixedOffset, NaiveDateTime};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+
+// UNIT STATE ENUMS
+/******************************************************************************/
+/// Declare an enum for one of systemd's state properties
+/// Values systemd adds in the future are kept in `Other` rather than dropped
+macro_rules! unit_state {
+    ($(#[$meta:meta])* $name:ident { $($variant:ident => $value:literal),+ $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+        #[serde(into = "String")]
+        pub enum $name {
+            $($variant,)+
+            Other(String),
+        }
+
+        impl $name {
+            /// Every value systemd documents, in declaration order
+            pub const VALUES: &[&str] = &[$($value),+];
+
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $value,)+
+                    $name::Other(value) => value,
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(value: &str) -> Self {
+                match value {
+                    $($value => $name::$variant,)+
+                    other => $name::Other(other.to_string()),
+                }
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> String {
+                value.as_str().to_string()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}", self.as_str())
+            }
+        }
+    };
+}
+
+unit_state!(
+    /// `ActiveState`: high-level state of the unit
+    ActiveState {
+        Active => "active",
+        Reloading => "reloading",
+        Inactive => "inactive",
+        Failed => "failed",
+        Activating => "activating",
+        Deactivating => "deactivating",
+        Maintenance => "maintenance",
+    }
+);
+
+unit_state!(
+    /// `SubState`: unit-type specific state
+    SubState {
+        Running => "running",
+        Exited => "exited",
+        Dead => "dead",
+        Failed => "failed",
+        Start => "start",
+        StartPre => "start-pre",
+        StartPost => "start-post",
+        Stop => "stop",
+        StopSigterm => "stop-sigterm",
+        StopSigkill => "stop-sigkill",
+        StopPost => "stop-post",
+        Reload => "reload",
+        AutoRestart => "auto-restart",
+        Listening => "listening",
+        Waiting => "waiting",
+        Elapsed => "elapsed",
+        Mounted => "mounted",
+        Plugged => "plugged",
+        Active => "active",
+    }
+);
+
+unit_state!(
+    /// `LoadState`: whether the unit file was found and parsed
+    LoadState {
+        Loaded => "loaded",
+        NotFound => "not-found",
+        BadSetting => "bad-setting",
+        Error => "error",
+        Masked => "masked",
+        Stub => "stub",
+        Merged => "merged",
+    }
+);
+
+unit_state!(
+    /// `UnitFileState`: install state of the unit file
+    UnitFileState {
+        Enabled => "enabled",
+        EnabledRuntime => "enabled-runtime",
+        Linked => "linked",
+        LinkedRuntime => "linked-runtime",
+        Alias => "alias",
+        Masked => "masked",
+        MaskedRuntime => "masked-runtime",
+        Static => "static",
+        Indirect => "indirect",
+        Disabled => "disabled",
+        Generated => "generated",
+        Transient => "transient",
+        Bad => "bad",
+    }
+);
+
+impl UnitFileState {
+    /// Unit starts at boot
+    pub fn is_enabled(&self) -> bool {
+        matches!(self, UnitFileState::Enabled | UnitFileState::EnabledRuntime)
+    }
+}
+
+// UNIT PROPERTIES
+/******************************************************************************/
+/// Parsed output of `systemctl show <service>`
+/// Properties without a dedicated field are kept, unparsed, in `other`
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct UnitProperties {
+    pub id: Option<String>,
+    pub description: Option<String>,
+    pub load_state: Option<LoadState>,
+    pub active_state: Option<ActiveState>,
+    pub sub_state: Option<SubState>,
+    pub unit_file_state: Option<UnitFileState>,
+    pub active_enter_timestamp: Option<NaiveDateTime>,
+    pub exec_main_start_timestamp: Option<NaiveDateTime>,
+    #[serde(rename = "MainPID")]
+    pub main_pid: Option<u32>,
+    #[serde(rename = "NRestarts")]
+    pub n_restarts: Option<u32>,
+    pub memory_current: Option<u64>,
+    #[serde(rename = "CPUUsageNSec")]
+    pub cpu_usage_nsec: Option<u64>,
+    #[serde(flatten)]
+    pub other: HashMap<String, String>,
+}
+
+impl UnitProperties {
+    /// Build from the `key=value` pairs printed by `systemctl show`
+    pub fn from_pairs<I>(pairs: I) -> UnitProperties
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut props = UnitProperties::default();
+        for (key, value) in pairs {
+            match key.as_str() {
+                "Id" => props.id = non_empty(&value),
+                "Description" => props.description = non_empty(&value),
+                "LoadState" => props.load_state = non_empty(&value).map(|v| v.as_str().into()),
+                "ActiveState" => props.active_state = non_empty(&value).map(|v| v.as_str().into()),
+                "SubState" => props.sub_state = non_empty(&value).map(|v| v.as_str().into()),
+                "UnitFileState" => {
+                    props.unit_file_state = non_empty(&value).map(|v| v.as_str().into())
+                }
+                "ActiveEnterTimestamp" => props.active_enter_timestamp = parse_timestamp(&value),
+                "ExecMainStartTimestamp" => {
+                    props.exec_main_start_timestamp = parse_timestamp(&value)
+                }
+                "MainPID" => props.main_pid = parse_number(&value),
+                "NRestarts" => props.n_restarts = parse_number(&value),
+                "MemoryCurrent" => props.memory_current = parse_number(&value),
+                "CPUUsageNSec" => props.cpu_usage_nsec = parse_number(&value),
+                _ => {
+                    props.other.insert(key, value);
+                }
+            }
+        }
+        props
+    }
+
+    /// Properties that are set, counting every entry in `other`
+    pub fn count(&self) -> usize {
+        let typed = [
+            self.id.is_some(),
+            self.description.is_some(),
+            self.load_state.is_some(),
+            self.active_state.is_some(),
+            self.sub_state.is_some(),
+            self.unit_file_state.is_some(),
+            self.active_enter_timestamp.is_some(),
+            self.exec_main_start_timestamp.is_some(),
+            self.main_pid.is_some(),
+            self.n_restarts.is_some(),
+            self.memory_current.is_some(),
+            self.cpu_usage_nsec.is_some(),
+        ];
+        typed.iter().filter(|set| **set).count() + self.other.len()
+    }
+
+    /// `UnitFileState` is `enabled` or `enabled-runtime`
+    pub fn is_enabled(&self) -> bool {
+        self.unit_file_state
+            .as_ref()
+            .map(|state| state.is_enabled())
+            .unwrap_or(false)
+    }
+}
+
+/// Treat an empty value as unset
+fn non_empty(value: &str) -> Option<String> {
+    match value.trim() {
+        "" => None,
+        value => Some(value.to_string()),
+    }
+}
+
+/// Parse a counter, treating `[not set]` and `u64::MAX` (systemd's "infinity")
+/// as unset
+fn parse_number<T: std::str::FromStr>(value: &str) -> Option<T> {
+    if value == u64::MAX.to_string() {
+        return None;
+    }
+    value.trim().parse().ok()
+}
+
+/// Parse a systemd timestamp such as `Mon 2024-05-06 22:29:24 UTC` into UTC
+/// The transports run `systemctl` with `TZ=UTC`; numeric offsets such as
+/// `+0100` are converted too, while zone names like `CET`, which are
+/// ambiguous, leave the timestamp unset
+fn parse_timestamp(value: &str) -> Option<NaiveDateTime> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    if parts.len() != 4 {
+        return None;
+    }
+    let datetime = format!("{} {}", parts[1], parts[2]);
+    let datetime = NaiveDateTime::parse_from_str(&datetime, "%Y-%m-%d %H:%M:%S").ok()?;
+    match parts[3] {
+        "UTC" | "GMT" | "Z" => Some(datetime),
+        zone => {
+            let offset: FixedOffset = zone.parse().ok()?;
+            Some(datetime - Duration::seconds(i64::from(offset.local_minus_utc())))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn utc(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 5, 6)
+            .unwrap()
+            .and_hms_opt(hour, minute, 24)
+            .unwrap()
+    }
+
+    #[test]
+    fn timestamps_in_utc() {
+        assert_eq!(parse_timestamp("Mon 2024-05-06 22:29:24 UTC"), Some(utc(22, 29)));
+        assert_eq!(parse_timestamp("Mon 2024-05-06 22:29:24 GMT"), Some(utc(22, 29)));
+    }
+
+    #[test]
+    fn timestamps_with_offsets_are_converted() {
+        assert_eq!(parse_timestamp("Mon 2024-05-06 22:29:24 +0200"), Some(utc(20, 29)));
+        assert_eq!(
+            parse_timestamp("Mon 2024-05-06 22:29:24 -05:30"),
+            Some(utc(3, 59) + Duration::days(1))
+        );
+    }
+
+    #[test]
+    fn ambiguous_or_missing_timestamps_are_unset() {
+        assert_eq!(parse_timestamp("Mon 2024-05-06 22:29:24 CET"), None);
+        assert_eq!(parse_timestamp("Mon 2024-05-06 22:29:24"), None);
+        assert_eq!(parse_timestamp("n/a"), None);
+        assert_eq!(parse_timestamp(""), None);
+    }
+
+    fn pairs(lines: &[&str]) -> Vec<(String, String)> {
+        lines
+            .iter()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn from_pairs_fills_typed_fields_and_keeps_the_rest() {
+        let props = UnitProperties::from_pairs(pairs(&[
+            "Id=nginx.service",
+            "Description=A high performance web server",
+            "LoadState=loaded",
+            "ActiveState=active",
+            "SubState=running",
+            "UnitFileState=enabled",
+            "ActiveEnterTimestamp=Mon 2024-05-06 22:29:24 UTC",
+            "MainPID=1234",
+            "NRestarts=2",
+            "Restart=on-failure",
+        ]));
+        assert_eq!(props.id.as_deref(), Some("nginx.service"));
+        assert_eq!(props.active_state, Some(ActiveState::Active));
+        assert_eq!(props.sub_state, Some(SubState::Running));
+        assert_eq!(props.load_state, Some(LoadState::Loaded));
+        assert!(props.is_enabled());
+        assert_eq!(props.active_enter_timestamp, Some(utc(22, 29)));
+        assert_eq!(props.main_pid, Some(1234));
+        assert_eq!(props.n_restarts, Some(2));
+        assert_eq!(props.other.get("Restart").map(String::as_str), Some("on-failure"));
+        assert_eq!(props.count(), 10);
+    }
+
+    #[test]
+    fn unset_and_infinite_values_are_none() {
+        let props = UnitProperties::from_pairs(pairs(&[
+            "Description=",
+            "MemoryCurrent=18446744073709551615",
+            "CPUUsageNSec=[not set]",
+            "ExecMainStartTimestamp=n/a",
+        ]));
+        assert_eq!(props.description, None);
+        assert_eq!(props.memory_current, None);
+        assert_eq!(props.cpu_usage_nsec, None);
+        assert_eq!(props.exec_main_start_timestamp, None);
+        assert!(!props.is_enabled());
+        assert_eq!(props.count(), 0);
+    }
+
+    #[test]
+    fn unknown_states_are_kept() {
+        let props = UnitProperties::from_pairs(pairs(&["ActiveState=hibernating"]));
+        assert_eq!(
+            props.active_state,
+            Some(ActiveState::Other(String::from("hibernating")))
+        );
+        assert_eq!(props.active_state.unwrap().as_str(), "hibernating");
+    }
+}